@@ -0,0 +1,71 @@
+//! Measures how quickly RPCs of various sizes can be sent to a child process.
+//!
+//! Run with `cargo bench --bench send`. Each RPC is flushed as it's sent, so every one of them goes down the pipe.
+//!
+//! The RPCs are [`RawBytes`], which cross the viaduct verbatim, so this measures the viaduct itself rather than the serialization backend.
+
+use std::{process::Command, time::Instant};
+use viaduct::{RawBytes, ViaductChild, ViaductEvent, ViaductParent};
+
+const BYTES_PER_SIZE: usize = 256 * 1024 * 1024;
+
+const SIZES: [usize; 4] = [16, 1024, 16 * 4096, 256 * 4096];
+
+/// Tells the process it was spawned by the benchmark.
+const CHILD_ARG: &str = "child";
+
+fn main() {
+	if std::env::args().nth(1).as_deref() == Some(CHILD_ARG) {
+		child();
+	} else {
+		for size in SIZES {
+			parent(size);
+		}
+	}
+}
+
+fn parent(size: usize) {
+	let (viaduct, mut child) = ViaductParent::<RawBytes<'static>, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+		.unwrap()
+		.arg(CHILD_ARG)
+		.build()
+		.unwrap();
+	let (tx, rx) = viaduct.split();
+	std::thread::spawn(move || rx.run(|_| {}));
+	tx.set_no_delay(true).unwrap();
+
+	let rpc = RawBytes::from(vec![0; size]);
+	let count = (BYTES_PER_SIZE / size).clamp(1_000, 1_000_000);
+
+	// The child responds once it has received everything sent before the request
+	let start = Instant::now();
+	for _ in 0..count {
+		tx.rpc_ref(&rpc).unwrap();
+	}
+	tx.request::<()>(()).unwrap();
+	let elapsed = start.elapsed();
+
+	println!(
+		"{size:>8} byte RPCs: {:>10.2?} each, {:>8.1} MiB/s",
+		elapsed / count as u32,
+		(size * count) as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64()
+	);
+
+	assert!(child.wait().unwrap().success());
+}
+
+fn child() {
+	let (_tx, mut rx) = unsafe { ViaductChild::<(), (), RawBytes<'static>, ()>::new().build() }.unwrap().split();
+
+	// The parent's request marks the end of the benchmark
+	let shutdown = rx.shutdown_handle().unwrap();
+	rx.run(|event| match event {
+		ViaductEvent::Rpc(rpc) => drop(std::hint::black_box(rpc)),
+		ViaductEvent::Request { responder, .. } => {
+			responder.respond(()).unwrap();
+			shutdown.shutdown();
+		}
+		_ => unreachable!(),
+	})
+	.unwrap();
+}
@@ -0,0 +1,48 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Acknowledged requests have no response
+			for i in 0..4 {
+				assert_eq!(tx.request::<()>(i).unwrap(), None);
+			}
+			println!("[PARENT] All requests were acknowledged");
+
+			tx.shutdown_send().unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let mut acked = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Request { responder, .. } => {
+					responder.ack().unwrap();
+					acked += 1;
+				}
+				_ => unreachable!(),
+			})
+			.unwrap_err();
+			assert_eq!(acked, 4);
+		}
+	}
+}
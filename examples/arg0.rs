@@ -0,0 +1,33 @@
+#[cfg(unix)]
+fn main() {
+	use std::process::Command;
+	use viaduct::{Never, ViaductChild, ViaductParent};
+
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	// Tell the parent and child apart by name, rather than by whether the viaduct can be built
+	if std::env::args().next().as_deref() == Some("viaduct-worker") {
+		let _viaduct = unsafe { ViaductChild::<Never, Never, Never, Never>::new().build() }.unwrap();
+		assert_eq!(viaduct::args().collect::<Vec<_>>(), ["viaduct-worker", "--verbose"]);
+		println!("[CHILD] Started as {:?}", viaduct::args().next().unwrap());
+		return;
+	}
+
+	let (_, mut child) = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+		.unwrap()
+		.arg0("viaduct-worker")
+		.arg("--verbose")
+		.build()
+		.unwrap();
+
+	assert!(child.wait().unwrap().success());
+}
+
+#[cfg(not(unix))]
+fn main() {
+	println!("Setting argv[0] is only supported on Unix");
+}
@@ -0,0 +1,59 @@
+use std::{process::Command, time::Duration};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// A single worker thread, which would be stuck if any of the requests blocked it
+			let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+			runtime.block_on(async {
+				let requests = (0..16)
+					.map(|i| {
+						let tx = tx.clone();
+						tokio::spawn(async move { tx.request_async::<u32>(i).await.unwrap() })
+					})
+					.collect::<Vec<_>>();
+
+				for (i, request) in requests.into_iter().enumerate() {
+					assert_eq!(request.await.unwrap(), Some(i as u32 * 2));
+				}
+			});
+			println!("[PARENT] Awaited 16 requests at once on a single thread");
+
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Request { request, responder } => {
+					// Respond out of order, and only once every request has been sent
+					std::thread::spawn(move || {
+						std::thread::sleep(Duration::from_millis(100 + u64::from(16 - request) * 10));
+						responder.respond(request * 2).unwrap();
+					});
+				}
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,80 @@
+use std::process::Command;
+use viaduct::{OutgoingMessage, ViaductChild, ViaductEvent, ViaductParent};
+
+const MESSAGES: u32 = 1000;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (parent, reaped) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.reaper_future();
+			let (viaduct, mut child) = parent.build().unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+			runtime.block_on(async move {
+				let (sink, task) = tx.into_async_sink(8);
+
+				// Several producers share the sink, and are held back by the bounded queue if the child falls behind
+				let producers = (0..4)
+					.map(|producer| {
+						let sink = sink.clone();
+						tokio::spawn(async move {
+							for i in (producer..MESSAGES).step_by(4) {
+								sink.send(OutgoingMessage::Rpc(i)).await.unwrap();
+							}
+						})
+					})
+					.collect::<Vec<_>>();
+				drop(sink);
+
+				for producer in producers {
+					producer.await.unwrap();
+				}
+
+				// The task finishes once every sender is gone and the queue is drained
+				task.await.unwrap().unwrap();
+
+				// The child exits once it has received everything, which the reaper notices
+				reaped.await.unwrap();
+				println!("[PARENT] Child process was reaped");
+			});
+
+			println!("[PARENT] Sent {MESSAGES} RPCs through the async sink");
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			let mut received = 0;
+			let mut sum = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(i) => {
+					received += 1;
+					sum += i;
+
+					if received == MESSAGES {
+						assert_eq!(sum, (0..MESSAGES).sum::<u32>());
+						println!("[CHILD] Received all {MESSAGES} RPCs");
+						std::process::exit(0);
+					}
+				}
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,57 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.buffered_writes(true)
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}).ok());
+
+			// These are coalesced in the write buffer...
+			for i in 0..1000 {
+				tx.rpc(i).unwrap();
+			}
+
+			// ...and flushed by the request, which can't be left sitting in the buffer while we wait for the response
+			let received = tx.request::<u32>(()).unwrap().unwrap();
+			assert_eq!(received, 1000);
+			println!("[PARENT] The child received {received} buffered RPCs before the request");
+
+			// Tell the child to stop
+			tx.rpc(u32::MAX).unwrap();
+			tx.flush().unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+
+			let mut received = 0;
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(u32::MAX) => shutdown.shutdown(),
+				ViaductEvent::Rpc(rpc) => {
+					assert_eq!(rpc, received);
+					received += 1;
+				}
+				ViaductEvent::Request { responder, .. } => responder.respond(received).unwrap(),
+				#[allow(unreachable_patterns)]
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use std::{
+	io::ErrorKind,
+	process::Command,
+	time::{Duration, Instant},
+};
+use viaduct::{Never, ViaductChild, ViaductError, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	if std::env::args().any(|arg| arg == "hang") {
+		// A broken child process, which never performs the handshake
+		std::thread::sleep(Duration::from_secs(60));
+		return;
+	}
+
+	if std::env::args().any(|arg| arg == "exit") {
+		// A child process that crashes before it performs the handshake
+		std::process::exit(7);
+	}
+
+	match unsafe { ViaductChild::<Never, Never, Never, Never>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let start = Instant::now();
+			let err = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.arg("hang")
+				.build_timeout(Duration::from_millis(500))
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::TimedOut);
+			assert!(start.elapsed() < Duration::from_secs(5));
+			println!("[PARENT] Gave up on the hung child process after {:?}", start.elapsed());
+
+			// A child process that exits is reported straight away, with its exit status
+			let start = Instant::now();
+			let err = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.arg("exit")
+				.build_timeout(Duration::from_secs(10))
+				.unwrap_err();
+			match ViaductError::from_io(&err) {
+				Some(ViaductError::ChildExitedDuringHandshake { status }) => assert_eq!(status.code(), Some(7)),
+				_ => panic!("Expected ChildExitedDuringHandshake, got {err:?}"),
+			}
+			assert!(start.elapsed() < Duration::from_secs(5));
+			println!("[PARENT] {err}");
+
+			// A well-behaved child process finishes well within the timeout
+			let (_, mut child) = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build_timeout(Duration::from_secs(10))
+				.unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(_) => println!("[CHILD] Handshake complete"),
+	}
+}
@@ -0,0 +1,44 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(err) => {
+			// Nothing was taken, so this isn't mistaken for building twice
+			assert!(ViaductError::from_io(&err).is_none(), "{err}");
+
+			let (viaduct, mut child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+
+			// Tell the child to stop
+			viaduct.split().0.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			// The pipe handles are still in our arguments, but they belong to the first viaduct
+			let err = unsafe { ViaductChild::<(), (), (), ()>::new().build() }.unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::AlreadyBuilt)), "{err}");
+			println!("[CHILD] {err}");
+
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
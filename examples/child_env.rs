@@ -0,0 +1,55 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			std::env::set_var("VIADUCT_EXAMPLE_INHERITED", "1");
+			std::env::set_var("VIADUCT_EXAMPLE_REMOVED", "1");
+
+			let (viaduct, mut child) = ViaductParent::<(), (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.env("RUST_LOG", "debug")
+				.envs([("VIADUCT_EXAMPLE_A", "a"), ("VIADUCT_EXAMPLE_B", "b")])
+				.env_remove("VIADUCT_EXAMPLE_REMOVED")
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			rx.run(|event| {
+				if let ViaductEvent::Rpc(checked) = event {
+					assert_eq!(checked, 5);
+					println!("[PARENT] The child process saw the environment it was given");
+
+					// Tell the child to stop
+					tx.rpc(()).unwrap();
+				}
+			})
+			.ok();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let var = |key| std::env::var(key).ok();
+			assert_eq!(var("RUST_LOG").as_deref(), Some("debug"));
+			assert_eq!(var("VIADUCT_EXAMPLE_A").as_deref(), Some("a"));
+			assert_eq!(var("VIADUCT_EXAMPLE_B").as_deref(), Some("b"));
+			assert_eq!(var("VIADUCT_EXAMPLE_INHERITED").as_deref(), Some("1"));
+			assert_eq!(var("VIADUCT_EXAMPLE_REMOVED"), None);
+
+			let (tx, mut rx) = viaduct.split();
+			tx.rpc(5).unwrap();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|_| shutdown.shutdown()).unwrap();
+		}
+	}
+}
@@ -0,0 +1,55 @@
+use std::process::{Child, Command};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+/// Owns the child process, so that the rest of the program can't get at its `Child`.
+struct Supervisor {
+	child: Child,
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let child_handle = viaduct.child_handle().unwrap();
+			assert_eq!(child_handle.pid(), child.id());
+
+			let mut supervisor = Supervisor { child };
+			let (tx, rx) = viaduct.split();
+			let event_loop = std::thread::spawn(move || rx.run(|_| {}));
+
+			// Any clone of the handle can kill the child process, from any thread
+			let killer = tx.child_handle().unwrap();
+			std::thread::spawn(move || killer.kill().unwrap()).join().unwrap();
+
+			let status = supervisor.child.wait().unwrap();
+			assert!(!status.success());
+			println!("[PARENT] Killed child process {}: {status}", child_handle.pid());
+
+			event_loop.join().unwrap().unwrap_err();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			assert!(viaduct.child_handle().is_none());
+
+			// Wait to be killed
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => unreachable!(),
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
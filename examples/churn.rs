@@ -0,0 +1,93 @@
+#[cfg(target_os = "linux")]
+fn main() {
+	use std::{
+		process::Command,
+		time::{Duration, Instant},
+	};
+	use viaduct::{test_util::open_handles, Never, ViaductChild, ViaductParent};
+
+	/// How many viaducts of each kind to create and tear down.
+	const ROUNDS: usize = 20;
+
+	fn threads() -> usize {
+		std::fs::read_dir("/proc/self/task").unwrap().count()
+	}
+
+	/// Waits a little while for `f` to return `true`, as threads take a moment to wind down.
+	fn eventually(mut f: impl FnMut() -> bool) -> bool {
+		let deadline = Instant::now() + Duration::from_secs(2);
+		while !f() {
+			if Instant::now() >= deadline {
+				return false;
+			}
+			std::thread::sleep(Duration::from_millis(10));
+		}
+		true
+	}
+
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 60 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(60));
+		std::process::exit(33);
+	});
+
+	match std::env::args().nth(1).as_deref() {
+		// A child process that crashes before it performs the handshake
+		Some("exit") => std::process::exit(7),
+
+		// A child process that never performs the handshake
+		Some("hang") => {
+			std::thread::sleep(Duration::from_secs(60));
+			return;
+		}
+
+		_ => {}
+	}
+
+	match unsafe { ViaductChild::<Never, Never, Never, Never>::new().with_reaper(|| {}).build() } {
+		// We're the parent process
+		Err(_) => {
+			let parent = || ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap())).unwrap();
+
+			let (handles, threads_before) = (open_handles().unwrap(), threads());
+
+			for _ in 0..ROUNDS {
+				// A child process that completes the handshake, then exits once we hang up
+				let (viaduct, mut child) = parent().with_reaper(|_| {}).build().unwrap();
+				drop(viaduct);
+				assert!(child.wait().unwrap().success());
+
+				// A child process that exits before completing the handshake
+				parent().arg("exit").build().unwrap_err();
+
+				// A child process that has to be killed because it never completes the handshake
+				parent().arg("hang").build_timeout(Duration::from_millis(20)).unwrap_err();
+			}
+
+			// Reaper threads finish once their child process has gone, closing their end of the reaper pipe
+			assert!(
+				eventually(|| threads() == threads_before),
+				"{} thread(s) were leaked",
+				threads() - threads_before
+			);
+			assert_eq!(open_handles().unwrap(), handles, "Handles were leaked");
+
+			// Every child process has been waited on, so there are no zombies left behind
+			assert_eq!(unsafe { libc::waitpid(-1, std::ptr::null_mut(), libc::WNOHANG) }, -1);
+			assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::ECHILD));
+
+			println!("[PARENT] Created and tore down {} viaducts without leaking anything", ROUNDS * 3);
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|_| {}).unwrap_err();
+		}
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+	println!("This stress test counts threads and processes using Linux's procfs");
+}
@@ -0,0 +1,59 @@
+use std::process::Command;
+use viaduct::{CloseReason, ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			assert!(viaduct.close_reason().is_none());
+
+			let (tx, rx) = viaduct.split();
+			let event_loop = std::thread::spawn(move || rx.run(|_| {}));
+
+			// The child shuts down its sending side after responding to this
+			tx.request::<()>(()).unwrap().unwrap();
+
+			// ...so our event loop stops, and both halves agree on why
+			let err = event_loop.join().unwrap().unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)), "{err}");
+			assert!(matches!(tx.close_reason(), Some(CloseReason::PeerDropped)));
+			println!("[PARENT] The viaduct was closed: {}", tx.close_reason().unwrap());
+
+			// Requests fail for the same reason
+			let err = tx.request::<()>(()).unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)), "{err}");
+
+			drop(tx);
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+
+			let result = rx.run(|event| match event {
+				ViaductEvent::Request { responder, .. } => {
+					responder.respond(()).unwrap();
+					tx.shutdown_send().unwrap();
+					assert!(matches!(tx.close_reason(), Some(CloseReason::Shutdown)));
+				}
+				_ => unreachable!(),
+			});
+
+			// We can still receive until the parent goes away, which replaces the shutdown as the close reason
+			assert!(matches!(ViaductError::from_io(&result.unwrap_err()), Some(ViaductError::PeerGone)));
+			assert!(matches!(tx.close_reason(), Some(CloseReason::PeerDropped)));
+			println!("[CHILD] The viaduct was closed: {}", tx.close_reason().unwrap());
+		}
+	}
+}
@@ -0,0 +1,98 @@
+use rand::{RngCore, SeedableRng};
+use std::process::Command;
+use viaduct::{Capability, ViaductChild, ViaductDeserialize, ViaductEvent, ViaductParent, ViaductSerialize};
+
+/// Only payloads at least this large are compressed.
+const THRESHOLD: usize = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Blob(Vec<u8>);
+impl ViaductSerialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(bytes.to_vec()))
+	}
+}
+
+fn blobs() -> Vec<Blob> {
+	let mut random = vec![0; 64 * 1024];
+	rand::rngs::StdRng::from_seed([7; 32]).fill_bytes(&mut random);
+
+	vec![
+		// Too small to be worth compressing
+		Blob(b"tiny".to_vec()),
+		// Very compressible
+		Blob(b"viaduct ".repeat(64 * 1024)),
+		// Doesn't compress at all
+		Blob(random),
+		// Right on the threshold
+		Blob(vec![0; THRESHOLD]),
+		Blob(Vec::new()),
+	]
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), Blob, Blob, Blob>::new().compression_threshold(THRESHOLD).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Blob, Blob, (), Blob>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.compression_threshold(THRESHOLD)
+				.require_capability(Capability::Compression)
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			for blob in blobs() {
+				// The child echoes requests back as responses, so these payloads go both ways
+				tx.rpc(blob.clone()).unwrap();
+				tx.rpc_uncompressed(blob.clone()).unwrap();
+				assert_eq!(tx.request::<Blob>(blob.clone()).unwrap(), Some(blob));
+			}
+
+			println!("[PARENT] Payloads survived compression");
+
+			tx.rpc(Blob(b"exit".to_vec())).unwrap();
+			child.wait().unwrap();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			let blobs = blobs();
+			let mut rpcs = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(blob) => {
+					if blob.0 == b"exit" {
+						std::process::exit(0);
+					}
+					assert_eq!(blob, blobs[rpcs / 2]);
+					rpcs += 1;
+				}
+				ViaductEvent::Request { request, responder } => {
+					assert!(blobs.contains(&request));
+					responder.respond(request).unwrap();
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
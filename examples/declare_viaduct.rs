@@ -0,0 +1,60 @@
+use std::process::Command;
+
+// Usually declared in a library shared by the parent and child processes
+viaduct::declare_viaduct! {
+	pub type {
+		parent_to_child_rpc: u32,
+		parent_to_child_request: u64,
+		child_to_parent_rpc: u8,
+		child_to_parent_request: (),
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ChildBuilder::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ParentBuilder::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx): (ParentViaductTx, ParentViaductRx) = viaduct.split();
+
+			std::thread::spawn(move || {
+				rx.run(|event: ParentViaductEvent| match event {
+					ParentViaductEvent::Rpc(rpc) => println!("[PARENT] Child sent {rpc}"),
+					ParentViaductEvent::Request { .. } => unreachable!(),
+					_ => unreachable!(),
+				})
+			});
+
+			tx.rpc(7).unwrap();
+			assert_eq!(tx.request::<u64>(21).unwrap(), Some(42));
+
+			// Tell the child to stop
+			tx.rpc(0).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let viaduct: ChildViaduct = viaduct;
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+
+			rx.run(|event: ChildViaductEvent| match event {
+				ChildViaductEvent::Rpc(0) => shutdown.shutdown(),
+				ChildViaductEvent::Rpc(rpc) => tx.rpc(rpc as u8).unwrap(),
+				ChildViaductEvent::Request { request, responder } => responder.respond(request * 2).unwrap(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,52 @@
+use std::{io::ErrorKind, ops::ControlFlow, process::Command};
+use viaduct::{DeserializeError, PacketType, ViaductChild, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	// The two processes disagree on the types they exchange, so nothing the parent sends can be deserialized by the child
+	match unsafe { ViaductChild::<(), (), u32, [u8; 3]>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<[u8; 3], u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// The child skips the malformed RPC...
+			tx.rpc([1, 2, 3]).unwrap();
+
+			// ...and drops the responder of the malformed request, so there's no response
+			assert_eq!(tx.request::<()>(1).unwrap(), None);
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let err = rx
+				.run_with_error_handler(
+					|_| panic!("Nothing the parent sends should deserialize"),
+					|err| {
+						println!("[CHILD] Received a malformed {:?}: {err:?}", err.packet_type());
+						match err {
+							DeserializeError::Rpc(_) => ControlFlow::Continue(()),
+							DeserializeError::Request(_) => ControlFlow::Break(()),
+						}
+					},
+				)
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidData);
+			assert!(err.to_string().contains(&format!("{:?}", PacketType::Request)), "{err}");
+		}
+	}
+}
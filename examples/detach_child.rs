@@ -0,0 +1,54 @@
+use std::process::Command;
+use viaduct::{Never, ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<Never, Never, Never, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, _child) = ViaductParent::<Never, (), Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.detach_child()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Ask the child whether it's running in its own session
+			assert_eq!(tx.request::<u8>(()).unwrap(), Some(1));
+
+			println!("[PARENT] Exiting before the child");
+
+			// Exit without waiting for the child, which carries on without us
+			std::process::exit(0);
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			let err = rx
+				.run(|event| match event {
+					ViaductEvent::Request { request: (), responder } => {
+						#[cfg(unix)]
+						let detached = unsafe { libc::getsid(0) == libc::getpid() };
+
+						#[cfg(windows)]
+						let detached = true;
+
+						responder.respond(detached as u8).unwrap();
+					}
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+
+			// The parent has exited, but we're still running
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)));
+		}
+	}
+}
@@ -0,0 +1,50 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), (), ()>::new().from_env().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.arg("--hello")
+				.pass_handles_in_env()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			rx.run(|event| {
+				if let ViaductEvent::Rpc(argc) = event {
+					assert_eq!(argc, 2);
+					println!("[PARENT] The child process was started with {argc} arguments");
+
+					// Tell the child to stop
+					tx.rpc(()).unwrap();
+				}
+			})
+			.ok();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			// The handles weren't passed in the arguments, so they are exactly as the parent set them
+			let args = std::env::args().collect::<Vec<_>>();
+			assert_eq!(args.len(), 2);
+			assert_eq!(args[1], "--hello");
+			assert!(std::env::var_os("VIADUCT_PIPES").is_none());
+
+			let (tx, mut rx) = viaduct.split();
+			tx.rpc(args.len() as u32).unwrap();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|_| shutdown.shutdown()).unwrap();
+		}
+	}
+}
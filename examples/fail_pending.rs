@@ -0,0 +1,60 @@
+use std::{io::ErrorKind, process::Command, time::Duration};
+use viaduct::{ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u8>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u8, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			let event_loop = std::thread::spawn(move || rx.run(|_| {}));
+
+			// The child never answers this one, so it's up to us to wake the requester
+			let ignored = {
+				let tx = tx.clone();
+				std::thread::spawn(move || tx.request::<()>(0))
+			};
+			std::thread::sleep(Duration::from_millis(100));
+			tx.fail_pending(|| ErrorKind::ConnectionReset.into());
+			assert_eq!(ignored.join().unwrap().unwrap_err().kind(), ErrorKind::ConnectionReset);
+			println!("[PARENT] Woke the requester that was left waiting");
+
+			// The child exits instead of answering this one, which fails the request rather than leaving it waiting forever
+			let err = tx.request::<()>(1).unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)), "{err}");
+			assert!(matches!(
+				ViaductError::from_io(&event_loop.join().unwrap().unwrap_err()),
+				Some(ViaductError::PeerGone)
+			));
+			println!("[PARENT] The request failed when the child went away");
+
+			// Nothing is left to receive responses, so requests fail straight away, for the same reason
+			let err = tx.request::<()>(2).unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)), "{err}");
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let mut ignored = Vec::new();
+			rx.run(|event| match event {
+				ViaductEvent::Request { request: 0, responder } => ignored.push(responder),
+				ViaductEvent::Request { request: 1, .. } => std::process::exit(0),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,95 @@
+#[cfg(unix)]
+fn main() {
+	use std::{
+		fs::File,
+		io::{Read, Seek, Write},
+		os::{fd::AsFd, unix::net::UnixStream},
+		process::Command,
+	};
+	use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+	const FROM_PARENT: &str = "Hello from a file the parent opened!";
+	const FROM_CHILD: &str = "Hello from a socket the child opened!";
+
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			let path = std::env::temp_dir().join(format!("viaduct-fd-passing-{}", std::process::id()));
+			let mut file = File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+			std::fs::remove_file(&path).unwrap();
+			file.write_all(FROM_PARENT.as_bytes()).unwrap();
+			file.rewind().unwrap();
+
+			// The child gets its own duplicate of the file descriptor, so ours can be closed straight away
+			tx.send_fd(file.as_fd()).unwrap();
+			drop(file);
+
+			let (mut read_len, mut read_socket) = (false, false);
+			rx.run(|event| {
+				match event {
+					ViaductEvent::Rpc(len) => {
+						assert_eq!(len as usize, FROM_PARENT.len());
+						read_len = true;
+					}
+					ViaductEvent::Handle(fd) => {
+						let mut message = String::new();
+						UnixStream::from(fd).read_to_string(&mut message).unwrap();
+						assert_eq!(message, FROM_CHILD);
+						println!("[PARENT] {message}");
+						read_socket = true;
+					}
+					_ => unreachable!(),
+				}
+
+				if read_len && read_socket {
+					// Tell the child to stop
+					tx.rpc(()).unwrap();
+				}
+			})
+			.ok();
+			assert!(read_len && read_socket);
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Handle(fd) => {
+					let mut message = String::new();
+					File::from(fd).read_to_string(&mut message).unwrap();
+					assert_eq!(message, FROM_PARENT);
+					println!("[CHILD] {message}");
+					tx.rpc(message.len() as u32).unwrap();
+
+					// Share a socket of our own in the other direction
+					let (mut ours, theirs) = UnixStream::pair().unwrap();
+					tx.send_fd(theirs.as_fd()).unwrap();
+					drop(theirs);
+					ours.write_all(FROM_CHILD.as_bytes()).unwrap();
+				}
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
+
+#[cfg(not(unix))]
+fn main() {
+	println!("File descriptors are only shared on Unix; see ViaductTx::send_handle on Windows");
+}
@@ -0,0 +1,132 @@
+use std::{
+	process::Command,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+};
+use viaduct::{BufferPool, SimpleBufferPool, ViaductChild, ViaductDeserialize, ViaductError, ViaductEvent, ViaductParent, ViaductSerialize};
+
+/// Some bytes, which will be fragmented when sent.
+#[derive(Debug, PartialEq, Eq)]
+struct Blob(Vec<u8>);
+impl ViaductSerialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(bytes.to_vec()))
+	}
+}
+
+fn blob(seed: usize) -> Blob {
+	Blob((0..1000 + seed).map(|i| (i * seed) as u8).collect())
+}
+
+/// A [`SimpleBufferPool`] which counts the buffers that were returned to it without an allocation.
+struct RecordingPool {
+	pool: SimpleBufferPool,
+	unallocated: AtomicUsize,
+}
+impl BufferPool for RecordingPool {
+	fn acquire(&self) -> Vec<u8> {
+		self.pool.acquire()
+	}
+
+	fn release(&self, buf: Vec<u8>) {
+		if buf.capacity() == 0 {
+			self.unallocated.fetch_add(1, Ordering::Relaxed);
+		}
+		self.pool.release(buf);
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	let child = ViaductChild::<Blob, Blob, Blob, Blob>::new()
+		.max_fragment_size(77)
+		.max_concurrent_fragments(4)
+		.max_reassembly_bytes(8192)
+		.buffer_pool(SimpleBufferPool::new(8));
+
+	match unsafe { child.build() } {
+		// We're the parent process
+		Err(_) => {
+			let pool = Arc::new(RecordingPool {
+				pool: SimpleBufferPool::new(8),
+				unallocated: AtomicUsize::new(0),
+			});
+			let (viaduct, mut child) = ViaductParent::<Blob, Blob, Blob, Blob>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.max_fragment_size(100)
+				.buffer_pool(pool.clone())
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Send lots of fragmented requests at once, so that their fragments are interleaved
+			let tx = Arc::new(tx);
+			let threads = (0..4)
+				.map(|thread| {
+					let tx = tx.clone();
+					std::thread::spawn(move || {
+						for i in 0..25 {
+							let seed = thread * 25 + i;
+							let response = tx.request::<Blob>(blob(seed)).unwrap().unwrap();
+							assert_eq!(response.0.len(), blob(seed).0.len() * 2);
+						}
+					})
+				})
+				.collect::<Vec<_>>();
+
+			threads.into_iter().for_each(|thread| thread.join().unwrap());
+			println!("[PARENT] Fragmented requests worked!");
+
+			// The responses were copied out of the pooled buffers, which went back into the pool with their allocations intact
+			assert_eq!(pool.unallocated.load(Ordering::Relaxed), 0);
+
+			// This is more than the child is willing to hold in memory
+			tx.rpc(Blob(vec![0; 10000])).unwrap();
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			let err = rx
+				.run(|event| match event {
+					ViaductEvent::Rpc(_) => panic!("[CHILD] Received an RPC that was too large"),
+					ViaductEvent::Request { request, responder } => {
+						let mut response = request.0.clone();
+						response.extend(request.0);
+						responder.respond(Blob(response)).unwrap();
+					}
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+
+			match ViaductError::from_io(&err) {
+				Some(ViaductError::ReassemblyLimit { .. }) => {
+					println!("[CHILD] {err}");
+					std::process::exit(0);
+				}
+				_ => panic!("[CHILD] Unexpected error: {err:?}"),
+			}
+		}
+	}
+}
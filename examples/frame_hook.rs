@@ -0,0 +1,76 @@
+use std::{
+	process::Command,
+	sync::{Arc, Mutex},
+};
+use viaduct::{FrameInfo, PacketType, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	let frames = Arc::new(Mutex::new(Vec::<FrameInfo>::new()));
+	let on_frame = {
+		let frames = frames.clone();
+		move |frame| frames.lock().unwrap().push(frame)
+	};
+
+	match unsafe { ViaductChild::<u32, (), (), u32>::new().on_frame(on_frame.clone()).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.on_frame(on_frame)
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || {
+				rx.run(|event| {
+					if let ViaductEvent::Rpc(rpc) = event {
+						assert_eq!(rpc, 42);
+					}
+				})
+				.ok();
+			});
+
+			let response = tx.request::<u64>(7).unwrap();
+			assert_eq!(response, Some(49));
+
+			// Tell the child to stop
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+
+			let frames = frames.lock().unwrap();
+			println!("[PARENT] Frames received: {frames:?}");
+			assert!(frames
+				.iter()
+				.any(|frame| frame.packet_type == PacketType::Rpc && frame.len == 4 && frame.request_id.is_none()));
+			assert!(frames
+				.iter()
+				.any(|frame| frame.packet_type == PacketType::Response && frame.len == 8 && frame.request_id.is_some()));
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, mut rx) = viaduct.split();
+			tx.rpc(42).unwrap();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Request { request, responder } => responder.respond(request as u64 * request as u64).unwrap(),
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				#[allow(unreachable_patterns)]
+				_ => unreachable!(),
+			})
+			.unwrap();
+
+			let frames = frames.lock().unwrap();
+			assert!(frames
+				.iter()
+				.any(|frame| frame.packet_type == PacketType::Request && frame.len == 4 && frame.request_id.is_some()));
+		}
+	}
+}
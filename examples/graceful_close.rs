@@ -0,0 +1,68 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{CloseReason, ViaductChild, ViaductEvent, ViaductParent};
+
+const RPCS: u32 = 100;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), u32, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			let clone = tx.clone();
+
+			for i in 0..RPCS {
+				tx.rpc(i).unwrap();
+			}
+			tx.close().unwrap();
+
+			// Every clone is closed too
+			assert_eq!(clone.rpc(RPCS).unwrap_err().kind(), ErrorKind::BrokenPipe);
+			assert!(matches!(clone.close_reason(), Some(CloseReason::Shutdown)));
+
+			// The child tells us how many RPCs it received before closing its own side, which stops our event loop cleanly
+			let mut received = None;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(count) => received = Some(count),
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+			assert_eq!(received, Some(RPCS));
+			assert!(matches!(clone.close_reason(), Some(CloseReason::PeerClosed)));
+			println!("[PARENT] The viaduct was closed: {}", clone.close_reason().unwrap());
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+
+			let mut expected = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(i) => {
+					assert_eq!(i, expected);
+					expected += 1;
+				}
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+			assert!(matches!(tx.close_reason(), Some(CloseReason::PeerClosed)));
+			println!("[CHILD] Received {expected} RPCs before the parent closed the viaduct");
+
+			tx.rpc(expected).unwrap();
+			tx.close().unwrap();
+		}
+	}
+}
@@ -0,0 +1,85 @@
+use std::process::Command;
+use viaduct::{test_util::assert_no_handle_leaks, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	// Spawning a thread opens handles on some platforms, so get that out of the way before we start counting
+	std::thread::spawn(|| {}).join().unwrap();
+
+	let mut child = ViaductChild::<(), u32, (), u32>::new();
+	if std::env::args().nth(1).as_deref() == Some("reaper") {
+		child = child.with_reaper(|| {});
+	}
+
+	match unsafe { child.build() } {
+		// We're the parent process
+		Err(_) => {
+			for (with_parent_reaper, with_child_reaper) in [(false, false), (true, false), (false, true), (true, true)] {
+				for _ in 0..5 {
+					assert_no_handle_leaks(|| {
+						let mut parent = ViaductParent::<(), u32, (), u32>::new(Command::new(std::env::current_exe().unwrap())).unwrap();
+						if with_child_reaper {
+							parent = parent.arg("reaper");
+						}
+
+						// The reaper thread holds onto handles until it has called back, so wait for that before counting them
+						let (reaped_tx, reaped_rx) = std::sync::mpsc::sync_channel(1);
+						if with_parent_reaper {
+							parent = parent.with_reaper(move |_| reaped_tx.send(()).unwrap());
+						} else {
+							drop(reaped_tx);
+						}
+
+						let (viaduct, mut child) = parent.build().unwrap();
+						let (tx, rx) = viaduct.split();
+
+						let event_loop = std::thread::spawn(move || {
+							rx.run(|event| {
+								if let ViaductEvent::Request { request, responder } = event {
+									responder.respond(request + 1).unwrap();
+								}
+							})
+						});
+
+						assert_eq!(tx.request::<u32>(1).unwrap(), Some(2));
+
+						// Tell the child to exit, the event loop will then stop
+						tx.rpc(()).unwrap();
+						drop(tx);
+
+						child.wait().unwrap();
+						event_loop.join().unwrap().unwrap_err();
+						if with_parent_reaper {
+							reaped_rx.recv().unwrap();
+						}
+					});
+				}
+			}
+
+			println!("[PARENT] No handles were leaked, with or without reapers");
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || {
+				rx.run(|event| match event {
+					ViaductEvent::Rpc(()) => std::process::exit(0),
+					ViaductEvent::Request { request, responder } => responder.respond(request + 1).unwrap(),
+					_ => unreachable!(),
+				})
+			});
+
+			assert_eq!(tx.request::<u32>(41).unwrap(), Some(42));
+
+			loop {
+				std::thread::park();
+			}
+		}
+	}
+}
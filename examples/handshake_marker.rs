@@ -0,0 +1,45 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+const MARKER: &str = "VIADUCT_EXAMPLE_START";
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), ()>::new().build_with_args() } {
+		// We're the parent process
+		Err(_) => {
+			// `PIPER_START` is one of our real arguments, which would be mistaken for the default marker
+			let (viaduct, mut child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.args(["--label", "PIPER_START", "1", "2"])
+				.with_handshake_marker(MARKER)
+				.build()
+				.unwrap();
+
+			// Tell the child to stop
+			viaduct.split().0.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+			println!("[PARENT] The child process received its arguments intact");
+		}
+
+		// We're the child process
+		Ok((viaduct, args)) => {
+			assert_eq!(args.skip(1).collect::<Vec<_>>(), ["--label", "PIPER_START", "1", "2"]);
+			assert!(std::env::var_os("VIADUCT_HANDSHAKE_MARKER").is_none());
+
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
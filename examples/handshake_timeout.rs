@@ -0,0 +1,62 @@
+use std::{
+	io::ErrorKind,
+	process::Command,
+	time::{Duration, Instant},
+};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	if std::env::args().any(|arg| arg == "--stall") {
+		// Pretend to be a mis-launched child process that never builds its side of the viaduct
+		std::thread::sleep(Duration::from_secs(60));
+		return;
+	}
+
+	match unsafe {
+		ViaductChild::<(), (), (), ()>::new()
+			.with_handshake_timeout(Duration::from_secs(10))
+			.build()
+	} {
+		// We're the parent process
+		Err(_) => {
+			let started = Instant::now();
+			let err = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.arg("--stall")
+				.with_handshake_timeout(Duration::from_millis(200))
+				.build()
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::TimedOut);
+			assert!(started.elapsed() < Duration::from_secs(5));
+			println!("[PARENT] Gave up on the stalled child process after {:?}", started.elapsed());
+
+			let (viaduct, mut child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.with_handshake_timeout(Duration::from_secs(10))
+				.build()
+				.unwrap();
+
+			// Tell the child to stop
+			viaduct.split().0.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
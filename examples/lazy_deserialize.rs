@@ -0,0 +1,99 @@
+use std::{
+	process::Command,
+	sync::OnceLock,
+	thread::ThreadId,
+	time::{Duration, Instant},
+};
+use viaduct::{ViaductChild, ViaductDeserialize, ViaductLazyEvent, ViaductParent, ViaductSerialize};
+
+/// How long it takes to deserialize a [`Slow`].
+const DESERIALIZE_TIME: Duration = Duration::from_millis(100);
+
+/// The thread running the child's event loop.
+static EVENT_LOOP: OnceLock<ThreadId> = OnceLock::new();
+
+/// A message that is expensive to deserialize.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Slow(u32);
+impl ViaductSerialize for Slow {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0.to_ne_bytes());
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Slow {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		// The event loop must never get held up deserializing
+		assert_ne!(EVENT_LOOP.get(), Some(&std::thread::current().id()));
+
+		std::thread::sleep(DESERIALIZE_TIME);
+		Ok(Self(u32::from_ne_bytes(bytes.try_into().unwrap())))
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), Slow, Slow>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Slow, Slow, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// The requests are deserialized in parallel, so they shouldn't take much longer than one of them
+			let start = Instant::now();
+			let threads = (0..8)
+				.map(|i| {
+					let tx = tx.clone();
+					std::thread::spawn(move || assert_eq!(tx.request::<u32>(Slow(i)).unwrap(), Some(i * 2)))
+				})
+				.collect::<Vec<_>>();
+			threads.into_iter().for_each(|thread| thread.join().unwrap());
+
+			let elapsed = start.elapsed();
+			println!("[PARENT] 8 requests took {elapsed:?}");
+			assert!(elapsed < DESERIALIZE_TIME * 4);
+
+			tx.rpc(Slow(0)).unwrap();
+			child.wait().unwrap();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run_lazy(|event| {
+				EVENT_LOOP.get_or_init(|| std::thread::current().id());
+
+				match event {
+					ViaductLazyEvent::Rpc(rpc) => {
+						std::thread::spawn(move || {
+							assert_eq!(rpc.decode(), Slow(0));
+							std::process::exit(0);
+						});
+					}
+					ViaductLazyEvent::Request { request, responder } => {
+						std::thread::spawn(move || {
+							let Slow(n) = request.decode();
+							responder.respond(n * 2).unwrap();
+						});
+					}
+					_ => unreachable!(),
+				}
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,53 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{RawBytes, ViaductChild, ViaductError, ViaductParent};
+
+const LIMIT: usize = 32;
+
+type Big = RawBytes<'static>;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	// Resyncing the stream doesn't skip over packets that exceed the limit
+	match unsafe {
+		ViaductChild::<(), (), Big, ()>::new()
+			.max_message_size(Some(LIMIT))
+			.resync_markers()
+			.build()
+	} {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Big, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.resync_markers()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			// There's no limit on what we can send, so this is only refused by the child
+			tx.rpc(RawBytes::from(vec![0; 64])).unwrap();
+
+			// ...which stops its event loop and exits
+			let err = rx.run(|_| unreachable!()).unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)));
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let err = rx.run(|_| panic!("The parent's oversized RPC shouldn't have been received")).unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidData);
+			assert!(matches!(
+				ViaductError::from_io(&err),
+				Some(ViaductError::ReceiveLimit { size: 64, limit: LIMIT })
+			));
+			println!("[CHILD] {err}");
+		}
+	}
+}
@@ -0,0 +1,63 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{RawBytes, ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+const LIMIT: usize = 32;
+
+type Big = RawBytes<'static>;
+
+fn big() -> Big {
+	RawBytes::from(vec![0; 64])
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), Big, u32>::new().max_send_size(LIMIT).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Big, u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.max_send_size(LIMIT)
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			let err = tx.rpc(big()).unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidInput);
+			assert!(matches!(
+				ViaductError::from_io(&err),
+				Some(ViaductError::MessageTooLarge { size: 64, limit: LIMIT })
+			));
+			println!("[PARENT] {err}");
+
+			// The child's response is too large for it to send, so we're told there isn't one
+			assert_eq!(tx.request::<Big>(1).unwrap(), None);
+
+			tx.shutdown_send().unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let err = rx
+				.run(|event| match event {
+					ViaductEvent::Request { responder, .. } => {
+						let err = responder.respond(big()).unwrap_err();
+						assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::MessageTooLarge { .. })));
+					}
+					ViaductEvent::Rpc(_) => panic!("The parent's oversized RPC shouldn't have been sent"),
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)));
+		}
+	}
+}
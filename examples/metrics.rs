@@ -0,0 +1,58 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || {
+				rx.run(|_| {}).ok();
+			});
+
+			tx.rpc(1).unwrap();
+			tx.rpc_batch([2, 3]).unwrap();
+			assert_eq!(tx.request::<u64>(4).unwrap(), Some(16));
+			assert_eq!(tx.request::<u64>(0).unwrap(), None);
+
+			let metrics = tx.metrics();
+			println!("[PARENT] {metrics:?}");
+			assert_eq!(metrics.rpcs_sent, 3);
+			assert_eq!(metrics.requests_sent, 2);
+			assert_eq!(metrics.responses_received, 2);
+			assert!(metrics.bytes_written >= 4 + 24 + 4 + 4);
+			assert_eq!(metrics.bytes_read, 8);
+
+			// Tell the child to stop
+			tx.rpc(u32::MAX).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(u32::MAX) => shutdown.shutdown(),
+				ViaductEvent::Rpc(_) => {}
+				ViaductEvent::Request { request: 0, responder } => responder.ack().unwrap(),
+				ViaductEvent::Request { request, responder } => responder.respond(request as u64 * request as u64).unwrap(),
+				#[allow(unreachable_patterns)]
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,130 @@
+use memmap2::MmapMut;
+use std::{fs::OpenOptions, process::Command};
+use viaduct::{MappedPayload, PacketType, ViaductChild, ViaductDeserialize, ViaductMappedEvent, ViaductParent, ViaductSerialize};
+
+/// Only payloads at least this large are received into a memory-mapped file.
+const THRESHOLD: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Blob(Vec<u8>);
+impl ViaductSerialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(bytes.to_vec()))
+	}
+}
+
+fn blob(len: usize) -> Blob {
+	Blob((0..len).map(|i| (i % 251) as u8).collect())
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+	bytes.iter().map(|&byte| byte as u64).sum()
+}
+
+/// Maps a fresh temporary file of `len` bytes into memory.
+fn map_file(len: usize) -> MmapMut {
+	let path = std::env::temp_dir().join(format!("viaduct-mmap-receive-{}-{len}", std::process::id()));
+	let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+	file.set_len(len as u64).unwrap();
+	let mmap = unsafe { MmapMut::map_mut(&file) }.unwrap();
+
+	// The mapping keeps the file's contents alive
+	drop(file);
+	std::fs::remove_file(&path).ok();
+
+	mmap
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<Blob, Blob, (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), Blob, Blob>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (_tx, rx) = viaduct.split();
+
+			let (events_tx, events_rx) = std::sync::mpsc::channel();
+			std::thread::spawn(move || {
+				rx.run_mapped(
+					|packet_type, len| {
+						assert!(matches!(packet_type, PacketType::Rpc | PacketType::Request));
+						(len >= THRESHOLD).then(|| map_file(len))
+					},
+					|event| match event {
+						ViaductMappedEvent::Rpc(MappedPayload::Mapped(mmap)) => {
+							println!("[PARENT] Received a {} byte RPC into a memory-mapped file", mmap.len());
+							events_tx.send(("mapped rpc", mmap.len(), checksum(&mmap))).unwrap();
+						}
+
+						ViaductMappedEvent::Rpc(MappedPayload::Buffered(Blob(bytes))) => {
+							println!("[PARENT] Received a {} byte RPC into memory", bytes.len());
+							events_tx.send(("buffered rpc", bytes.len(), checksum(&bytes))).unwrap();
+						}
+
+						ViaductMappedEvent::Request {
+							request: MappedPayload::Mapped(mmap),
+							responder,
+						} => {
+							println!("[PARENT] Received a {} byte request into a memory-mapped file", mmap.len());
+							events_tx.send(("mapped request", mmap.len(), checksum(&mmap))).unwrap();
+							responder.respond(checksum(&mmap)).unwrap();
+						}
+
+						ViaductMappedEvent::Request {
+							request: MappedPayload::Buffered(_),
+							..
+						} => panic!("Expected the request to be memory-mapped"),
+
+						_ => unreachable!(),
+					},
+				)
+			});
+
+			let expected = [
+				("mapped rpc", 64 * 1024 * 1024, checksum(&blob(64 * 1024 * 1024).0)),
+				("buffered rpc", 1024, checksum(&blob(1024).0)),
+				("mapped rpc", THRESHOLD, checksum(&blob(THRESHOLD).0)),
+				("buffered rpc", 0, 0),
+				("mapped request", 8 * 1024 * 1024, checksum(&blob(8 * 1024 * 1024).0)),
+			];
+			for expected in expected {
+				assert_eq!(events_rx.recv().unwrap(), expected);
+			}
+
+			assert!(child.wait().unwrap().success());
+			println!("[PARENT] All payloads arrived intact");
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			tx.rpc(blob(64 * 1024 * 1024)).unwrap();
+			tx.rpc(blob(1024)).unwrap();
+			tx.rpc(blob(THRESHOLD)).unwrap();
+			tx.rpc(Blob(Vec::new())).unwrap();
+
+			let request = blob(8 * 1024 * 1024);
+			assert_eq!(tx.request::<u64>(request.clone()).unwrap(), Some(checksum(&request.0)));
+		}
+	}
+}
@@ -0,0 +1,97 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{PacketType, ViaductChild, ViaductDeserialize, ViaductEvent, ViaductParent, ViaductSerialize};
+
+/// Much larger than a pipe's buffer, so it can't arrive all at once.
+const BLOB_SIZE: usize = 4 * 1024 * 1024;
+
+struct Blob(Vec<u8>);
+impl ViaductSerialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Blob {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(bytes.to_vec()))
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<Blob, (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), Blob, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, mut rx) = viaduct.split();
+
+			assert!(rx.is_blocking());
+			rx.set_nonblocking(true).unwrap();
+			assert!(!rx.is_blocking());
+
+			// The child hasn't sent anything yet
+			assert_eq!(rx.peek_packet_type().unwrap_err().kind(), ErrorKind::WouldBlock);
+
+			// Ask for the blob, and do something else until it starts arriving
+			tx.rpc(()).unwrap();
+			let mut polls = 0;
+			loop {
+				match rx.peek_packet_type() {
+					Ok(packet_type) => break assert_eq!(packet_type, PacketType::Rpc),
+					Err(err) if err.kind() == ErrorKind::WouldBlock => polls += 1,
+					Err(err) => panic!("{err}"),
+				}
+				std::thread::sleep(std::time::Duration::from_millis(1));
+			}
+			println!("[PARENT] Polled {polls} times before the blob started arriving");
+
+			// The event loop receives the blob in full, then stops when there's nothing left to receive
+			let mut received = 0;
+			let err = rx
+				.run(|event| match event {
+					ViaductEvent::Rpc(Blob(blob)) => {
+						assert!(blob.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+						assert_eq!(blob.len(), BLOB_SIZE);
+						received += 1;
+					}
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::WouldBlock);
+			assert_eq!(received, 1);
+			println!("[PARENT] Received the blob without blocking");
+
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+
+			let mut asked = false;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) if !asked => {
+					asked = true;
+					std::thread::sleep(std::time::Duration::from_millis(100));
+					tx.rpc(Blob((0..BLOB_SIZE).map(|i| i as u8).collect())).unwrap();
+				}
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
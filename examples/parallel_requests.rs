@@ -69,22 +69,26 @@ fn main() {
 			.spawn(|| {
 				println!("parent pid {:?}", std::process::id());
 
-				let ((tx, rx), mut child) = ViaductParent::<(), Add, (), Add>::new(Command::new(std::env::current_exe().unwrap()))
+				let (viaduct, mut child) = ViaductParent::<(), Add, (), Add>::new(Command::new(std::env::current_exe().unwrap()))
 					.unwrap()
 					.arg("Viaduct test!")
+					// Requests are answered on the pool, so hold the child back once a couple are waiting for an answer
+					.max_outstanding_responders(2)
 					.build()
 					.unwrap();
+				let (tx, rx) = viaduct.split();
 
 				let (shutdown_tx, shutdown_rx) = std::sync::mpsc::sync_channel(1);
 
 				std::thread::Builder::new()
 					.name("parent event loop".to_string())
 					.spawn(move || {
-						rx.run(|event| match event {
+						rx.run_pool(MATH_PROBLEMS.len(), |event| match event {
 							ViaductEvent::Rpc(_) => shutdown_tx.try_send(()).unwrap(),
 							ViaductEvent::Request { request, responder } => {
 								responder.respond(request.a + request.b).unwrap();
 							}
+							_ => unreachable!(),
 						})
 						.unwrap();
 					})
@@ -100,7 +104,8 @@ fn main() {
 			.unwrap(),
 
 		// We're the child process
-		Ok(((tx, rx), mut args)) => {
+		Ok((viaduct, mut args)) => {
+			let (tx, rx) = viaduct.split();
 			assert_eq!(args.nth(1).as_deref(), Some("Viaduct test!"));
 
 			std::thread::Builder::new()
@@ -118,6 +123,7 @@ fn main() {
 								ViaductEvent::Request { request, responder } => {
 									responder.respond(request.a + request.b).unwrap();
 								}
+								_ => unreachable!(),
 							})
 							.unwrap();
 						})
@@ -0,0 +1,83 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the client, which sends requests to the broker
+		Err(_) => {
+			let (viaduct, mut broker) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// The broker forwards our requests to the worker, and the worker's responses back to us
+			for i in 1..=100 {
+				assert_eq!(tx.request::<u32>(i).unwrap(), Some(i * 2));
+			}
+
+			// The worker doesn't respond to zero
+			assert_eq!(tx.request::<u32>(0).unwrap(), None);
+
+			// The context is passed on to the worker
+			assert_eq!(tx.request_with_context::<u32>("add one", 5).unwrap(), Some(11));
+
+			println!("[CLIENT] Requests were proxied to the worker");
+
+			tx.rpc(()).unwrap();
+			broker.wait().unwrap();
+		}
+
+		// We're the worker, which answers the requests
+		Ok(viaduct) if viaduct::args().any(|arg| arg == "worker") => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				ViaductEvent::Request { request, responder } => {
+					if request == 0 {
+						drop(responder);
+					} else if responder.context() == Some("add one") {
+						responder.respond(request * 2 + 1).unwrap();
+					} else {
+						responder.respond(request * 2).unwrap();
+					}
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+
+		// We're the broker, which sits between the client and the worker
+		Ok(viaduct) => {
+			let (worker_viaduct, mut worker) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.arg("worker")
+				.build()
+				.unwrap();
+			let (worker_tx, worker_rx) = worker_viaduct.split();
+
+			// The worker's responses are forwarded by this event loop
+			std::thread::spawn(move || worker_rx.run(|_| {}));
+
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => {
+					worker_tx.rpc(()).unwrap();
+					worker.wait().unwrap();
+					std::process::exit(0);
+				}
+				ViaductEvent::Request { request, responder } => responder.proxy_to(&worker_tx, request).unwrap(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,59 @@
+use std::process::Command;
+use viaduct::{RawBytes, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), RawBytes, RawBytes>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<RawBytes, RawBytes, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Bytes can be sent without copying them into a `Vec` first...
+			let message = "Hello, child!";
+			tx.rpc(RawBytes::from(message.as_bytes())).unwrap();
+
+			// ...and come out exactly as they went in, whatever the serialization backend
+			let reversed = tx.request::<RawBytes>(RawBytes::from(vec![1, 2, 3, 255])).unwrap().unwrap();
+			assert_eq!(&*reversed, [255, 3, 2, 1]);
+
+			let empty = tx.request::<RawBytes>(RawBytes::default()).unwrap().unwrap();
+			assert!(empty.is_empty());
+
+			println!("[PARENT] Received {:?}", reversed.into_vec());
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(bytes) => {
+					assert_eq!(std::str::from_utf8(&bytes).unwrap(), "Hello, child!");
+				}
+				ViaductEvent::Request { request, responder } => {
+					let done = request.is_empty();
+					let mut bytes = request.into_vec();
+					bytes.reverse();
+					responder.respond(RawBytes::from(bytes)).unwrap();
+					if done {
+						shutdown.shutdown();
+					}
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -17,7 +17,8 @@ fn main() {
 		println!("[CHILD] Reaper callback failed");
 		std::process::exit(1);
 	} else {
-		let (_, mut child) = std::thread::spawn(|| {
+		// Keep our side of the viaduct alive, otherwise the child will think we have gone away
+		let (_viaduct, mut child) = std::thread::spawn(|| {
 			ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
 				.unwrap()
 				.build()
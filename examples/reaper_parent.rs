@@ -3,7 +3,10 @@ use std::{process::Command, sync::Arc, time::Duration};
 use viaduct::{Never, ViaductChild, ViaductParent};
 
 fn main() {
-	if let Ok(_child) = unsafe { ViaductChild::<Never, Never, Never, Never>::new().build() } {
+	if let Ok(handshake) = unsafe { ViaductChild::<Never, Never, Never, Never>::new().build_deferred() } {
+		// The handshake happens in the background while we do the rest of our initialization
+		let _child = handshake.join().unwrap().unwrap();
+
 		println!("[CHILD] Exiting in 5 seconds...");
 		std::thread::sleep(Duration::from_secs(5));
 		println!("[CHILD] Goodbye!");
@@ -14,12 +17,17 @@ fn main() {
 
 		let (_, child) = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
 			.unwrap()
-			.with_reaper(move || {
+			// Notice the child exiting promptly, rather than up to 5 seconds later
+			.with_reaper_interval(Duration::from_millis(100))
+			.with_reaper(move |status| {
+				// The child exits cleanly, and we find out how it went without having to wait on it ourselves
+				assert!(status.expect("[PARENT] Child process' exit status wasn't found out").success());
+
 				std::thread::sleep(Duration::from_secs(1));
 				match shared_child_ref.lock().take().map(|mut child| child.try_wait()) {
 					Some(Ok(None)) => panic!("[PARENT] Child process exited too early"),
 					_ => {
-						println!("[PARENT] Reaper callback!");
+						println!("[PARENT] Reaper callback! ({})", status.unwrap());
 						std::process::exit(0)
 					}
 				}
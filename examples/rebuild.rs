@@ -0,0 +1,59 @@
+use std::process::Command;
+use viaduct::{CloseReason, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u8, u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let mut rebuilder = ViaductParent::<u8, u32, (), ()>::rebuilder(|| ViaductParent::new(Command::new(std::env::current_exe().unwrap())));
+
+			let (viaduct, mut child) = rebuilder.rebuild().unwrap();
+			let (old_tx, old_rx) = viaduct.split();
+			let old_event_loop = std::thread::spawn(move || old_rx.run(|_| {}));
+			assert_eq!(old_tx.request::<u32>(2).unwrap(), Some(4));
+
+			// Crash the child process
+			old_tx.rpc(1).unwrap();
+			assert!(!child.wait().unwrap().success());
+			assert!(old_event_loop.join().unwrap().is_err());
+
+			let (viaduct, mut child) = rebuilder.rebuild().unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}).ok());
+
+			// The old viaduct is no use any more...
+			assert!(old_tx.rpc(0).is_err());
+			assert!(old_tx.request::<u32>(3).is_err());
+			assert!(matches!(old_tx.close_reason(), Some(CloseReason::PeerDropped | CloseReason::Rebuilt)));
+
+			// ...but the new one is
+			assert_eq!(tx.request::<u32>(3).unwrap(), Some(9));
+			println!("[PARENT] Rebuilt the viaduct after the child process crashed");
+
+			// Tell the child to stop
+			tx.rpc(0).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(1) => std::process::exit(1),
+				ViaductEvent::Rpc(_) => shutdown.shutdown(),
+				ViaductEvent::Request { request, responder } => responder.respond(request * request).unwrap(),
+				#[allow(unreachable_patterns)]
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,81 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, mut rx) = viaduct.split();
+			rx.set_nonblocking(true).unwrap();
+
+			// Pump events ourselves, whenever the pipe becomes readable
+			let mut received = Vec::new();
+			while received.len() < 3 {
+				wait_readable(&rx);
+				loop {
+					match rx.recv_one() {
+						Ok(Some(ViaductEvent::Rpc(rpc))) => received.push(rpc),
+						Ok(_) => {}
+						Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+						Err(err) => panic!("{err}"),
+					}
+				}
+			}
+			assert_eq!(received, [1, 2, 3]);
+			println!("[PARENT] Received {received:?}");
+
+			// Tell the child to stop
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, mut rx) = viaduct.split();
+			for rpc in 1..=3 {
+				tx.rpc(rpc).unwrap();
+			}
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|_| shutdown.shutdown()).unwrap();
+		}
+	}
+}
+
+#[cfg(unix)]
+fn wait_readable<RpcTx, RequestTx, RpcRx, RequestRx>(rx: &viaduct::ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>)
+where
+	RpcTx: viaduct::ViaductSerialize,
+	RequestTx: viaduct::ViaductSerialize,
+	RpcRx: viaduct::ViaductDeserialize,
+	RequestRx: viaduct::ViaductDeserialize,
+{
+	let mut pollfd = libc::pollfd {
+		fd: rx.as_raw_reader(),
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	assert_ne!(unsafe { libc::poll(&mut pollfd, 1, -1) }, -1);
+}
+
+#[cfg(windows)]
+fn wait_readable<RpcTx, RequestTx, RpcRx, RequestRx>(_rx: &viaduct::ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>)
+where
+	RpcTx: viaduct::ViaductSerialize,
+	RequestTx: viaduct::ViaductSerialize,
+	RpcRx: viaduct::ViaductDeserialize,
+	RequestRx: viaduct::ViaductDeserialize,
+{
+	// Anonymous pipes can't be waited on, so just give the child a moment
+	std::thread::sleep(std::time::Duration::from_millis(1));
+}
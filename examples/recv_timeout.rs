@@ -0,0 +1,48 @@
+use std::{process::Command, time::Duration};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, mut rx) = viaduct.split();
+
+			// The child takes a while to send anything, so we can get other work done in the meantime
+			let mut timeouts = 0;
+			let rpc = loop {
+				match rx.recv_one_timeout(Duration::from_millis(10)).unwrap() {
+					Some(ViaductEvent::Rpc(rpc)) => break rpc,
+					Some(_) => unreachable!(),
+					None => timeouts += 1,
+				}
+			};
+			assert_eq!(rpc, 42);
+			assert!(timeouts > 0);
+			println!("[PARENT] Timed out {timeouts} times before receiving the RPC");
+
+			// Tell the child to stop
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, mut rx) = viaduct.split();
+			std::thread::sleep(Duration::from_millis(200));
+			tx.rpc(42).unwrap();
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|_| shutdown.shutdown()).unwrap();
+		}
+	}
+}
@@ -0,0 +1,63 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), u32, (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), u32>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			// The child asks us to double numbers for it, while it's handling our request
+			let event_loop = std::thread::spawn(move || {
+				rx.run(|event| match event {
+					ViaductEvent::Request { request, responder } => responder.respond(request * 2).unwrap(),
+					ViaductEvent::Rpc(()) => unreachable!(),
+					_ => unreachable!(),
+				})
+			});
+
+			assert_eq!(tx.request::<u32>(5).unwrap(), Some(20));
+			println!("[PARENT] The child answered our request with the help of its own");
+
+			// Tell the child to stop
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+			event_loop.join().unwrap().unwrap_err();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+
+			rx.run(|event| match event {
+				ViaductEvent::Request { request, responder } => {
+					// This would never get a response, as we're the ones who would receive it
+					let err = tx.request::<u32>(request).unwrap_err();
+					assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::RequestFromEventLoop)), "{err}");
+
+					// ...but another thread can wait for it while the event loop carries on
+					let tx = tx.clone();
+					std::thread::spawn(move || {
+						let doubled = tx.request::<u32>(request).unwrap().unwrap();
+						let quadrupled = tx.request::<u32>(doubled).unwrap().unwrap();
+						responder.respond(quadrupled).unwrap();
+					});
+				}
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
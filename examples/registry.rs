@@ -0,0 +1,98 @@
+use std::{process::Command, sync::mpsc};
+use viaduct::{ViaductChild, ViaductParent};
+
+/// An even number, handled by its own handler.
+struct Even(u32);
+impl TryFrom<u32> for Even {
+	type Error = u32;
+
+	fn try_from(n: u32) -> Result<Self, u32> {
+		if n.is_multiple_of(2) {
+			Ok(Even(n))
+		} else {
+			Err(n)
+		}
+	}
+}
+
+/// An odd number, handled by its own handler.
+struct Odd(u32);
+impl TryFrom<u32> for Odd {
+	type Error = u32;
+
+	fn try_from(n: u32) -> Result<Self, u32> {
+		if !n.is_multiple_of(2) {
+			Ok(Odd(n))
+		} else {
+			Err(n)
+		}
+	}
+}
+
+/// A request small enough to be squared; anything else has no handler.
+struct Square(u32);
+impl TryFrom<u32> for Square {
+	type Error = u32;
+
+	fn try_from(n: u32) -> Result<Self, u32> {
+		if n < 1000 {
+			Ok(Square(n))
+		} else {
+			Err(n)
+		}
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			for n in 0..10 {
+				tx.rpc(n).unwrap();
+			}
+
+			assert_eq!(tx.request::<u32>(12).unwrap(), Some(144));
+			assert_eq!(tx.request::<u32>(1000).unwrap(), None);
+			println!("[PARENT] Requests were routed by the registry");
+
+			// Tell the child to exit
+			tx.rpc(u32::MAX).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			let (evens_tx, evens_rx) = mpsc::channel();
+			let (odds_tx, odds_rx) = mpsc::channel();
+
+			rx.on::<Even>(move |Even(n)| evens_tx.send(n).unwrap())
+				.on::<Odd>(move |Odd(n)| {
+					if n == u32::MAX {
+						assert_eq!(evens_rx.try_iter().collect::<Vec<_>>(), [0, 2, 4, 6, 8]);
+						assert_eq!(odds_rx.try_iter().collect::<Vec<_>>(), [1, 3, 5, 7, 9]);
+						println!("[CHILD] RPCs were routed by the registry");
+						std::process::exit(0);
+					}
+					odds_tx.send(n).unwrap();
+				})
+				.on_request::<Square>(|Square(n), responder| responder.respond(n * n).unwrap());
+
+			rx.run_registry().unwrap();
+		}
+	}
+}
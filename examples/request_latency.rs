@@ -0,0 +1,62 @@
+use parking_lot::Mutex;
+use std::{process::Command, sync::Arc, time::Duration};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			let latencies = Arc::new(Mutex::new(Vec::new()));
+			tx.on_request_complete({
+				let latencies = latencies.clone();
+				move |latency| latencies.lock().push(latency)
+			});
+
+			for delay in [10, 20, 30] {
+				assert_eq!(tx.request::<u32>(delay).unwrap(), Some(delay));
+			}
+
+			// Requests that time out aren't reported
+			tx.request_timeout::<u32>(Duration::from_millis(10), 100).unwrap_err();
+
+			let latencies = latencies.lock().clone();
+			println!("[PARENT] Round-trip latencies: {latencies:?}");
+
+			assert_eq!(latencies.len(), 3);
+			for (latency, delay) in latencies.into_iter().zip([10, 20, 30]) {
+				assert!(latency >= Duration::from_millis(delay));
+			}
+
+			tx.rpc(()).unwrap();
+			child.wait().unwrap();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				ViaductEvent::Request { request, responder } => {
+					std::thread::sleep(Duration::from_millis(request as u64));
+					responder.respond(request).unwrap();
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,76 @@
+use std::{process::Command, time::Duration};
+use viaduct::{Priority, ViaductChild, ViaductEvent, ViaductParent};
+
+const PRIORITIES: [Priority; 4] = [Priority::Low, Priority::Normal, Priority::High, Priority::Urgent];
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u8>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u8, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Keep the child busy while the other requests queue up behind this one
+			let busy = {
+				let tx = tx.clone();
+				std::thread::spawn(move || tx.request::<u32>(u8::MAX).unwrap().unwrap())
+			};
+			std::thread::sleep(Duration::from_millis(100));
+
+			let requests = (0..3)
+				.flat_map(|_| PRIORITIES)
+				.map(|priority| {
+					let tx = tx.clone();
+					std::thread::spawn(move || (priority, tx.request_priority::<u32>(priority, priority as u8).unwrap().unwrap()))
+				})
+				.collect::<Vec<_>>();
+
+			assert_eq!(busy.join().unwrap(), 0);
+
+			let mut handled = requests.into_iter().map(|request| request.join().unwrap()).collect::<Vec<_>>();
+			handled.sort_by_key(|&(_, order)| order);
+			println!("[PARENT] Requests were handled in this order: {handled:?}");
+
+			// Everything that was waiting was handled in order of priority
+			assert!(handled.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+
+			let mut handled = 0;
+			rx.run_prioritized(|event| match event {
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+
+				ViaductEvent::Request { request: u8::MAX, responder } => {
+					assert_eq!(responder.priority(), Priority::Normal);
+					std::thread::sleep(Duration::from_millis(500));
+					responder.respond(0u32).unwrap();
+				}
+
+				ViaductEvent::Request { request, responder } => {
+					assert_eq!(responder.priority() as u8, request);
+					handled += 1;
+					responder.respond(handled as u32).unwrap();
+				}
+
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,74 @@
+use std::{io::ErrorKind, process::Command, time::Duration};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+/// Tells the child to sleep for a while and then drop the responder without responding.
+const DROP_RESPONDER: u32 = u32::MAX;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			for _ in 0..5 {
+				// The child will respond after we've stopped waiting
+				let err = tx.request_timeout::<u32>(Duration::from_millis(50), 300).unwrap_err();
+				assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+				// The late response must be discarded, and must not be mistaken for the response to this request
+				assert_eq!(tx.request::<u32>(1).unwrap(), Some(1));
+
+				// The child will drop the responder after we've stopped waiting
+				let err = tx.request_timeout::<u32>(Duration::from_millis(50), DROP_RESPONDER).unwrap_err();
+				assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+				assert_eq!(tx.request::<u32>(2).unwrap(), Some(2));
+			}
+
+			// Time out right around when the response arrives to exercise the race between the two
+			for i in 0..50 {
+				match tx.request_timeout::<u32>(Duration::from_millis(10), 10) {
+					Ok(response) => assert_eq!(response, Some(10)),
+					Err(err) => assert_eq!(err.kind(), ErrorKind::TimedOut),
+				}
+				assert_eq!(tx.request::<u32>(i).unwrap(), Some(i));
+			}
+
+			println!("[PARENT] Late responses were discarded");
+
+			tx.rpc(()).unwrap();
+			child.wait().unwrap();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				ViaductEvent::Request { request, responder } => {
+					if request == DROP_RESPONDER {
+						std::thread::sleep(Duration::from_millis(300));
+						drop(responder);
+					} else {
+						std::thread::sleep(Duration::from_millis(request as u64));
+						responder.respond(request).unwrap();
+					}
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
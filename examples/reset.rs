@@ -0,0 +1,63 @@
+use std::{io::ErrorKind, process::Command, time::Duration};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			for _ in 0..5 {
+				// The child takes a long time to answer this request, so give up on it from another thread
+				let waiting = std::thread::spawn({
+					let tx = tx.clone();
+					move || tx.request::<u32>(500)
+				});
+
+				std::thread::sleep(Duration::from_millis(100));
+				tx.reset();
+
+				let err = waiting.join().unwrap().unwrap_err();
+				assert_eq!(err.kind(), ErrorKind::ConnectionAborted);
+
+				// The late response must be discarded, and must not be mistaken for the response to this request
+				assert_eq!(tx.request::<u32>(1).unwrap(), Some(1));
+			}
+
+			// Resetting with nothing pending is harmless
+			tx.reset();
+			assert_eq!(tx.request::<u32>(2).unwrap(), Some(2));
+
+			println!("[PARENT] Abandoned requests were reset");
+
+			tx.rpc(()).unwrap();
+			child.wait().unwrap();
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => std::process::exit(0),
+				ViaductEvent::Request { request, responder } => {
+					std::thread::sleep(Duration::from_millis(request as u64));
+					responder.respond(request).unwrap();
+				}
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
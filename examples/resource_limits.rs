@@ -0,0 +1,40 @@
+#[cfg(unix)]
+fn main() {
+	use std::process::Command;
+	use viaduct::{Never, ResourceLimits, ViaductChild, ViaductParent};
+
+	const OPEN_FILES: u64 = 64;
+
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<Never, Never, Never, Never>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (_, mut child) = ViaductParent::<Never, Never, Never, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.resource_limits(ResourceLimits::new().open_files(OPEN_FILES).core_size(0))
+				.build()
+				.unwrap();
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(_) => {
+			let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+			assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) }, 0);
+			assert_eq!(limit.rlim_cur, OPEN_FILES as libc::rlim_t);
+			assert_eq!(limit.rlim_max, OPEN_FILES as libc::rlim_t);
+			println!("[CHILD] Limited to {} open files", limit.rlim_cur);
+		}
+	}
+}
+
+#[cfg(not(unix))]
+fn main() {
+	println!("Resource limits are only supported on Unix");
+}
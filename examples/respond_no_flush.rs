@@ -0,0 +1,32 @@
+use viaduct::{
+	test_util::{self, Sent},
+	ViaductEvent,
+};
+
+const REQUESTS: u32 = 10;
+
+fn main() {
+	let (viaduct, written) = test_util::in_memory::<(), (), (), u32>();
+	let (tx, rx) = viaduct.split();
+	tx.set_no_delay(false).unwrap();
+
+	let mut frames = Vec::new();
+	for n in 0..REQUESTS {
+		frames.extend(test_util::request_frame(&n));
+	}
+
+	// Answer a burst of requests, letting the responses pile up in the write buffer
+	rx.run_from_reader(std::io::Cursor::new(frames), |event| match event {
+		ViaductEvent::Request { request, responder } => responder.respond_no_flush(request * 2).unwrap(),
+		ViaductEvent::Rpc(_) => unreachable!(),
+		_ => unreachable!(),
+	})
+	.unwrap();
+	assert!(written.take().is_empty());
+
+	// Then send them all at once
+	tx.flush().unwrap();
+	let responses = written.decode::<(), (), u32>();
+	assert_eq!(responses, (0..REQUESTS).map(|n| Sent::Response(Some(n * 2))).collect::<Vec<_>>());
+	println!("Flushed {} responses in one go", responses.len());
+}
@@ -0,0 +1,53 @@
+use std::process::Command;
+use viaduct::{ViaductChild, ViaductLazyEvent, ViaductParent};
+
+/// A large request that we don't want to deserialize just to add it up.
+#[derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+struct Samples {
+	values: Vec<u32>,
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), Samples>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), Samples, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			let values = (0..100_000).collect::<Vec<u32>>();
+			let expected = values.iter().map(|&value| u64::from(value)).sum::<u64>();
+
+			let sum = tx.request::<u64>(Samples { values }).unwrap().unwrap();
+			assert_eq!(sum, expected);
+			println!("[PARENT] The child added up the samples without deserializing them: {sum}");
+
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run_lazy(|event| match event {
+				ViaductLazyEvent::Request { request, responder } => {
+					let samples = request.archived();
+					let sum = samples.values.iter().map(|value| u64::from(value.to_native())).sum::<u64>();
+					responder.respond(sum).unwrap();
+				}
+				ViaductLazyEvent::Rpc(_) => std::process::exit(0),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
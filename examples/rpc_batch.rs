@@ -0,0 +1,66 @@
+use std::process::Command;
+use viaduct::{PacketType, ViaductChild, ViaductEvent, ViaductParent};
+
+const MESSAGES: u32 = 10_000;
+const BATCH_SIZE: u32 = 100;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().max_fragment_size(256).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.max_fragment_size(256)
+				.build()
+				.unwrap();
+			let (tx, _rx) = viaduct.split();
+
+			for batch in (0..MESSAGES).step_by(BATCH_SIZE as usize) {
+				if batch % (BATCH_SIZE * 10) == 0 {
+					// Single RPCs stay in order with the batches around them
+					tx.rpc(batch).unwrap();
+					tx.rpc_batch(batch + 1..batch + BATCH_SIZE).unwrap();
+				} else {
+					tx.rpc_batch(batch..batch + BATCH_SIZE).unwrap();
+				}
+			}
+
+			// Empty batches aren't sent at all
+			tx.rpc_batch([]).unwrap();
+
+			tx.rpc(u32::MAX).unwrap();
+			assert!(child.wait().unwrap().success());
+			println!("[PARENT] The child received every batched RPC in order");
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+
+			// Peeking sees the batch's RPCs, one at a time
+			assert_eq!(rx.peek_packet_type().unwrap(), PacketType::Rpc);
+
+			let mut expected = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(u32::MAX) => {
+					assert_eq!(expected, MESSAGES);
+					std::process::exit(0);
+				}
+
+				ViaductEvent::Rpc(i) => {
+					assert_eq!(i, expected);
+					expected += 1;
+				}
+
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,75 @@
+use std::{io::ErrorKind, process::Command, time::Duration};
+use viaduct::{TrySendError, ViaductChild, ViaductEvent, ViaductParent};
+
+const CREDITS: usize = 8;
+const RPCS: u32 = 1000;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().rpc_credits(CREDITS).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			// Credits are granted back to our event loop
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// The child doesn't start its event loop for a while, so we can only get so far ahead of it
+			assert_eq!(tx.rpc_credits(), Some(CREDITS as u64));
+			for i in 0..CREDITS as u32 {
+				tx.rpc(i).unwrap();
+			}
+			assert_eq!(tx.rpc_credits(), Some(0));
+			assert_eq!(tx.rpc_ref(&(CREDITS as u32)).unwrap_err().kind(), ErrorKind::WouldBlock);
+			assert!(matches!(tx.try_rpc(CREDITS as u32), Err(TrySendError::WouldBlock(rpc)) if rpc == CREDITS as u32));
+			println!("[PARENT] Ran out of credits after {CREDITS} RPCs");
+
+			let mut would_block = 0;
+			for i in CREDITS as u32..=RPCS {
+				loop {
+					match tx.rpc_ref(&i) {
+						Ok(()) => break,
+						Err(err) if err.kind() == ErrorKind::WouldBlock => {
+							would_block += 1;
+							std::thread::sleep(Duration::from_millis(1));
+						}
+						Err(err) => panic!("{err}"),
+					}
+				}
+			}
+			println!("[PARENT] Sent {RPCS} RPCs, waiting for credits {would_block} times");
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			std::thread::sleep(Duration::from_millis(500));
+
+			let shutdown = rx.shutdown_handle().unwrap();
+			let mut expected = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(i) => {
+					assert_eq!(i, expected);
+					expected += 1;
+					if i == RPCS {
+						shutdown.shutdown();
+					}
+				}
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
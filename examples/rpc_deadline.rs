@@ -0,0 +1,72 @@
+use std::{
+	borrow::Cow,
+	process::Command,
+	time::{Duration, Instant},
+};
+use viaduct::{RawBytes, ViaductChild, ViaductEvent, ViaductParent};
+
+/// Small enough to be written to the pipe in one go, so that RPCs are dropped rather than blocking partway through.
+type Frame = RawBytes<'static>;
+
+const FRAME: Frame = RawBytes(Cow::Borrowed(&[0; 1024]));
+
+/// Tells the child to exit.
+const LAST_FRAME: Frame = RawBytes(Cow::Borrowed(&[1; 1024]));
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), Frame, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Frame, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// The child isn't reading yet, so the pipe fills up and stale frames start being dropped instead of blocking
+			let mut sent = 0;
+			let mut dropped = 0;
+			while dropped < 10 {
+				if tx.rpc_deadline(Instant::now() + Duration::from_millis(10), FRAME).unwrap() {
+					sent += 1;
+				} else {
+					dropped += 1;
+				}
+			}
+			println!("[PARENT] Sent {sent} frames, dropped {dropped} once the pipe was full");
+
+			// This must get through once the child starts reading again
+			tx.rpc(LAST_FRAME).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			std::thread::sleep(Duration::from_millis(500));
+
+			let mut received = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(frame) if frame == LAST_FRAME => {
+					println!("[CHILD] Received {received} frames");
+					std::process::exit(0);
+				}
+				ViaductEvent::Rpc(frame) => {
+					assert_eq!(frame, FRAME);
+					received += 1;
+				}
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,58 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{Never, RpcOnly, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			// A misbehaving parent, which sends requests that the child never receives
+			let (viaduct, mut child) = ViaductParent::<u32, u32, u8, Never>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			let event_loop = std::thread::spawn(move || {
+				let mut received = 0;
+				rx.run(|event| match event {
+					ViaductEvent::Rpc(rpc) => received += rpc as u32,
+					ViaductEvent::Request { .. } => unreachable!(),
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+				received
+			});
+
+			for i in 1..=3 {
+				tx.rpc(i).unwrap();
+			}
+
+			// The child refuses the request instead of panicking, so it's never answered
+			assert!(tx.request::<u32>(4).is_err());
+			assert_eq!(event_loop.join().unwrap(), 6);
+			assert!(child.wait().unwrap().success());
+			println!("[PARENT] The child refused our request");
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let viaduct: RpcOnly<u8, u32> = viaduct;
+			let (tx, rx) = viaduct.split();
+
+			let err = rx
+				.run(|event| match event {
+					ViaductEvent::Rpc(rpc) => tx.rpc(rpc as u8).unwrap(),
+					ViaductEvent::Request { request, .. } => match request {},
+					_ => unreachable!(),
+				})
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidData);
+			println!("[CHILD] {err}");
+		}
+	}
+}
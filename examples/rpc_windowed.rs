@@ -0,0 +1,86 @@
+use std::{
+	process::Command,
+	time::{Duration, Instant},
+};
+use viaduct::{ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+const MESSAGES: u32 = 200;
+const WINDOW: usize = 8;
+const HANDLING_TIME: Duration = Duration::from_millis(1);
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().rpc_window(WINDOW).build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.rpc_window(WINDOW)
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			// Acknowledgements are received by our event loop
+			let event_loop = std::thread::spawn(move || rx.run(|_| {}));
+
+			let start = Instant::now();
+			for i in 0..MESSAGES {
+				tx.rpc_windowed(i).unwrap();
+			}
+			let elapsed = start.elapsed();
+
+			// All of these would fit in the pipe, so without the window we'd finish straight away
+			let throttled = HANDLING_TIME * (MESSAGES - 2 * WINDOW as u32);
+			assert!(elapsed >= throttled, "sending took {elapsed:?}, expected at least {throttled:?}");
+			println!("[PARENT] Sending was held back to the child's pace ({elapsed:?})");
+
+			assert!(child.wait().unwrap().success());
+
+			// No more acknowledgements can arrive once the event loop has stopped, so this fails instead of waiting forever
+			event_loop.join().unwrap().unwrap_err();
+			for i in 0..=WINDOW as u32 {
+				if tx.rpc_windowed(i).is_err() {
+					break;
+				}
+				assert!(
+					i < WINDOW as u32,
+					"the window filled up after the event loop stopped, but sending didn't fail"
+				);
+			}
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+
+			let mut received = 0;
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(i) => {
+					if i == 0 {
+						// Our own acknowledgements would be received by this event loop, so it can't wait for them
+						let err = tx.rpc_windowed(()).unwrap_err();
+						assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::RequestFromEventLoop)), "{err}");
+					}
+
+					assert_eq!(i, received);
+					received += 1;
+
+					// A slow consumer
+					std::thread::sleep(HANDLING_TIME);
+
+					if received == MESSAGES {
+						std::process::exit(0);
+					}
+				}
+				ViaductEvent::Request { .. } => unreachable!(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
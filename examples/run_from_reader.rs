@@ -0,0 +1,60 @@
+use viaduct::{
+	test_util::{self, Sent},
+	Priority, ViaductEvent,
+};
+
+fn main() {
+	// Nothing here spawns a process or a thread, so the outcome is the same every time
+	let (viaduct, written) = test_util::in_memory::<u32, u32, u32, u32>();
+	let (tx, rx) = viaduct.split();
+
+	let mut frames = Vec::new();
+	for n in 1..=3 {
+		frames.extend(test_util::rpc_frame(&n));
+	}
+	frames.extend(test_util::request_frame(&12u32));
+	frames.extend(test_util::request_frame(&0u32));
+
+	let mut sum = 0;
+	rx.run_from_reader(std::io::Cursor::new(frames), |event| match event {
+		ViaductEvent::Rpc(n) => sum += n,
+
+		ViaductEvent::Request { request, responder } => {
+			// Requests for zero are dropped without a response
+			if request != 0 {
+				responder.respond(request * request).unwrap();
+			}
+		}
+
+		_ => unreachable!(),
+	})
+	.unwrap();
+
+	assert_eq!(sum, 6);
+	assert_eq!(written.decode::<u32, u32, u32>(), [Sent::Response(Some(144)), Sent::Response(None)]);
+
+	// A batch is decoded into the RPCs it's made up of
+	tx.rpc_batch([4, 5]).unwrap();
+	assert_eq!(written.decode::<u32, u32, u32>(), [Sent::Rpc(4), Sent::Rpc(5)]);
+
+	// The viaduct above closed when its reader ran out, so requests need one that is still open
+	let (viaduct, written) = test_util::in_memory::<u32, u32, u32, u32>();
+	let (tx, rx) = viaduct.split();
+
+	// Nothing will ever respond to this request, so it waits on another thread until the event loop runs out of input
+	let requester = {
+		let tx = tx.clone();
+		std::thread::spawn(move || tx.request_priority::<u32>(Priority::High, 7))
+	};
+	let sent = loop {
+		let sent = written.decode::<u32, u32, u32>();
+		if !sent.is_empty() {
+			break sent;
+		}
+		std::thread::yield_now();
+	};
+	assert_eq!(sent, [Sent::Request(7)]);
+	rx.run_from_reader(std::io::empty(), |_| {}).unwrap();
+	requester.join().unwrap().unwrap_err();
+	println!("Handled every packet");
+}
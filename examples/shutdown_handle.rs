@@ -0,0 +1,56 @@
+use std::{process::Command, time::Duration};
+use viaduct::{CloseReason, ViaductChild, ViaductEvent, ViaductParent};
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			let event_loop = std::thread::spawn(move || rx.run(|_| unreachable!()));
+
+			// The child hasn't sent anything, so our event loop is blocked waiting on it...
+			std::thread::sleep(Duration::from_millis(100));
+			assert!(!event_loop.is_finished());
+
+			// ...until we shut it down from this thread
+			shutdown.shutdown();
+			event_loop.join().unwrap().unwrap();
+			assert!(shutdown.is_shutdown());
+			assert!(matches!(tx.close_reason(), Some(CloseReason::LocalClose)));
+			println!("[PARENT] The event loop stopped: {}", tx.close_reason().unwrap());
+
+			// We can still send, so tell the child to stop too
+			tx.rpc(()).unwrap();
+
+			drop(tx);
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+
+			// Shutting down from inside the event handler stops the event loop once it returns
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+
+			println!("[CHILD] The event loop stopped");
+		}
+	}
+}
@@ -0,0 +1,64 @@
+use std::{io::ErrorKind, process::Command, sync::mpsc};
+use viaduct::{ViaductChild, ViaductError, ViaductEvent, ViaductParent};
+
+const MESSAGES: u32 = 100;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<u32, (), u32, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), u32, ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			let (received_tx, received_rx) = mpsc::channel();
+			std::thread::spawn(move || {
+				rx.run(|event| {
+					if let ViaductEvent::Rpc(received) = event {
+						received_tx.send(received).unwrap();
+					}
+				})
+			});
+
+			for i in 0..MESSAGES {
+				tx.rpc(i).unwrap();
+			}
+
+			// We're done sending, but the child still has something to tell us
+			tx.shutdown_send().unwrap();
+			assert_eq!(tx.rpc(0).unwrap_err().kind(), ErrorKind::BrokenPipe);
+
+			assert_eq!(received_rx.recv().unwrap(), MESSAGES);
+			println!("[PARENT] Child received all {MESSAGES} RPCs before the end of the stream");
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (tx, rx) = viaduct.split();
+			let mut received = 0;
+			let err = rx
+				.run(|event| {
+					if let ViaductEvent::Rpc(i) = event {
+						assert_eq!(i, received);
+						received += 1;
+					}
+				})
+				.unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+			assert!(matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)));
+
+			// Our side of the viaduct still works
+			tx.rpc(received).unwrap();
+		}
+	}
+}
@@ -0,0 +1,36 @@
+use viaduct::ViaductEvent;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	unsafe {
+		viaduct::split::<(), u32, (), (), _>(
+			// We're the parent process
+			|viaduct, mut child| {
+				let (tx, rx) = viaduct.split();
+				std::thread::spawn(move || rx.run(|_| {}));
+
+				assert_eq!(tx.request::<u32>(20).unwrap(), Some(21));
+				println!("[PARENT] The child answered");
+
+				tx.rpc(()).unwrap();
+				assert!(child.wait().unwrap().success());
+			},
+			// We're the child process
+			|viaduct| {
+				let (_tx, rx) = viaduct.split();
+				rx.run(|event| match event {
+					ViaductEvent::Rpc(()) => std::process::exit(0),
+					ViaductEvent::Request { request, responder } => responder.respond(request + 1).unwrap(),
+					_ => unreachable!(),
+				})
+				.unwrap();
+			},
+		)
+	}
+	.unwrap();
+}
@@ -0,0 +1,85 @@
+use std::{io::ErrorKind, process::Command};
+use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+/// Tells the child to respond with a single response instead of a stream.
+const RESPOND_ONCE: u32 = 0;
+
+/// Tells the child to drop the responder without responding.
+const DROP_RESPONDER: u32 = 1;
+
+/// Tells the child to drop the sink partway through the stream.
+const DROP_SINK: u32 = 2;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), (), u32>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<(), u32, (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			let chunks = tx.request_stream::<u32>(1000).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+			assert_eq!(chunks, (0..1000).collect::<Vec<_>>());
+			println!("[PARENT] Received {} chunks", chunks.len());
+
+			let collect = |request| tx.request_stream::<u32>(request).unwrap().map(Result::unwrap).collect::<Vec<_>>();
+			assert_eq!(collect(RESPOND_ONCE), [7]);
+			assert_eq!(collect(DROP_RESPONDER), Vec::<u32>::new());
+			assert_eq!(collect(DROP_SINK), [0, 1, 2]);
+
+			// The rest of an abandoned stream must be discarded, and must not be mistaken for the response to the next request
+			let first = tx.request_stream::<u32>(1000).unwrap().take(5).map(Result::unwrap).collect::<Vec<_>>();
+			assert_eq!(first, [0, 1, 2, 3, 4]);
+			assert_eq!(tx.request::<u32>(RESPOND_ONCE).unwrap(), Some(7));
+
+			// A request that isn't expecting a stream fails instead of hanging
+			let err = tx.request::<u32>(1000).unwrap_err();
+			assert_eq!(err.kind(), ErrorKind::InvalidData);
+			assert_eq!(collect(3), [0, 1, 2]);
+
+			println!("[PARENT] Streams ended as expected");
+
+			// Tell the child to stop
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => shutdown.shutdown(),
+				ViaductEvent::Request { request, responder } => match request {
+					RESPOND_ONCE => responder.respond(7u32).unwrap(),
+					DROP_RESPONDER => drop(responder),
+					DROP_SINK => {
+						let mut sink = responder.respond_stream().unwrap();
+						for i in 0..3u32 {
+							sink.send(i).unwrap();
+						}
+					}
+					len => {
+						let mut sink = responder.respond_stream().unwrap();
+						for i in 0..len {
+							sink.send(i).unwrap();
+						}
+						sink.end().unwrap();
+					}
+				},
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,81 @@
+#[cfg(unix)]
+fn main() {
+	use std::{net::TcpListener, process::Command, time::Duration};
+	use viaduct::{ViaductChild, ViaductEvent, ViaductParent};
+
+	const ADDR_ENV: &str = "VIADUCT_EXAMPLE_TCP_ADDR";
+
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match std::env::var(ADDR_ENV) {
+		// We're the parent process
+		Err(_) => {
+			// Find a free port to listen on
+			let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+			// The "child" process is started separately, just as if it were being attached to from a debugger
+			let mut child = Command::new(std::env::current_exe().unwrap())
+				.env(ADDR_ENV, addr.to_string())
+				.spawn()
+				.unwrap();
+
+			let viaduct = ViaductParent::<(), u32, (), u64>::new(Command::new("unused"))
+				.unwrap()
+				.with_handshake_timeout(Duration::from_secs(10))
+				.listen_tcp(addr)
+				.unwrap();
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			let event_loop = std::thread::spawn(move || {
+				rx.run(|event| match event {
+					ViaductEvent::Rpc(()) => shutdown.shutdown(),
+					ViaductEvent::Request { .. } => unreachable!(),
+					_ => unreachable!(),
+				})
+			});
+
+			let doubled: u64 = tx.request(21).unwrap().unwrap();
+			assert_eq!(doubled, 42);
+			println!("[PARENT] Child doubled 21 over TCP: {doubled}");
+
+			// Handles can't be shared over TCP
+			assert!(!tx.peer_supports(viaduct::Capability::HandlePassing));
+
+			tx.rpc(()).unwrap();
+			assert!(child.wait().unwrap().success());
+			event_loop.join().unwrap().unwrap();
+		}
+
+		// We're the child process
+		Ok(addr) => {
+			// The parent process may not be listening yet
+			let viaduct = loop {
+				match ViaductChild::<(), u64, (), u32>::new().connect_tcp(&*addr) {
+					Ok(viaduct) => break viaduct,
+					Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => std::thread::sleep(Duration::from_millis(10)),
+					Err(err) => panic!("{err}"),
+				}
+			};
+
+			let (tx, mut rx) = viaduct.split();
+			let shutdown = rx.shutdown_handle().unwrap();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(()) => {
+					// Tell the parent we're done, then stop
+					tx.rpc(()).unwrap();
+					shutdown.shutdown();
+				}
+				ViaductEvent::Request { request, responder } => responder.respond(request as u64 * 2).unwrap(),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
+
+#[cfg(not(unix))]
+fn main() {}
@@ -0,0 +1,60 @@
+use std::{process::Command, time::Duration};
+use viaduct::{Timestamp, ViaductChild, ViaductEvent, ViaductParent};
+
+const MESSAGES: u32 = 100;
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), u32, ()>::new().timestamps().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<u32, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.timestamps()
+				.build()
+				.unwrap();
+			let (tx, _rx) = viaduct.split();
+
+			for i in 0..MESSAGES {
+				tx.rpc(i).unwrap();
+			}
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			let before = Timestamp::now();
+
+			let mut received = 0;
+			let mut total = Duration::ZERO;
+			let mut last = None;
+			rx.run_timestamped(|event, sent_at| {
+				let sent_at = sent_at.expect("Packets should be timestamped");
+				assert!(last.is_none_or(|last| sent_at >= last), "Timestamps should never go backwards");
+				last = Some(sent_at);
+
+				if let ViaductEvent::Rpc(i) = event {
+					assert_eq!(i, received);
+					total += sent_at.elapsed();
+					received += 1;
+
+					if received == MESSAGES {
+						println!("[CHILD] Average transit time: {:?}", total / MESSAGES);
+
+						// Both processes read the same clock
+						assert!(Timestamp::now().duration_since(before) < Duration::from_secs(30));
+						std::process::exit(0);
+					}
+				}
+			})
+			.unwrap();
+		}
+	}
+}
@@ -0,0 +1,75 @@
+use std::{process::Command, time::Duration};
+use viaduct::{TrySendError, ViaductChild, ViaductDeserialize, ViaductEvent, ViaductParent, ViaductSerialize};
+
+/// An RPC that takes a while to serialize, holding up anything else that wants to send.
+#[derive(Debug, PartialEq, Eq)]
+struct Update(u8);
+impl ViaductSerialize for Update {
+	type Error = std::convert::Infallible;
+
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		if self.0 == 0 {
+			std::thread::sleep(Duration::from_millis(500));
+		}
+		buf.push(self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for Update {
+	type Error = std::convert::Infallible;
+
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(bytes[0]))
+	}
+}
+
+fn main() {
+	std::thread::spawn(|| {
+		// If something is wrong, main will block forever. So kill it after 30 seconds.
+		std::thread::sleep(std::time::Duration::from_secs(30));
+		std::process::exit(33);
+	});
+
+	match unsafe { ViaductChild::<(), (), Update, ()>::new().build() } {
+		// We're the parent process
+		Err(_) => {
+			let (viaduct, mut child) = ViaductParent::<Update, (), (), ()>::new(Command::new(std::env::current_exe().unwrap()))
+				.unwrap()
+				.build()
+				.unwrap();
+			let (tx, rx) = viaduct.split();
+			std::thread::spawn(move || rx.run(|_| {}));
+
+			// Another thread is busy sending a slow RPC...
+			let slow = {
+				let tx = tx.clone();
+				std::thread::spawn(move || tx.rpc(Update(0)).unwrap())
+			};
+			std::thread::sleep(Duration::from_millis(100));
+
+			// ...so rather than waiting for it, we get our RPC back
+			match tx.try_rpc(Update(1)) {
+				Err(TrySendError::WouldBlock(update)) => assert_eq!(update, Update(1)),
+				result => panic!("expected the RPC to be handed back, got {result:?}"),
+			}
+			println!("[PARENT] Didn't block while another thread was sending");
+
+			// Once it's done, the RPC goes through
+			slow.join().unwrap();
+			tx.try_rpc(Update(1)).unwrap();
+
+			assert!(child.wait().unwrap().success());
+		}
+
+		// We're the child process
+		Ok(viaduct) => {
+			let (_tx, rx) = viaduct.split();
+			rx.run(|event| match event {
+				ViaductEvent::Rpc(Update(0)) => {}
+				ViaductEvent::Rpc(Update(1)) => std::process::exit(0),
+				_ => unreachable!(),
+			})
+			.unwrap();
+		}
+	}
+}
@@ -1,5 +1,5 @@
 use std::process::Command;
-use viaduct::{ViaductChild, ViaductDeserialize, ViaductEvent, ViaductParent, ViaductSerialize};
+use viaduct::{Capability, ViaductChild, ViaductError, ViaductEvent, ViaductParent};
 
 fn main() {
 	std::thread::spawn(|| {
@@ -17,19 +17,25 @@ fn main() {
 			.spawn(|| {
 				println!("parent pid {:?}", std::process::id());
 
-				let ((tx, rx), mut child) =
+				let (viaduct, mut child) =
 					ViaductParent::<DummyRpcParentToChild, DummyRequestParentToChild, DummyRpcChildToParent, DummyRequestChildToParent>::new(
 						Command::new(std::env::current_exe().unwrap()),
 					)
 					.unwrap()
 					.arg("Viaduct test!")
+					.on_raw_recv(|packet_type, payload| println!("[PARENT] Raw {packet_type:?} received: {} bytes", payload.len()))
 					.build()
 					.unwrap();
+				let (tx, rx) = viaduct.split();
 
-				std::thread::Builder::new()
+				// Both sides are running the same build, so they support the same capabilities
+				assert!(tx.peer_supports(Capability::Fragmentation));
+				assert!(tx.peer_supports(Capability::RequestContext));
+
+				let event_loop = std::thread::Builder::new()
 					.name("parent event loop".to_string())
 					.spawn(move || {
-						rx.run(|event| match event {
+						let result = rx.run(|event| match event {
 							ViaductEvent::Rpc(rpc) => {
 								assert_eq!(rpc.magic, 321);
 								println!("[PARENT] RPC received: {}", rpc.magic);
@@ -39,13 +45,29 @@ fn main() {
 								println!("[PARENT] Request received: {}", request.magic);
 								responder.respond(DummyResponseParentToChild { magic: (420, 69) }).unwrap();
 							}
-						})
-						.unwrap();
+							_ => unreachable!(),
+						});
+
+						// The event loop ends when the child exits
+						match result {
+							Err(err) if matches!(ViaductError::from_io(&err), Some(ViaductError::PeerGone)) => {}
+							result => result.unwrap(),
+						}
 					})
 					.unwrap();
 
 				tx.rpc(DummyRpcParentToChild { magic: 123 }).unwrap();
 
+				// Switch on resync markers for the rest of the session
+				assert!(tx.peer_supports(Capability::ResyncMarkers));
+				tx.negotiate_upgrade(Capability::ResyncMarkers).unwrap();
+
+				// Fragmentation is always in use when both sides support it, so there's nothing to upgrade
+				assert_eq!(
+					tx.negotiate_upgrade(Capability::Fragmentation).unwrap_err().kind(),
+					std::io::ErrorKind::Unsupported
+				);
+
 				let response = tx
 					.request::<DummyResponseChildToParent>(DummyRequestParentToChild { magic: 42 })
 					.unwrap()
@@ -53,19 +75,24 @@ fn main() {
 				assert_eq!(response.magic, 42069);
 				println!("[PARENT] Response received: {:?}", response.magic);
 
-				child.wait().unwrap();
+				assert!(child.wait().unwrap().success());
+				event_loop.join().unwrap();
 			})
 			.unwrap(),
 
 		// We're the child process
-		Ok(((tx, rx), mut args)) => {
+		Ok((viaduct, mut args)) => {
+			let (tx, rx) = viaduct.split();
 			assert_eq!(args.nth(1).as_deref(), Some("Viaduct test!"));
+			assert!(viaduct::args().eq(std::env::args().take(2)));
 
 			std::thread::Builder::new()
 				.name("child".to_string())
 				.spawn(move || {
 					println!("child pid {:?}", std::process::id());
 
+					let (done_tx, done_rx) = std::sync::mpsc::sync_channel(1);
+
 					std::thread::Builder::new()
 						.name("child event loop".to_string())
 						.spawn(move || {
@@ -79,7 +106,9 @@ fn main() {
 									assert_eq!(request.magic, 42);
 									println!("[CHILD] Request received: {}", request.magic);
 									responder.respond(DummyResponseChildToParent { magic: 42069 }).unwrap();
+									done_tx.try_send(()).unwrap();
 								}
+								_ => unreachable!(),
 							})
 							.unwrap();
 						})
@@ -93,23 +122,28 @@ fn main() {
 						.unwrap();
 					assert_eq!(response.magic, (420, 69));
 					println!("[CHILD] Response received: {:?}", response.magic);
+
+					// Don't exit until the parent's request has been answered
+					done_rx.recv().unwrap();
 				})
 				.unwrap()
 		}
 	};
 
-	named_thread.join().ok();
+	named_thread.join().unwrap();
 }
 
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// An RPC that is sent from the parent process to the child process.
 struct DummyRpcParentToChild {
 	magic: u8,
 }
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// An RPC that is sent from the child process to the parent process.
 struct DummyRpcChildToParent {
@@ -117,14 +151,16 @@ struct DummyRpcChildToParent {
 }
 
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// A request that is sent from the parent process to the child process.
 struct DummyRequestParentToChild {
 	magic: u32,
 }
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// A request that is sent from the child process to the parent process.
 struct DummyRequestChildToParent {
@@ -132,14 +168,16 @@ struct DummyRequestChildToParent {
 }
 
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// A response that is sent from the child process to the parent process.
 struct DummyResponseChildToParent {
 	magic: u128,
 }
 #[cfg_attr(feature = "speedy", derive(speedy::Writable, speedy::Readable))]
-#[cfg_attr(feature = "bincode", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(any(feature = "bincode", feature = "postcard"), derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize))]
 #[derive(Debug)]
 /// A response that is sent from the parent process to the child process.
 struct DummyResponseParentToChild {
@@ -147,10 +185,12 @@ struct DummyResponseParentToChild {
 }
 
 // Manual serialization and deserialization implementations
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 use std::io::Write;
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
+use viaduct::{ViaductDeserialize, ViaductSerialize};
 
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyRpcParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -159,7 +199,7 @@ impl ViaductSerialize for DummyRpcParentToChild {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyRpcParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -168,7 +208,7 @@ impl ViaductDeserialize for DummyRpcParentToChild {
 		Ok(Self { magic: bytes[0] })
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyRpcChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -177,7 +217,7 @@ impl ViaductSerialize for DummyRpcChildToParent {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyRpcChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -187,7 +227,7 @@ impl ViaductDeserialize for DummyRpcChildToParent {
 		})
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyRequestParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -196,7 +236,7 @@ impl ViaductSerialize for DummyRequestParentToChild {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyRequestParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -206,7 +246,7 @@ impl ViaductDeserialize for DummyRequestParentToChild {
 		})
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyRequestChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -215,7 +255,7 @@ impl ViaductSerialize for DummyRequestChildToParent {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyRequestChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -225,7 +265,7 @@ impl ViaductDeserialize for DummyRequestChildToParent {
 		})
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyResponseChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -234,7 +274,7 @@ impl ViaductSerialize for DummyResponseChildToParent {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyResponseChildToParent {
 	type Error = std::convert::Infallible;
 
@@ -244,7 +284,7 @@ impl ViaductDeserialize for DummyResponseChildToParent {
 		})
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductSerialize for DummyResponseParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -254,7 +294,7 @@ impl ViaductSerialize for DummyResponseParentToChild {
 		Ok(())
 	}
 }
-#[cfg(not(any(feature = "bincode", feature = "speedy")))]
+#[cfg(not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "rkyv")))]
 impl ViaductDeserialize for DummyResponseParentToChild {
 	type Error = std::convert::Infallible;
 
@@ -0,0 +1,16 @@
+/// Which CPU core a thread spawned by Viaduct should be pinned to, if any. Does nothing unless the `core_affinity` feature is enabled.
+#[derive(Clone, Copy, Default)]
+pub(super) struct ThreadAffinity {
+	#[cfg(feature = "core_affinity")]
+	pub(super) core_id: Option<core_affinity::CoreId>,
+}
+impl ThreadAffinity {
+	/// Pins the current thread to the core, on a best-effort basis.
+	#[inline]
+	pub(super) fn apply(self) {
+		#[cfg(feature = "core_affinity")]
+		if let Some(core_id) = self.core_id {
+			core_affinity::set_for_current(core_id);
+		}
+	}
+}
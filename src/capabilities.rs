@@ -0,0 +1,130 @@
+/// An optional feature of the viaduct protocol.
+///
+/// The capabilities supported by each side of the viaduct are exchanged during the handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+	/// Packets can be split into fragments.
+	///
+	/// See [`ViaductParent::max_fragment_size`](crate::ViaductParent::max_fragment_size).
+	Fragmentation,
+
+	/// Requests can carry a correlation context.
+	///
+	/// See [`ViaductTx::request_with_context`](crate::ViaductTx::request_with_context).
+	RequestContext,
+
+	/// Resync markers can be switched on mid-session.
+	///
+	/// See [`ViaductTx::negotiate_upgrade`](crate::ViaductTx::negotiate_upgrade) and [`ViaductParent::resync_markers`](crate::ViaductParent::resync_markers).
+	ResyncMarkers,
+
+	/// Handles can be shared with the peer process.
+	///
+	/// See `ViaductTx::send_handle` on Windows, and `ViaductTx::send_fd` on Unix.
+	HandlePassing,
+
+	/// RPCs can be sent with flow control.
+	///
+	/// See [`ViaductTx::rpc_windowed`](crate::ViaductTx::rpc_windowed).
+	WindowedRpc,
+
+	/// Large payloads can be compressed.
+	///
+	/// Requires the `compression` feature. See `ViaductParent::compression_threshold`.
+	Compression,
+
+	/// Several RPCs can be sent in a single packet.
+	///
+	/// See [`ViaductTx::rpc_batch`](crate::ViaductTx::rpc_batch).
+	RpcBatch,
+
+	/// Requests can be sent with a priority.
+	///
+	/// See [`ViaductTx::request_priority`](crate::ViaductTx::request_priority).
+	RequestPriority,
+
+	/// Requests can be responded to with a stream of chunks.
+	///
+	/// See [`ViaductTx::request_stream`](crate::ViaductTx::request_stream).
+	StreamingResponses,
+
+	/// The sending side of the viaduct can be closed gracefully, stopping the peer's event loop without an error.
+	///
+	/// See [`ViaductTx::close`](crate::ViaductTx::close).
+	GracefulClose,
+}
+impl Capability {
+	const ALL: &'static [Capability] = &[
+		Capability::Fragmentation,
+		Capability::RequestContext,
+		Capability::ResyncMarkers,
+		Capability::HandlePassing,
+		Capability::WindowedRpc,
+		Capability::Compression,
+		Capability::RpcBatch,
+		Capability::RequestPriority,
+		Capability::StreamingResponses,
+		Capability::GracefulClose,
+	];
+
+	#[inline]
+	const fn bit(self) -> u64 {
+		1 << self as u64
+	}
+}
+
+/// A set of [`Capability`]s, as exchanged during the handshake.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct Capabilities(u64);
+impl Capabilities {
+	/// The capabilities supported by this build of Viaduct.
+	pub(super) const LOCAL: Self = Self(
+		Capability::Fragmentation.bit()
+			| Capability::RequestContext.bit()
+			| Capability::ResyncMarkers.bit()
+			| Capability::HandlePassing.bit()
+			| Capability::WindowedRpc.bit()
+			| Capability::RpcBatch.bit()
+			| Capability::RequestPriority.bit()
+			| Capability::StreamingResponses.bit()
+			| Capability::GracefulClose.bit()
+			| if cfg!(feature = "compression") {
+				Capability::Compression.bit()
+			} else {
+				0
+			},
+	);
+
+	#[inline]
+	pub(super) const fn from_bits(bits: u64) -> Self {
+		Self(bits)
+	}
+
+	#[inline]
+	pub(super) const fn bits(self) -> u64 {
+		self.0
+	}
+
+	#[inline]
+	pub(super) const fn contains(self, capability: Capability) -> bool {
+		self.0 & capability.bit() != 0
+	}
+
+	#[inline]
+	pub(super) fn insert(&mut self, capability: Capability) {
+		self.0 |= capability.bit();
+	}
+
+	#[cfg_attr(not(unix), allow(dead_code))]
+	#[inline]
+	pub(super) const fn without(self, capability: Capability) -> Self {
+		Self(self.0 & !capability.bit())
+	}
+
+	/// Iterates over the capabilities in this set that this build of Viaduct knows about.
+	#[inline]
+	pub(super) fn iter(self) -> impl Iterator<Item = Capability> {
+		Capability::ALL.iter().copied().filter(move |capability| self.contains(*capability))
+	}
+}
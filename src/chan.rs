@@ -1,33 +1,68 @@
 use crate::{
+	capabilities::Capabilities,
+	metrics::MetricsRecorder,
+	options::{FrameHook, LatencyHook, RawHook, ViaductOptions},
+	os,
+	pool::BufferPool,
+	reaper::ReaperPipe,
+	registry::Registry,
 	serde::{ViaductDeserialize, ViaductSerialize},
-	ViaductEvent,
+	timing::{Stopwatch, Timestamp, TimingRecorder},
+	Capability, ChildHandle, CloseReason, TrySendError, ViaductError, ViaductEvent, ViaductLazyEvent, ViaductMappedEvent,
 };
 use interprocess::unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter};
-use parking_lot::{Condvar, Mutex};
+use parking_lot::{Condvar, Mutex, MutexGuard};
 use std::{
-	collections::BTreeSet,
-	io::{Read, Write},
+	cell::RefCell,
+	collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+	io::{BufWriter, IoSlice, Read, Write},
 	marker::PhantomData,
 	mem::size_of,
-	sync::Arc,
+	ops::ControlFlow,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Arc,
+	},
 	time::{Duration, Instant},
 };
 use uuid::Uuid;
 
-const RPC: u8 = 0;
-const REQUEST: u8 = 1;
-const SOME_RESPONSE: u8 = 2;
-const NONE_RESPONSE: u8 = 3;
+pub(super) const RPC: u8 = 0;
+pub(super) const REQUEST: u8 = 1;
+pub(super) const SOME_RESPONSE: u8 = 2;
+pub(super) const NONE_RESPONSE: u8 = 3;
+pub(super) const REQUEST_WITH_CONTEXT: u8 = 4;
+const FRAGMENT: u8 = 5;
+const FRAGMENT_END: u8 = 6;
+const UPGRADE: u8 = 7;
+const UPGRADE_ACK: u8 = 8;
+const HANDLE: u8 = 9;
+pub(super) const WINDOWED_RPC: u8 = 10;
+const WINDOW_ACK: u8 = 11;
+pub(super) const BATCH: u8 = 12;
+pub(super) const REQUEST_WITH_PRIORITY: u8 = 13;
+pub(super) const STREAM_CHUNK: u8 = 14;
+pub(super) const STREAM_END: u8 = 15;
+const CREDIT: u8 = 16;
+pub(super) const GOODBYE: u8 = 17;
+
+/// Set in the packet type of a packet whose payload is compressed.
+pub(super) const COMPRESSED: u8 = 0x80;
+
+/// Precedes every frame when resync markers are enabled, so that the reader can find the start of the next frame if the stream becomes desynchronized.
+const RESYNC_MARKER: [u8; 16] = *b"\0VIADUCT\xFFRESYNC\0";
 
 pub(super) const HELLO: &[u8] = b"Read this if you are a beautiful strong unnamed pipe who don't need no handles";
 
+/// The version of the wire format, exchanged right after [`HELLO`] during the handshake.
+///
+/// Bump this whenever the framing changes in a way that an older peer couldn't understand, so that mismatched peers fail the handshake instead of silently corrupting the stream.
+pub(super) const PROTOCOL_VERSION: u16 = 2;
+
 /// A channel pair for sending and receiving data across the viaduct.
-pub type Viaduct<RpcTx, RequestTx, RpcRx, RequestRx> = (
-	ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
-	ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>,
-);
-/// Use [`ViaductRequestResponder::respond`] to send a response to the other side.
-pub struct ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
+///
+/// Use [`Viaduct::split`] to take the two halves apart, typically so that the [`ViaductRx`] can be moved to the thread running the event loop while the [`ViaductTx`] (which can be cloned) is kept for sending. [`Viaduct::join`] puts them back together.
+pub struct Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
@@ -35,122 +70,168 @@ where
 	RequestRx: ViaductDeserialize,
 {
 	tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
-	request_id: Uuid,
+	rx: ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>,
 }
-impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
+
+/// A viaduct that only carries RPCs, sending `Tx` and receiving `Rx`, with [`Never`](crate::Never) for both types of request.
+///
+/// The peer process' side of the viaduct is `RpcOnly<Rx, Tx>`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use viaduct::{RpcOnly, ViaductParent, doctest::*};
+/// let (viaduct, child) = ViaductParent::new(std::process::Command::new("child.exe")).unwrap().build().unwrap();
+/// let viaduct: RpcOnly<ExampleRpc, ExampleRpc> = viaduct;
+/// ```
+pub type RpcOnly<Tx, Rx> = Viaduct<Tx, crate::Never, Rx, crate::Never>;
+
+/// A viaduct that only carries requests, sending `Tx` and receiving `Rx`, with [`Never`](crate::Never) for both types of RPC.
+///
+/// The peer process' side of the viaduct is `RequestOnly<Rx, Tx>`.
+pub type RequestOnly<Tx, Rx> = Viaduct<crate::Never, Tx, crate::Never, Rx>;
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
 	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	/// Sends a response to the other side.
-	///
-	/// You can send whatever type you want, as long as it implements [`ViaductSerialize`].
+	/// Puts the two halves of a viaduct back together.
 	///
 	/// # Panics
 	///
-	/// This function won't panic, but the peer process will panic if you send a different type to what it was expecting.
+	/// This function will panic if `tx` and `rx` are halves of different viaducts.
+	#[inline]
+	pub fn join(tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>, rx: ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		assert!(Arc::ptr_eq(&tx.0, &rx.tx.0), "ViaductTx and ViaductRx are halves of different viaducts");
+		Self { tx, rx }
+	}
+
+	/// Takes the viaduct apart into its sending and receiving halves.
 	///
 	/// # Example
 	///
 	/// ```no_run
-	/// # use viaduct::{ViaductEvent, ViaductChild, doctest::*};
-	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().1;
-	/// rx.run(|event| match event {
-	///     ViaductEvent::Rpc(rpc) => match rpc {
-	///         ExampleRpc::Cow => println!("Moo"),
-	///         ExampleRpc::Pig => println!("Oink"),
-	///         ExampleRpc::Horse => println!("Neigh"),
-	///     },
-	///
-	///     ViaductEvent::Request { request, responder } => match request {
-	///         ExampleRequest::DoAFrontflip => {
-	///             println!("Doing a frontflip!");
-	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
-	///         },
+	/// # use viaduct::{ViaductChild, doctest::*};
+	/// let viaduct = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap();
+	/// let (tx, rx) = viaduct.split();
 	///
-	///         ExampleRequest::DoABackflip => {
-	///             println!("Doing a backflip!");
-	///             responder.respond(Ok::<_, BackflipError>(())).unwrap();
-	///         },
-	///     }
-	/// }).unwrap();
+	/// std::thread::spawn(move || rx.run(|_event| {}));
+	/// tx.rpc(ExampleRpc::Cow).unwrap();
 	/// ```
-	pub fn respond(self, response: impl ViaductSerialize) -> Result<(), std::io::Error> {
-		{
-			let mut state = self.tx.0.state.lock();
-			let ViaductTxState { tx, buf, .. } = &mut *state;
+	#[allow(clippy::type_complexity)]
+	#[inline]
+	pub fn split(
+		self,
+	) -> (
+		ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) {
+		(self.tx, self.rx)
+	}
 
-			response
-				.to_pipeable({
-					buf.clear();
-					buf
-				})
-				.expect("Failed to serialize response");
+	/// Returns the sending half of the viaduct.
+	#[inline]
+	pub fn tx(&self) -> &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx> {
+		&self.tx
+	}
 
-			tx.write_all(&[2])?;
-			tx.write_all(self.request_id.as_bytes())?;
-			tx.write_all(&u64::to_ne_bytes(buf.len() as _))?;
-			tx.write_all(buf)?;
-		}
+	/// Returns the receiving half of the viaduct.
+	#[inline]
+	pub fn rx(&mut self) -> &mut ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx> {
+		&mut self.rx
+	}
 
-		std::mem::forget(self);
+	/// Returns why the viaduct was closed, or `None` if it is still open.
+	///
+	/// See [`ViaductTx::close_reason`].
+	#[inline]
+	pub fn close_reason(&self) -> Option<CloseReason> {
+		self.tx.close_reason()
+	}
 
-		Ok(())
+	/// Returns a handle to the child process, or `None` in the child process.
+	///
+	/// See [`ViaductTx::child_handle`].
+	#[inline]
+	pub fn child_handle(&self) -> Option<ChildHandle> {
+		self.tx.child_handle()
 	}
 }
-impl<RpcTx, RequestTx, RpcRx, RequestRx> Drop for ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
+impl<RpcTx, RequestTx, RpcRx, RequestRx> From<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>>
+	for (
+		ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>,
+	)
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
 	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	fn drop(&mut self) {
-		let mut state = self.tx.0.state.lock();
-		let ViaductTxState { tx, .. } = &mut *state;
-
-		(|| {
-			tx.write_all(&[3])?;
-			tx.write_all(self.request_id.as_bytes())?;
-			Ok::<_, std::io::Error>(())
-		})()
-		.unwrap();
+	#[inline]
+	fn from(viaduct: Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		viaduct.split()
 	}
 }
 
-/// The receiving side of a viaduct.
-pub struct ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+/// Use [`ViaductRequestResponder::respond`] to send a response to the other side.
+///
+/// If the responder is dropped without responding, the requester receives `None`. Any error sending that is ignored, as the peer has usually gone away; use [`ViaductRequestResponder::ack`] instead to find out whether it was sent.
+pub struct ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
 	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	pub(super) buf: Vec<u8>,
-	pub(super) tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
-	pub(super) rx: UnnamedPipeReader,
-	pub(super) _phantom: PhantomData<RequestRx>,
+	tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+	request_id: Uuid,
+	context: Option<String>,
+	priority: Priority,
+	responded: bool,
 }
-impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
-	RpcRx: ViaductDeserialize,
 	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	/// Runs the event loop. This function will never return unless an error occurs.
+	/// Returns the correlation context the peer attached to this request, if any.
+	///
+	/// See [`ViaductTx::request_with_context`].
+	#[inline]
+	pub fn context(&self) -> Option<&str> {
+		self.context.as_deref()
+	}
+
+	/// Returns the priority the peer sent this request with.
+	///
+	/// See [`ViaductTx::request_priority`].
+	#[inline]
+	pub fn priority(&self) -> Priority {
+		self.priority
+	}
+
+	/// Sends a response to the other side.
+	///
+	/// You can send whatever type you want, as long as it implements [`ViaductSerialize`].
+	///
+	/// The response is flushed down the pipe straight away, even if no-delay mode is disabled (see [`ViaductTx::set_no_delay`]), as the requester is waiting for it.
+	///
+	/// If the response is larger than the maximum send size (see [`ViaductParent::max_send_size`](crate::ViaductParent::max_send_size)), the requester receives `None` instead, and a [`ViaductError::MessageTooLarge`] error is returned.
 	///
 	/// # Panics
 	///
-	/// This function will panic if the peer process sends some data (RPC or request) and this process fails to deserialize it.
+	/// This function won't panic, but the peer process will panic if you send a different type to what it was expecting.
 	///
 	/// # Example
 	///
 	/// ```no_run
 	/// # use viaduct::{ViaductEvent, ViaductChild, doctest::*};
-	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().1;
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
 	/// rx.run(|event| match event {
 	///     ViaductEvent::Rpc(rpc) => match rpc {
 	///         ExampleRpc::Cow => println!("Moo"),
@@ -169,326 +250,4176 @@ where
 	///             responder.respond(Ok::<_, BackflipError>(())).unwrap();
 	///         },
 	///     }
+	///
+	///     // Handles shared by the peer process, and any kinds of event added in the future
+	///     _ => {}
 	/// }).unwrap();
 	/// ```
-	pub fn run<EventHandler>(mut self, mut event_handler: EventHandler) -> Result<(), std::io::Error>
-	where
-		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
-	{
-		let recv_into_buf = |rx: &mut UnnamedPipeReader, buf: &mut Vec<u8>| -> Result<(), std::io::Error> {
-			let len = {
-				let mut len = [0u8; size_of::<u64>()];
-				rx.read_exact(&mut len)?;
-				usize::try_from(u64::from_ne_bytes(len)).expect("Viaduct packet was larger than what this architecture can handle")
-			};
-			buf.resize(len, 0);
-			rx.read_exact(buf)?;
-			Ok(())
-		};
-
-		loop {
-			let packet_type = {
-				let mut packet_type = [0u8];
-				self.rx.read_exact(&mut packet_type)?;
-				packet_type[0]
-			};
-			match packet_type {
-				RPC => {
-					recv_into_buf(&mut self.rx, &mut self.buf)?;
+	pub fn respond(self, response: impl ViaductSerialize) -> Result<(), std::io::Error> {
+		self.send_response(response, true)
+	}
 
-					let rpc = RpcRx::from_pipeable(&self.buf).expect("Failed to deserialize RpcRx");
-					event_handler(ViaductEvent::Rpc(rpc));
-				}
+	/// Sends a response to the other side, without flushing it down the pipe if no-delay mode is disabled (see [`ViaductTx::set_no_delay`]).
+	///
+	/// [`ViaductRequestResponder::respond`] always flushes, because the requester is waiting for the response. When answering many requests in a row, this lets the responses be coalesced into fewer writes instead; they are sent once [`ViaductTx::flush`] is called, the write buffer fills up, or something else is flushed. Make sure one of those happens, or the requesters will wait forever.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRequestResponder::respond`].
+	pub fn respond_no_flush(self, response: impl ViaductSerialize) -> Result<(), std::io::Error> {
+		self.send_response(response, false)
+	}
 
-				REQUEST => {
-					let request_id = {
-						let mut request_id = [0u8; 16];
-						self.rx.read_exact(&mut request_id)?;
-						Uuid::from_bytes(request_id)
-					};
+	/// Acknowledges the request without a response, so the requester receives `None`.
+	///
+	/// This is what happens when the responder is dropped, but makes the intent clear for requests that only need to know they were received, and returns the error if the acknowledgement can't be sent.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductEvent, ViaductChild, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run(|event| match event {
+	///     ViaductEvent::Request { request, responder } => {
+	///         println!("Request received: {request:?}");
+	///         responder.ack().unwrap();
+	///     }
+	///     _ => {}
+	/// }).unwrap();
+	/// ```
+	pub fn ack(mut self) -> Result<(), std::io::Error> {
+		// Don't send another "no response" packet when we're dropped, even if this fails
+		self.responded = true;
 
-					recv_into_buf(&mut self.rx, &mut self.buf)?;
+		if !self.tx.0.claim_responder(&self.request_id) {
+			// We were abandoned, so a "no response" packet has already been sent
+			return Ok(());
+		}
 
-					event_handler(ViaductEvent::Request {
-						request: RequestRx::from_pipeable(&self.buf).expect("Failed to deserialize RequestRx"),
-						responder: ViaductRequestResponder {
-							tx: self.tx.clone(),
-							request_id,
-						},
-					});
-				}
+		self.send_no_response(&mut self.tx.0.state.lock())
+	}
 
-				SOME_RESPONSE => {
-					let mut response = self.tx.0.response.lock();
-					self.tx
-						.0
-						.response_condvar
-						.wait_while(&mut response, |response| response.for_request_id.is_some());
-
-					let request_id = {
-						let mut request_id = [0u8; 16];
-						self.rx.read_exact(&mut request_id)?;
-						Uuid::from_bytes(request_id)
-					};
+	/// Starts responding to the request with a stream of chunks, which the requester receives one at a time from [`ViaductTx::request_stream`].
+	///
+	/// Send each chunk with [`ResponseSink::send`], and finish the stream with [`ResponseSink::end`]. If the sink is dropped without ending the stream, the stream ends there, just as if the responder had been dropped.
+	///
+	/// Requires the peer to support [`Capability::StreamingResponses`], otherwise a [`ViaductError::MissingCapability`] error is returned and the requester receives `None`.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductEvent, ViaductChild};
+	/// # let rx = unsafe { ViaductChild::<(), u32, (), u32>::new().build() }.unwrap().split().1;
+	/// rx.run(|event| match event {
+	///     ViaductEvent::Request { request, responder } => {
+	///         let mut sink = responder.respond_stream().unwrap();
+	///         for i in 0..request {
+	///             sink.send(i).unwrap();
+	///         }
+	///         sink.end().unwrap();
+	///     }
+	///     _ => {}
+	/// }).unwrap();
+	/// ```
+	pub fn respond_stream(self) -> Result<ResponseSink<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> {
+		if !self.tx.peer_supports(Capability::StreamingResponses) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::StreamingResponses,
+				peer_supported: self.tx.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
 
-					// Receive the response into the sender's buffer
-					response.buf.clear();
-					recv_into_buf(&mut self.rx, &mut response.buf)?;
+		Ok(ResponseSink { responder: self })
+	}
 
-					if !response.pending.remove(&request_id) {
-						// The request was cancelled. Discard.
-						continue;
-					}
+	/// Sends a request to `downstream`, which may be a different viaduct to the one this request arrived on, and responds to this request with whatever `downstream` responds with.
+	///
+	/// This doesn't block: the response is forwarded, without being deserialized, by `downstream`'s event loop as soon as it arrives, so that a broker can route requests between processes without keeping track of them itself. If `downstream` doesn't respond (or the request is abandoned with [`ViaductTx::reset`]), the requester receives `None`. Any correlation context the requester attached is passed on to `downstream` as well, and so is the request's priority, if `downstream` supports [`Capability::RequestPriority`].
+	///
+	/// If the request can't be sent to `downstream`, the requester receives `None` and the error is returned.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the requester will panic if `downstream` responds with a different type to what it was expecting.
+	pub fn proxy_to<DownRpcTx, DownRequestTx, DownRpcRx, DownRequestRx>(
+		self,
+		downstream: &ViaductTx<DownRpcTx, DownRequestTx, DownRpcRx, DownRequestRx>,
+		request: DownRequestTx,
+	) -> Result<(), std::io::Error>
+	where
+		Self: Send + 'static,
+		DownRpcTx: ViaductSerialize,
+		DownRequestTx: ViaductSerialize,
+		DownRpcRx: ViaductDeserialize,
+		DownRequestRx: ViaductDeserialize,
+	{
+		let request_id = Uuid::new_v4();
+		let context = self.context.clone();
+		let priority = if downstream.peer_supports(Capability::RequestPriority) {
+			self.priority
+		} else {
+			Priority::Normal
+		};
 
-					response.for_request_id = Some((request_id, true));
+		#[cfg(feature = "tracing")]
+		tracing::debug!(upstream_request_id = %self.request_id, downstream_request_id = %request_id, "viaduct request proxied");
 
-					// Tell the sender that the response is ready and in their buffer!
-					self.tx.0.response_condvar.notify_all();
+		// If this fails, dropping the responder tells the requester there's no response
+		downstream.0.insert_pending(
+			request_id,
+			PendingResponse::Forward(Box::new(move |response| {
+				if let Some(response) = response {
+					// There's nobody to report a failure to; if this fails, the requester will find out when the viaduct breaks
+					self.send_response_with(true, |buf| buf.extend_from_slice(&response)).ok();
 				}
+			})),
+		)?;
 
-				NONE_RESPONSE => {
-					let mut response = self.tx.0.response.lock();
-					self.tx
-						.0
-						.response_condvar
-						.wait_while(&mut response, |response| response.for_request_id.is_some());
-
-					let request_id = {
-						let mut request_id = [0u8; 16];
-						self.rx.read_exact(&mut request_id)?;
-						Uuid::from_bytes(request_id)
-					};
+		if let Err(err) = downstream.send_request(request_id, request, context.as_deref(), priority, None) {
+			// Dropping the responder tells the requester there's no response
+			let forward = downstream.0.pending.lock().remove(&request_id);
+			drop(forward);
+			return Err(err);
+		}
 
-					if !response.pending.remove(&request_id) {
-						// The request was cancelled. Discard.
-						continue;
-					}
+		Ok(())
+	}
 
-					response.for_request_id = Some((request_id, false));
+	fn send_response(self, response: impl ViaductSerialize, flush: bool) -> Result<(), std::io::Error> {
+		self.send_response_with(flush, |buf| response.to_pipeable(buf).expect("Failed to serialize response"))
+	}
 
-					// Tell the sender that the response is ready and in their buffer!
-					self.tx.0.response_condvar.notify_all();
-				}
+	/// Sends a response once `serialize` has written it into the send buffer.
+	fn send_response_with(mut self, flush: bool, serialize: impl FnOnce(&mut Vec<u8>)) -> Result<(), std::io::Error> {
+		// Don't send a "no response" packet when we're dropped, even if this fails
+		self.responded = true;
+
+		if !self.tx.0.claim_responder(&self.request_id) {
+			// We were abandoned, so a "no response" packet has already been sent
+			return Ok(());
+		}
 
-				_ => unreachable!(),
+		let mut state = self.tx.0.state.lock();
+
+		let serialize_start = Stopwatch::start();
+		state.buf.clear();
+		serialize(&mut state.buf);
+		let serialize = serialize_start.elapsed();
+
+		let write = Stopwatch::start();
+		let mut header = [SOME_RESPONSE; 1 + 16];
+		header[1..].copy_from_slice(self.request_id.as_bytes());
+		if let Err(err) = ViaductTxState::send_packet(&mut state, &header, true, flush) {
+			if let Some(ViaductError::MessageTooLarge { .. }) = ViaductError::from_io(&err) {
+				// Don't leave the requester waiting for a response that will never come
+				header[0] = NONE_RESPONSE;
+				ViaductTxState::send_packet(&mut state, &header, false, true)?;
 			}
+			return Err(err);
 		}
+		self.tx.0.timings.record_send(serialize, write.elapsed());
+
+		Ok(())
 	}
-}
 
-#[derive(Default)]
-pub(super) struct ViaductResponseState {
-	pending: BTreeSet<Uuid>,
-	for_request_id: Option<(Uuid, bool)>,
-	buf: Vec<u8>,
-}
-impl ViaductResponseState {
-	#[inline]
-	fn request_id(&self) -> Option<&Uuid> {
-		self.for_request_id.as_ref().map(|(id, _)| id)
+	/// Tells the requester that there won't be a response.
+	fn send_no_response(&self, state: &mut MutexGuard<'_, ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx>>) -> Result<(), std::io::Error> {
+		let write = Stopwatch::start();
+		let mut header = [NONE_RESPONSE; 1 + 16];
+		header[1..].copy_from_slice(self.request_id.as_bytes());
+		ViaductTxState::send_packet(state, &header, false, true)?;
+		self.tx.0.timings.record_send(Duration::ZERO, write.elapsed());
+
+		Ok(())
 	}
 }
-
-/// The sending side of a viaduct.
-///
-/// This handle can be freely cloned and sent across threads.
-pub struct ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>(pub(super) Arc<ViaductTxInner<RpcTx, RequestTx, RpcRx, RequestRx>>)
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Drop for ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
 	RpcRx: ViaductDeserialize,
-	RequestRx: ViaductDeserialize;
+	RequestRx: ViaductDeserialize,
+{
+	fn drop(&mut self) {
+		if let Some(limit) = &self.tx.0.responder_limit {
+			limit.release();
+		}
 
-pub(super) struct ViaductTxInner<RpcTx, RequestTx, RpcRx, RequestRx> {
-	pub(super) state: Mutex<ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx>>,
-	pub(super) response: Mutex<ViaductResponseState>,
-	pub(super) response_condvar: Condvar,
-}
+		if self.responded || !self.tx.0.claim_responder(&self.request_id) {
+			return;
+		}
 
-pub(super) struct ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx> {
-	pub(super) tx: UnnamedPipeWriter,
-	buf: Vec<u8>,
-	_phantom: PhantomData<(RpcTx, RequestTx, RpcRx, RequestRx)>,
+		#[cfg(feature = "tracing")]
+		tracing::warn!(request_id = %self.request_id, "viaduct request responder dropped without responding");
+
+		let mut state = self.tx.0.state.lock();
+		if state.tx.get_ref().0.is_none() {
+			// We can't tell the peer anything after shutting down the sending side
+			return;
+		}
+
+		// The peer has probably gone away, and panicking here could abort the process if we're unwinding; use `ack` to find out
+		if let Err(_err) = self.send_no_response(&mut state) {
+			#[cfg(feature = "tracing")]
+			tracing::warn!(request_id = %self.request_id, err = %_err, "viaduct request responder failed to tell the peer there is no response");
+		}
+	}
 }
-impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx>
+
+/// Sends a response to a request as a stream of chunks.
+///
+/// See [`ViaductRequestResponder::respond_stream`].
+pub struct ResponseSink<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
-	RpcRx: ViaductDeserialize,
 	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	#[inline]
-	pub(super) fn new(tx: UnnamedPipeWriter) -> Self {
-		Self {
-			buf: Vec::new(),
-			tx,
-			_phantom: Default::default(),
-		}
-	}
+	/// Tells the requester the stream has ended if we're dropped without calling [`ResponseSink::end`].
+	responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
 }
-
-impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ResponseSink<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
-	RpcRx: ViaductDeserialize,
 	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
-	/// Sends an RPC to the peer process.
+	/// Sends the next chunk of the response.
+	///
+	/// Each chunk is flushed down the pipe straight away, as the requester is waiting for it. If a chunk is larger than the maximum send size (see [`ViaductParent::max_send_size`](crate::ViaductParent::max_send_size)), a [`ViaductError::MessageTooLarge`] error is returned, and the stream carries on without it.
 	///
 	/// # Panics
 	///
-	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
-	pub fn rpc(&self, rpc: RpcTx) -> Result<(), std::io::Error> {
-		let mut state = self.0.state.lock();
+	/// This function won't panic, but the peer process will panic if you send a different type to what it was expecting.
+	pub fn send(&mut self, chunk: impl ViaductSerialize) -> Result<(), std::io::Error> {
+		let responder = &self.responder;
+		if responder.tx.0.responder_abandoned(&responder.request_id) {
+			// A "no response" packet has already been sent, which ended the stream
+			return Ok(());
+		}
 
-		let ViaductTxState { buf, tx, .. } = &mut *state;
+		let mut state = responder.tx.0.state.lock();
 
-		rpc.to_pipeable({
-			buf.clear();
-			buf
-		})
-		.expect("Failed to serialize RpcTx");
+		let serialize = Stopwatch::start();
+		state.buf.clear();
+		chunk.to_pipeable(&mut state.buf).expect("Failed to serialize response chunk");
+		let serialize = serialize.elapsed();
 
-		tx.write_all(&[0])?;
-		tx.write_all(&u64::to_ne_bytes(buf.len() as _))?;
-		tx.write_all(&*buf)?;
+		let write = Stopwatch::start();
+		let mut header = [STREAM_CHUNK; 1 + 16];
+		header[1..].copy_from_slice(responder.request_id.as_bytes());
+		ViaductTxState::send_packet(&mut state, &header, true, true)?;
+		responder.tx.0.timings.record_send(serialize, write.elapsed());
 
 		Ok(())
 	}
 
-	/// Sends a request to the peer process and awaits a response.
-	///
-	/// This will block the current thread.
-	///
-	/// # Panics
-	///
-	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
-	pub fn request<Response: ViaductDeserialize>(&self, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
-		let mut response = self.0.response.lock();
-
-		// Get a request ID
-		let request_id = Uuid::new_v4();
-
-		response.pending.insert(request_id);
-
-		// Send the request down the wire
-		{
-			let mut state = self.0.state.lock();
-			let ViaductTxState { buf, tx, .. } = &mut *state;
+	/// Ends the stream, after which the requester's [`ResponseStream`] returns `None`.
+	pub fn end(mut self) -> Result<(), std::io::Error> {
+		let responder = &mut self.responder;
 
-			request
-				.to_pipeable({
-					buf.clear();
-					buf
-				})
-				.expect("Failed to serialize RequestTx");
+		// Don't send a "no response" packet when we're dropped, even if this fails
+		responder.responded = true;
 
-			tx.write_all(&[1])?;
-			tx.write_all(request_id.as_bytes())?;
-			tx.write_all(&u64::to_ne_bytes(buf.len() as _))?;
-			tx.write_all(&*buf)?;
+		if !responder.tx.0.claim_responder(&responder.request_id) {
+			// We were abandoned, so a "no response" packet has already ended the stream
+			return Ok(());
 		}
 
-		self.0
-			.response_condvar
-			.wait_while(&mut response, |response| response.request_id() != Some(&request_id));
-
-		let (for_request_id, some) = response.for_request_id.take().unwrap();
-		debug_assert_eq!(for_request_id, request_id);
+		let write = Stopwatch::start();
+		let mut header = [STREAM_END; 1 + 16];
+		header[1..].copy_from_slice(responder.request_id.as_bytes());
+		ViaductTxState::send_packet(&mut responder.tx.0.state.lock(), &header, false, true)?;
+		responder.tx.0.timings.record_send(Duration::ZERO, write.elapsed());
 
-		// Notify the condvar because the writer half might be waiting for the request ID to become None
-		self.0.response_condvar.notify_all();
-
-		// Deserialize the response and return it
-		Ok(if some {
-			Some(Response::from_pipeable(&response.buf).expect("Failed to deserialize Response"))
-		} else {
-			None
-		})
+		Ok(())
 	}
+}
 
-	/// Sends a request to the peer process and awaits a response, timing out after an [`Instant`](std::time::Instant) has passed.
-	///
-	/// This will block the current thread.
-	///
-	/// # Panics
-	///
-	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
-	pub fn request_timeout_at<Response: ViaductDeserialize>(
-		&self,
-		timeout_at: Instant,
-		request: RequestTx,
+/// The receiving side of a viaduct.
+pub struct ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	pub(super) buf: Vec<u8>,
+	pub(super) tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+	pub(super) rx: PipeReader,
+	pub(super) reassembly: Reassembly,
+	pub(super) pool: Option<Arc<dyn BufferPool>>,
+	pub(super) peeked: Option<Frame>,
+	pub(super) batch: RpcBatch,
+	pub(super) resync: bool,
+	pub(super) max_message_size: Option<usize>,
+	pub(super) marker_consumed: bool,
+	pub(super) nonblocking: bool,
+	pub(super) shutdown: Option<ShutdownListener>,
+	pub(super) timestamps: bool,
+	pub(super) timestamp: Option<Timestamp>,
+	pub(super) on_raw_recv: Option<RawHook>,
+	pub(super) on_frame: Option<FrameHook>,
+	pub(super) registry: Registry<RpcTx, RequestTx, RpcRx, RequestRx>,
+	pub(super) _phantom: PhantomData<RequestRx>,
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestTx: ViaductSerialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Runs the event loop. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// When the peer process exits, or otherwise closes its side of the viaduct, this returns a [`ViaductError::PeerGone`] error once everything it sent has been handled. If the peer closes its side gracefully with [`ViaductTx::close`], this returns `Ok(())` instead.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process sends some data (RPC or request) and this process fails to deserialize it. Use [`ViaductRx::run_with_error_handler`] to handle malformed data instead.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductEvent, ViaductChild, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run(|event| match event {
+	///     ViaductEvent::Rpc(rpc) => match rpc {
+	///         ExampleRpc::Cow => println!("Moo"),
+	///         ExampleRpc::Pig => println!("Oink"),
+	///         ExampleRpc::Horse => println!("Neigh"),
+	///     },
+	///
+	///     ViaductEvent::Request { request, responder } => match request {
+	///         ExampleRequest::DoAFrontflip => {
+	///             println!("Doing a frontflip!");
+	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///         },
+	///
+	///         ExampleRequest::DoABackflip => {
+	///             println!("Doing a backflip!");
+	///             responder.respond(Ok::<_, BackflipError>(())).unwrap();
+	///         },
+	///     }
+	///
+	///     // Handles shared by the peer process, and any kinds of event added in the future
+	///     _ => {}
+	/// }).unwrap();
+	/// ```
+	pub fn run<EventHandler>(mut self, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		loop {
+			match self.recv(&mut ()) {
+				Ok(Some(event)) => handle_event(&mut event_handler, event),
+				Ok(None) => {}
+				Err(err) => return stopped(err),
+			}
+		}
+	}
+
+	/// Runs the event loop, passing RPCs and requests that fail to deserialize to `error_handler` instead of panicking. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// `error_handler` decides what happens next: returning [`ControlFlow::Continue`] skips the malformed RPC or request and carries on receiving, while returning [`ControlFlow::Break`] stops the event loop, which returns an error of kind [`InvalidData`](std::io::ErrorKind::InvalidData). The responder of a malformed request is dropped, so the requester receives `None`.
+	///
+	/// See [`ViaductRx::run`] for more information.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use std::ops::ControlFlow;
+	/// # use viaduct::{ViaductChild, ViaductEvent, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run_with_error_handler(
+	///     ViaductEvent::handler(
+	///         |rpc| println!("RPC received: {rpc:?}"),
+	///         |request, responder| {
+	///             println!("Request received: {request:?}");
+	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///         },
+	///     ),
+	///     |err| {
+	///         eprintln!("Received a malformed {:?}: {err:?}", err.packet_type());
+	///         ControlFlow::Continue(())
+	///     },
+	/// ).unwrap();
+	/// ```
+	pub fn run_with_error_handler<EventHandler, ErrorHandler>(
+		mut self,
+		mut event_handler: EventHandler,
+		mut error_handler: ErrorHandler,
+	) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+		ErrorHandler: FnMut(DeserializeError<RpcRx, RequestRx>) -> ControlFlow<()>,
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		loop {
+			match self.recv(&mut ()) {
+				Ok(Some(CheckedEvent::Event(event))) => handle_event(&mut event_handler, event),
+				Ok(Some(CheckedEvent::Malformed(err))) => {
+					let message = format!("Failed to deserialize {:?}: {err:?}", err.packet_type());
+					if error_handler(err).is_break() {
+						return Err(self.close_with(std::io::Error::new(std::io::ErrorKind::InvalidData, message)));
+					}
+				}
+				Ok(None) => {}
+				Err(err) => return stopped(err),
+			}
+		}
+	}
+
+	/// Runs the event loop, passing the event handler the [`Timestamp`] of when each packet was sent. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// The timestamp is `None` unless timestamps are enabled with [`ViaductParent::timestamps`](crate::ViaductParent::timestamps) or [`ViaductChild::timestamps`](crate::ViaductChild::timestamps). For fragmented packets, it's when the last fragment was sent.
+	///
+	/// See [`ViaductRx::run`] for more information.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().timestamps().build() }.unwrap().split().1;
+	/// rx.run_timestamped(|_event, sent_at| {
+	///     if let Some(sent_at) = sent_at {
+	///         println!("Packet took {:?} to arrive", sent_at.elapsed());
+	///     }
+	/// }).unwrap();
+	/// ```
+	pub fn run_timestamped<EventHandler>(mut self, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>, Option<Timestamp>),
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		loop {
+			match self.recv(&mut ()) {
+				Ok(Some(event)) => {
+					let timestamp = self.timestamp;
+					handle_event(&mut |event| event_handler(event, timestamp), event);
+				}
+				Ok(None) => {}
+				Err(err) => return stopped(err),
+			}
+		}
+	}
+
+	/// Runs the event loop without deserializing RPCs and requests, leaving that to the event handler. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// Normally, the event loop deserializes each RPC and request before passing it to the event handler, so an expensive deserialization holds up reading the next packet. Here, the event handler receives the serialized bytes in a [`LazyMessage`] instead, which it can [decode](LazyMessage::decode) on whichever thread it likes, such as a worker thread, keeping the event loop busy only with reading from the pipe.
+	///
+	/// With the `rkyv` backend, the event handler can also [access the archived message](LazyMessage::archived) in place, skipping deserialization entirely.
+	///
+	/// See [`ViaductRx::run`] for more information.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, ViaductLazyEvent, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run_lazy(|event| match event {
+	///     ViaductLazyEvent::Rpc(rpc) => {
+	///         std::thread::spawn(move || println!("RPC received: {:?}", rpc.decode()));
+	///     }
+	///
+	///     ViaductLazyEvent::Request { request, responder } => {
+	///         std::thread::spawn(move || {
+	///             println!("Request received: {:?}", request.decode());
+	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///         });
+	///     }
+	///
+	///     // Handles shared by the peer process, and any kinds of event added in the future
+	///     _ => {}
+	/// }).unwrap();
+	/// ```
+	pub fn run_lazy<EventHandler>(mut self, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductLazyEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		loop {
+			let event = match self.recv(&mut ()) {
+				Ok(Some(event)) => event,
+				Ok(None) => continue,
+				Err(err) => return stopped(err),
+			};
+
+			#[cfg(feature = "tracing")]
+			let _span = match &event {
+				ViaductLazyEvent::Request { responder, .. } => Some(
+					tracing::debug_span!("viaduct_request_received", request_id = %responder.request_id, context = responder.context()).entered(),
+				),
+				_ => None,
+			};
+
+			event_handler(event);
+		}
+	}
+
+	/// Runs the event loop, receiving the payloads of RPCs and requests into destinations chosen by `map` rather than into a buffer on the heap. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// This is intended for payloads too large to comfortably hold in memory, such as multi-gigabyte transfers, which can be received straight into a memory-mapped file and processed out of core. Before each payload is read, `map` is called with its type and length in bytes, and returns the destination to read it into, which must be exactly that long, or `None` to receive and deserialize the payload as [`ViaductRx::run`] would. The event handler is then given the destination back in a [`MappedPayload`].
+	///
+	/// Payloads are read straight into the destination unless they arrived [fragmented](crate::ViaductParent::max_fragment_size) or [compressed](crate::ViaductParent::compression_threshold), in which case they are reassembled or decompressed in memory first and then copied in. The sender should leave both disabled for payloads that shouldn't be held in memory. Mapped payloads still count towards [`ViaductParent::max_message_size`](crate::ViaductParent::max_message_size), so it needs raising for payloads larger than 1 GiB. RPCs sent together with [`ViaductTx::rpc_batch`] are always received into a buffer.
+	///
+	/// # Errors
+	///
+	/// Returns an error of kind [`InvalidInput`](std::io::ErrorKind::InvalidInput) if `map` returns a destination that isn't the length of the payload.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRx::run`].
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, ViaductMappedEvent, MappedPayload, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run_mapped(
+	///     |_packet_type, len| (len > 1024 * 1024).then(|| vec![0u8; len].into_boxed_slice()),
+	///     |event| match event {
+	///         ViaductMappedEvent::Rpc(MappedPayload::Mapped(bytes)) => println!("Received a {} byte RPC", bytes.len()),
+	///         ViaductMappedEvent::Rpc(MappedPayload::Buffered(rpc)) => println!("RPC received: {rpc:?}"),
+	///
+	///         ViaductMappedEvent::Request { responder, .. } => {
+	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///         }
+	///
+	///         // Handles shared by the peer process, and any kinds of event added in the future
+	///         _ => {}
+	///     },
+	/// ).unwrap();
+	/// ```
+	pub fn run_mapped<M, Map, EventHandler>(mut self, map: Map, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		M: AsMut<[u8]>,
+		Map: FnMut(PacketType, usize) -> Option<M>,
+		EventHandler: FnMut(ViaductMappedEvent<M, RpcTx, RequestTx, RpcRx, RequestRx>),
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		let mut destination = MapDestination(map);
+		loop {
+			let event = match self.recv(&mut destination) {
+				Ok(Some(event)) => event,
+				Ok(None) => continue,
+				Err(err) => return stopped(err),
+			};
+
+			#[cfg(feature = "tracing")]
+			let _span = match &event {
+				ViaductMappedEvent::Request { responder, .. } => Some(
+					tracing::debug_span!("viaduct_request_received", request_id = %responder.request_id, context = responder.context()).entered(),
+				),
+				_ => None,
+			};
+
+			event_handler(event);
+		}
+	}
+
+	/// Runs the event loop over the packets in `reader` instead of the pipe from the peer process, returning once `reader` runs dry.
+	///
+	/// This is intended for testing event handlers deterministically, on the current thread and without a peer process. Pair it with `test_util::in_memory` (requires the `test-util` feature) to get a viaduct whose responses, RPCs and requests are written to memory for inspection, and `test_util::rpc_frame` and `test_util::request_frame` to build the packets to feed in.
+	///
+	/// The viaduct stops reading from its pipe, which is closed. Anything the event handler sends still goes to the viaduct's usual destination.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRx::run`].
+	pub fn run_from_reader<EventHandler>(mut self, reader: impl Read + Send + 'static, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		self.rx = PipeReader::Reader(Box::new(reader));
+		loop {
+			match self.recv(&mut ()) {
+				Ok(Some(event)) => handle_event(&mut event_handler, event),
+				Ok(None) => {}
+				Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+				Err(err) => return stopped(err),
+			}
+		}
+	}
+
+	/// Pins the current thread to a specific CPU core, then runs the event loop. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// Use [`core_affinity::get_core_ids`] to list the available cores.
+	///
+	/// See [`ViaductRx::run`] for more information.
+	#[cfg(feature = "core_affinity")]
+	pub fn run_on_core<EventHandler>(self, core_id: core_affinity::CoreId, event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+	{
+		if !core_affinity::set_for_current(core_id) {
+			return Err(std::io::Error::other(format!("Failed to pin the event loop to core {}", core_id.id)));
+		}
+
+		self.run(event_handler)
+	}
+
+	/// Runs the event loop, dispatching events to a pool of `num_threads` worker threads. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// The calling thread reads from the viaduct and hands RPCs and requests to the workers through a shared queue, each of which calls `event_handler`.
+	/// Responses to requests sent from this process are still routed by the calling thread.
+	///
+	/// Unlike [`ViaductRx::run`], events are **not** guaranteed to be handled in the order they were received, as they are processed in parallel.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `num_threads` is zero.
+	///
+	/// This function will panic if the peer process sends some data (RPC or request) and this process fails to deserialize it.
+	pub fn run_pool<EventHandler>(mut self, num_threads: usize, event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: Fn(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>) + Sync,
+		RpcTx: Send,
+		RequestTx: Send,
+		RpcRx: Send,
+		RequestRx: Send,
+	{
+		assert_ne!(num_threads, 0, "Viaduct event loop pool must have at least one thread");
+
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		let (queue_tx, queue_rx) = std::sync::mpsc::sync_channel(num_threads);
+		let queue_rx = Mutex::new(queue_rx);
+
+		std::thread::scope(|scope| {
+			// Dropping the queue's sender when we return will stop the workers
+			let queue_tx = queue_tx;
+
+			for _ in 0..num_threads {
+				let queue_rx = &queue_rx;
+				let mut event_handler = &event_handler;
+				scope.spawn(move || loop {
+					let event = match queue_rx.lock().recv() {
+						Ok(event) => event,
+						Err(_) => break,
+					};
+					handle_event(&mut event_handler, event);
+				});
+			}
+
+			loop {
+				match self.recv(&mut ()) {
+					Ok(Some(event)) => {
+						if queue_tx.send(event).is_err() {
+							// A worker panicked
+							return Ok(());
+						}
+					}
+					Ok(None) => {}
+					Err(err) => return stopped(err),
+				}
+			}
+		})
+	}
+
+	/// Runs the event loop, handling the events that are waiting to be handled in order of [`Priority`] rather than in the order they arrived. This function will never return unless an error occurs, or it is [shut down](ViaductRx::shutdown_handle).
+	///
+	/// The calling thread reads from the viaduct into a queue, from which a worker thread takes the highest priority event to pass to `event_handler` each time it finishes with the last one. Requests sent with [`ViaductTx::request_priority`] have the priority they were sent with, while RPCs and other requests have [`Priority::Normal`]. Events of the same priority are handled in the order they arrived.
+	///
+	/// The queue is unbounded, so the peer can get as far ahead of the event handler as it likes. Responses to requests sent from this process are still routed by the calling thread, so they aren't held up by the queue.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRx::run`].
+	pub fn run_prioritized<EventHandler>(mut self, mut event_handler: EventHandler) -> Result<(), std::io::Error>
+	where
+		EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>) + Send,
+		RpcTx: Send,
+		RequestTx: Send,
+		RpcRx: Send,
+		RequestRx: Send,
+	{
+		let _event_loop = EventLoopGuard::enter(&self.tx);
+		let queue = PriorityQueue::default();
+
+		std::thread::scope(|scope| {
+			let queue = &queue;
+			scope.spawn(move || {
+				// If the event handler panics, stop the event loop from queueing events that will never be handled
+				let _close = CloseOnDrop(queue);
+				while let Some(event) = queue.pop() {
+					handle_event(&mut event_handler, event);
+				}
+			});
+
+			let result = loop {
+				match self.recv(&mut ()) {
+					Ok(Some(event)) => {
+						let priority = match &event {
+							ViaductEvent::Request { responder, .. } => responder.priority,
+							_ => Priority::Normal,
+						};
+						if !queue.push(priority, event) {
+							// The event handler panicked
+							return Ok(());
+						}
+					}
+					Ok(None) => {}
+					Err(err) => break stopped(err),
+				}
+			};
+
+			// Let the event handler finish what's left in the queue before returning
+			queue.close();
+			result
+		})
+	}
+
+	/// Returns how many messages this viaduct has sent and received, and how many bytes they took up.
+	///
+	/// This is shared with the viaduct's [`ViaductTx`]; see [`ViaductTx::metrics`].
+	#[inline]
+	pub fn metrics(&self) -> crate::ViaductMetrics {
+		self.tx.metrics()
+	}
+
+	/// Returns the cumulative time this viaduct has spent serializing, writing, reading and deserializing packets.
+	///
+	/// This is shared with the viaduct's [`ViaductTx`]; see [`ViaductTx::timings`].
+	///
+	/// Requires the `timing` feature.
+	#[cfg(feature = "timing")]
+	#[inline]
+	pub fn timings(&self) -> crate::ViaductTimings {
+		self.tx.timings()
+	}
+
+	/// Returns whether `capability` can be used on this viaduct.
+	///
+	/// See [`ViaductTx::peer_supports`].
+	#[inline]
+	pub fn peer_supports(&self, capability: Capability) -> bool {
+		self.tx.peer_supports(capability)
+	}
+
+	/// Returns why the viaduct was closed, or `None` if it is still open.
+	///
+	/// See [`ViaductTx::close_reason`].
+	#[inline]
+	pub fn close_reason(&self) -> Option<CloseReason> {
+		self.tx.close_reason()
+	}
+
+	/// Returns whether receiving blocks until the peer process sends something, which is the default.
+	///
+	/// See [`ViaductRx::set_nonblocking`].
+	#[inline]
+	pub fn is_blocking(&self) -> bool {
+		!self.nonblocking
+	}
+
+	/// Switches the viaduct between blocking and non-blocking receiving.
+	///
+	/// In non-blocking mode, receiving fails with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock) instead of waiting when the peer process hasn't sent anything, so [`ViaductRx::peek_packet_type`] can be used to check for packets without blocking, and the event loop returns as soon as it runs out of packets. Packets that have started arriving are always received in full, so the viaduct can't be left halfway through one.
+	///
+	/// On Unix, the pipe itself is switched to non-blocking mode with `fcntl`, so it can be registered with an external event loop such as `epoll`. Anonymous pipes on Windows can't be, so the pipe is checked for data before each packet instead. Sending is unaffected either way.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), std::io::Error> {
+		if let PipeReader::Pipe(pipe) = &self.rx {
+			os::set_nonblocking(pipe, nonblocking)?;
+		}
+		self.nonblocking = nonblocking;
+		Ok(())
+	}
+
+	/// Returns a handle that can stop the event loop from any thread.
+	///
+	/// Once [`ShutdownHandle::shutdown`] is called, the event loop finishes handling the packet it is receiving, if any, and then returns `Ok(())` instead of waiting for the next one. This is the clean way to stop a viaduct whose peer process is still running, as the event loop would otherwise block on the pipe until the peer sends something or exits. Receiving directly afterwards, such as with [`ViaductRx::peek_packet_type`], fails with an error of kind [`Interrupted`](std::io::ErrorKind::Interrupted).
+	///
+	/// On Unix, the event loop waits on the pipe and a second pipe that the handle writes to, so it is woken immediately. Anonymous pipes on Windows can't be waited on alongside anything else, so the event loop checks for the signal every millisecond while waiting instead.
+	pub fn shutdown_handle(&mut self) -> Result<ShutdownHandle, std::io::Error> {
+		if let Some(listener) = &self.shutdown {
+			return Ok(ShutdownHandle(listener.signal.clone()));
+		}
+
+		#[cfg(unix)]
+		let (wake_w, wake_r) = {
+			let (wake_w, wake_r) = interprocess::unnamed_pipe::pipe()?;
+			os::disinherit(&wake_w)?;
+			os::disinherit(&wake_r)?;
+			(wake_w, wake_r)
+		};
+
+		let signal = Arc::new(ShutdownSignal {
+			requested: AtomicBool::new(false),
+			#[cfg(unix)]
+			wake: Mutex::new(wake_w),
+		});
+		self.shutdown = Some(ShutdownListener {
+			signal: signal.clone(),
+			#[cfg(unix)]
+			wake: wake_r,
+		});
+		Ok(ShutdownHandle(signal))
+	}
+
+	/// Returns the file descriptor of the pipe the viaduct receives from, so it can be registered with an external event loop such as `mio` or `epoll`.
+	///
+	/// Switch the viaduct to [non-blocking](ViaductRx::set_nonblocking) mode, and call [`ViaductRx::recv_one`] whenever the pipe becomes readable until it fails with [`WouldBlock`](std::io::ErrorKind::WouldBlock). The descriptor still belongs to the viaduct, so it mustn't be closed or read from.
+	#[cfg(unix)]
+	pub fn as_raw_reader(&self) -> std::os::unix::io::RawFd {
+		use os::RawPipe;
+		match &self.rx {
+			PipeReader::Pipe(pipe) => pipe.as_raw(),
+			PipeReader::Reader(_) => unreachable!("The viaduct only reads from elsewhere in ViaductRx::run_from_reader"),
+		}
+	}
+
+	/// Returns the handle of the pipe the viaduct receives from, so it can be waited on by an external event loop.
+	///
+	/// Anonymous pipes on Windows can't be used with overlapped I/O, so this is mostly useful for checking whether the pipe has data to read. See [`ViaductRx::recv_one`]. The handle still belongs to the viaduct, so it mustn't be closed or read from.
+	#[cfg(windows)]
+	pub fn as_raw_reader(&self) -> std::os::windows::io::RawHandle {
+		use os::RawPipe;
+		match &self.rx {
+			PipeReader::Pipe(pipe) => pipe.as_raw(),
+			PipeReader::Reader(_) => unreachable!("The viaduct only reads from elsewhere in ViaductRx::run_from_reader"),
+		}
+	}
+
+	/// Receives a single packet, returning the event it carried, rather than running the event loop.
+	///
+	/// This is for pumping events on demand, such as from an external event loop (see [`ViaductRx::as_raw_reader`]). Returns `None` if the packet didn't carry an event: a response, which is routed to its requester, or a fragment of a packet that hasn't been received in full yet. In [non-blocking](ViaductRx::set_nonblocking) mode, this fails with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock) if nothing has been received.
+	///
+	/// Any other error closes the viaduct, just as it would stop the event loop.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRx::run`].
+	#[inline]
+	pub fn recv_one(&mut self) -> Result<Option<ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>>, std::io::Error> {
+		self.recv(&mut ())
+	}
+
+	/// Receives a single packet like [`ViaductRx::recv_one`], but gives up and returns `None` if nothing arrives within `timeout`.
+	///
+	/// This lets the viaduct be interleaved with other periodic work on a single thread. The timeout only covers waiting for a packet to start arriving: once it has, it's received in full, which can take longer. `None` is also returned if the packet didn't carry an event, as with [`ViaductRx::recv_one`].
+	///
+	/// # Panics
+	///
+	/// See [`ViaductRx::run`].
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use std::time::Duration;
+	/// # use viaduct::{ViaductChild, ViaductEvent, doctest::*};
+	/// # let mut rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// # fn do_periodic_work() {}
+	/// loop {
+	///     if let Some(ViaductEvent::Rpc(rpc)) = rx.recv_one_timeout(Duration::from_millis(16)).unwrap() {
+	///         println!("RPC received: {rpc:?}");
+	///     }
+	///     do_periodic_work();
+	/// }
+	/// ```
+	pub fn recv_one_timeout(&mut self, timeout: Duration) -> Result<Option<ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>>, std::io::Error> {
+		if let (None, true, PipeReader::Pipe(pipe)) = (&self.peeked, self.batch.is_empty(), &self.rx) {
+			if !os::wait_readable_timeout(pipe, timeout).map_err(|err| self.close_with(err))? {
+				return Ok(None);
+			}
+		}
+		self.recv(&mut ())
+	}
+
+	/// Returns the type of the next packet without consuming it, blocking until one arrives.
+	///
+	/// The packet is buffered, so the event loop will still see it in full. Responses to requests sent from this process are routed to their requesters while peeking, just as they would be by the event loop, so they are never reported.
+	pub fn peek_packet_type(&mut self) -> Result<PacketType, std::io::Error> {
+		self.peek_next().map_err(|err| self.close_with(err))
+	}
+
+	fn peek_next(&mut self) -> Result<PacketType, std::io::Error> {
+		if !self.batch.is_empty() {
+			return Ok(PacketType::Rpc);
+		}
+
+		loop {
+			let frame = match self.peeked.take() {
+				Some(frame) => frame,
+				None => match self.next_frame().map_err(peer_gone)? {
+					Some(frame) => frame,
+					None => continue,
+				},
+			};
+
+			let packet_type = match frame.packet_type() {
+				Some(RPC | WINDOWED_RPC | BATCH) => PacketType::Rpc,
+				Some(REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY) => PacketType::Request,
+				Some(HANDLE) => PacketType::Handle,
+				Some(SOME_RESPONSE | NONE_RESPONSE | STREAM_CHUNK | STREAM_END | UPGRADE | UPGRADE_ACK | WINDOW_ACK | CREDIT) => {
+					self.recv_frame::<ViaductEvent<_, _, _, _>, _>(frame, &mut ())?;
+					continue;
+				}
+				_ => return Err(unknown_packet_type()),
+			};
+
+			self.peeked = Some(frame);
+			return Ok(packet_type);
+		}
+	}
+
+	/// Receives a single packet from the viaduct.
+	///
+	/// Responses are routed to their requesters internally, in which case this returns `None`. Errors stop the event loop, so they close the viaduct.
+	fn recv<Event, Dest>(&mut self, destination: &mut Dest) -> Result<Option<Event>, std::io::Error>
+	where
+		Event: RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>,
+		Dest: Destination<Target = Event::Target>,
+	{
+		self.recv_next(destination).map_err(|err| self.close_with(err))
+	}
+
+	fn recv_next<Event, Dest>(&mut self, destination: &mut Dest) -> Result<Option<Event>, std::io::Error>
+	where
+		Event: RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>,
+		Dest: Destination<Target = Event::Target>,
+	{
+		if let Some(rpc) = self.batch.next()? {
+			self.buf.clear();
+			self.buf.extend_from_slice(rpc);
+			return Ok(Some(Event::rpc(Payload::Buffered(&mut self.buf), &self.tx)));
+		}
+
+		let frame = match self.peeked.take() {
+			Some(frame) => frame,
+			None => match self.next_frame().map_err(peer_gone)? {
+				Some(frame) => frame,
+				None => return Ok(None),
+			},
+		};
+
+		match self.recv_frame(frame, destination).map_err(peer_gone) {
+			// Only an unknown packet type means we've lost track of where frames start; anything else, such as an exceeded limit, is reported as usual
+			Err(err) if self.resync && is_unknown_packet_type(&err) => {
+				#[cfg(feature = "tracing")]
+				tracing::warn!(%err, "viaduct stream desynchronized, scanning for the next resync marker");
+
+				self.resync_stream([0; RESYNC_MARKER.len()])?;
+				Ok(None)
+			}
+			result => result,
+		}
+	}
+
+	/// Closes the viaduct because of an error that stopped the event loop, failing any requests that are still waiting for a response, as none will arrive now.
+	fn close_with(&self, err: std::io::Error) -> std::io::Error {
+		if is_peer_closed(&err) {
+			self.tx.0.close(CloseReason::PeerClosed);
+		} else if err.kind() != std::io::ErrorKind::WouldBlock && !is_shutdown(&err) {
+			self.tx.0.close(CloseReason::from_io(&err));
+		}
+		err
+	}
+
+	/// Reads the resync marker that precedes the next frame, scanning forward to the next one if it isn't there.
+	fn recv_marker(&mut self) -> Result<(), std::io::Error> {
+		if std::mem::take(&mut self.marker_consumed) {
+			return Ok(());
+		}
+
+		let mut marker = [0u8; RESYNC_MARKER.len()];
+		self.rx.read_exact(&mut marker)?;
+		if marker != RESYNC_MARKER {
+			#[cfg(feature = "tracing")]
+			tracing::warn!("viaduct stream desynchronized, scanning for the next resync marker");
+
+			self.resync_stream(marker)?;
+		}
+		Ok(())
+	}
+
+	/// Discards bytes from the stream until `window` (the last bytes read) matches the resync marker.
+	fn resync_stream(&mut self, mut window: [u8; RESYNC_MARKER.len()]) -> Result<(), std::io::Error> {
+		while window != RESYNC_MARKER {
+			let mut byte = [0u8];
+			self.rx.read_exact(&mut byte)?;
+			window.copy_within(1.., 0);
+			window[RESYNC_MARKER.len() - 1] = byte[0];
+		}
+		self.marker_consumed = true;
+		Ok(())
+	}
+
+	/// Reads the next frame's packet type, reassembling it first if it was fragmented.
+	///
+	/// Returns `None` if a fragment was received, but the packet isn't complete yet.
+	fn next_frame(&mut self) -> Result<Option<Frame>, std::io::Error> {
+		if let Some(listener) = &self.shutdown {
+			if listener.signal.requested.load(Ordering::Acquire) {
+				return Err(shutdown_requested());
+			}
+
+			if let (false, PipeReader::Pipe(pipe)) = (self.nonblocking, &self.rx) {
+				#[cfg(unix)]
+				let readable = os::wait_readable_unless(pipe, &listener.wake)?;
+				#[cfg(windows)]
+				let readable = os::wait_readable_unless(pipe, || listener.signal.requested.load(Ordering::Acquire))?;
+
+				if !readable {
+					return Err(shutdown_requested());
+				}
+			}
+		}
+
+		if let (true, PipeReader::Pipe(pipe)) = (self.nonblocking, &self.rx) {
+			if !os::poll_readable(pipe)? {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::WouldBlock,
+					"Nothing has been received from the peer process yet",
+				));
+			}
+		}
+
+		if self.resync {
+			self.recv_marker()?;
+		}
+
+		if self.timestamps {
+			let mut timestamp = [0u8; size_of::<u64>()];
+			self.rx.read_exact(&mut timestamp)?;
+			self.timestamp = Some(Timestamp::from_nanos(u64::from_ne_bytes(timestamp)));
+		}
+
+		let packet_type = {
+			let mut packet_type = [0u8];
+			self.rx.read_exact(&mut packet_type)?;
+			packet_type[0]
+		};
+
+		match packet_type {
+			FRAGMENT | FRAGMENT_END => {
+				let read = Stopwatch::start();
+				let packet = self.reassembly.recv(&mut self.rx, packet_type == FRAGMENT_END)?;
+				self.tx.0.timings.record_read(read.elapsed());
+
+				Ok(packet.map(Frame::Reassembled))
+			}
+
+			_ => Ok(Some(Frame::Direct(packet_type))),
+		}
+	}
+
+	/// Receives the rest of a frame.
+	fn recv_frame<Event, Dest>(&mut self, frame: Frame, destination: &mut Dest) -> Result<Option<Event>, std::io::Error>
+	where
+		Event: RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>,
+		Dest: Destination<Target = Event::Target>,
+	{
+		let mut pooled = self.pool.as_ref().map(|pool| pool.acquire());
+		let buf = pooled.as_mut().unwrap_or(&mut self.buf);
+
+		let event = match frame {
+			Frame::Reassembled(packet) => {
+				let event = (|| {
+					let mut packet = packet.as_slice();
+					let packet_type = {
+						let mut packet_type = [0u8];
+						packet.read_exact(&mut packet_type)?;
+						packet_type[0]
+					};
+					Self::recv_packet(
+						packet_type,
+						&mut packet,
+						buf,
+						destination,
+						&mut self.batch,
+						&self.tx,
+						&mut self.resync,
+						self.max_message_size,
+						&mut self.on_raw_recv,
+						&mut self.on_frame,
+					)
+				})();
+				self.reassembly.release(packet);
+				event
+			}
+
+			Frame::Direct(packet_type) => Self::recv_packet(
+				packet_type,
+				&mut self.rx,
+				buf,
+				destination,
+				&mut self.batch,
+				&self.tx,
+				&mut self.resync,
+				self.max_message_size,
+				&mut self.on_raw_recv,
+				&mut self.on_frame,
+			),
+		};
+
+		if let (Some(pool), Some(buf)) = (&self.pool, pooled) {
+			pool.release(buf);
+		}
+
+		event
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn recv_packet<Event, Dest>(
+		packet_type: u8,
+		rx: &mut impl Read,
+		buf: &mut Vec<u8>,
+		destination: &mut Dest,
+		batch: &mut RpcBatch,
+		tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		resync: &mut bool,
+		max_message_size: Option<usize>,
+		on_raw_recv: &mut Option<RawHook>,
+		on_frame: &mut Option<FrameHook>,
+	) -> Result<Option<Event>, std::io::Error>
+	where
+		Event: RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>,
+		Dest: Destination<Target = Event::Target>,
+	{
+		let recv_into_buf = |rx: &mut dyn Read, buf: &mut Vec<u8>| -> Result<(), std::io::Error> {
+			let len = recv_len(rx, max_message_size, &tx.0.metrics)?;
+			buf.resize(len, 0);
+			rx.read_exact(buf)?;
+			Ok(())
+		};
+
+		let mut frame = |packet_type: PacketType, len: usize, request_id: Option<Uuid>| {
+			if let Some(on_frame) = on_frame {
+				on_frame(FrameInfo {
+					packet_type,
+					len,
+					request_id: request_id.map(|request_id| request_id.as_u128()),
+				});
+			}
+		};
+
+		let compressed = packet_type & COMPRESSED != 0;
+		let packet_type = packet_type & !COMPRESSED;
+		let recv_payload = |rx: &mut dyn Read, buf: &mut Vec<u8>| -> Result<(), std::io::Error> {
+			recv_into_buf(rx, buf)?;
+			if compressed {
+				decompress(buf, max_message_size)?;
+			}
+			Ok(())
+		};
+
+		match packet_type {
+			RPC | WINDOWED_RPC | BATCH if RpcRx::UNINHABITED => Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Received an RPC, but this viaduct never receives RPCs",
+			)),
+
+			REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY if RequestRx::UNINHABITED => Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Received a request, but this viaduct never receives requests",
+			)),
+
+			RPC | WINDOWED_RPC => {
+				let read = Stopwatch::start();
+
+				if packet_type == WINDOWED_RPC {
+					let ack_requested = {
+						let mut ack_requested = [0u8];
+						rx.read_exact(&mut ack_requested)?;
+						ack_requested[0] != 0
+					};
+
+					let received = tx.0.rpc_window.received.fetch_add(1, Ordering::Relaxed) + 1;
+					if ack_requested {
+						let mut state = tx.0.state.lock();
+						let mut header = [WINDOW_ACK; 1 + size_of::<u64>()];
+						header[1..].copy_from_slice(&received.to_ne_bytes());
+						ViaductTxState::send_packet(&mut state, &header, false, true)?;
+					}
+				}
+				tx.0.grant_rpc_credits()?;
+
+				let mut payload = recv_mapped_payload(rx, buf, compressed, max_message_size, &tx.0.metrics, PacketType::Rpc, destination)?;
+				tx.0.timings.record_read(read.elapsed());
+
+				frame(PacketType::Rpc, payload.as_bytes().len(), None);
+				if let Some(on_raw_recv) = on_raw_recv {
+					on_raw_recv(PacketType::Rpc, payload.as_bytes());
+				}
+
+				Ok(Some(Event::rpc(payload, tx)))
+			}
+
+			BATCH => {
+				let read = Stopwatch::start();
+				recv_payload(rx, buf)?;
+				tx.0.timings.record_read(read.elapsed());
+				tx.0.grant_rpc_credits()?;
+
+				frame(PacketType::Rpc, buf.len(), None);
+				if let Some(on_raw_recv) = on_raw_recv {
+					on_raw_recv(PacketType::Rpc, buf);
+				}
+
+				// The RPCs are handed out one at a time by the following calls to `recv`
+				batch.replace(buf);
+				Ok(None)
+			}
+
+			REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY => {
+				let read = Stopwatch::start();
+
+				let priority = if packet_type == REQUEST_WITH_PRIORITY {
+					let mut priority = [0u8];
+					rx.read_exact(&mut priority)?;
+					Priority::from_u8(priority[0])
+						.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Received a request with an unknown priority"))?
+				} else {
+					Priority::Normal
+				};
+
+				let request_id = {
+					let mut request_id = [0u8; 16];
+					rx.read_exact(&mut request_id)?;
+					Uuid::from_bytes(request_id)
+				};
+
+				let context = if packet_type == REQUEST_WITH_CONTEXT {
+					recv_into_buf(rx, buf)?;
+					Some(
+						std::str::from_utf8(buf)
+							.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?
+							.to_owned(),
+					)
+				} else {
+					None
+				};
+
+				let mut payload = recv_mapped_payload(rx, buf, compressed, max_message_size, &tx.0.metrics, PacketType::Request, destination)?;
+				tx.0.timings.record_read(read.elapsed());
+
+				frame(PacketType::Request, payload.as_bytes().len(), Some(request_id));
+				if let Some(on_raw_recv) = on_raw_recv {
+					on_raw_recv(PacketType::Request, payload.as_bytes());
+				}
+
+				if let Some(limit) = &tx.0.responder_limit {
+					// Stop reading until there's room for another responder, so the peer is held back instead
+					limit.acquire();
+				}
+
+				if let Some(responders) = &tx.0.responders {
+					responders.lock().insert(request_id);
+				}
+
+				let responder = ViaductRequestResponder {
+					tx: tx.clone(),
+					request_id,
+					context,
+					priority,
+					responded: false,
+				};
+				Ok(Some(Event::request(payload, tx, responder)))
+			}
+
+			SOME_RESPONSE => {
+				let read = Stopwatch::start();
+
+				let request_id = {
+					let mut request_id = [0u8; 16];
+					rx.read_exact(&mut request_id)?;
+					Uuid::from_bytes(request_id)
+				};
+
+				recv_payload(rx, buf)?;
+				tx.0.timings.record_read(read.elapsed());
+
+				frame(PacketType::Response, buf.len(), Some(request_id));
+				tx.0.metrics.record_response_received();
+				if let Some(on_raw_recv) = on_raw_recv {
+					on_raw_recv(PacketType::Response, buf);
+				}
+
+				// Hand a copy of the response over to the requester, unless the request was cancelled, in which case it's discarded
+				// The buffer itself may belong to the buffer pool, so it has to stay behind to be released back into it
+				let pending = tx.0.pending.lock().remove(&request_id);
+				if let Some(pending) = pending {
+					pending.deliver(Some(buf.to_vec()));
+				}
+
+				Ok(None)
+			}
+
+			STREAM_CHUNK => {
+				let read = Stopwatch::start();
+
+				let request_id = {
+					let mut request_id = [0u8; 16];
+					rx.read_exact(&mut request_id)?;
+					Uuid::from_bytes(request_id)
+				};
+
+				recv_payload(rx, buf)?;
+				tx.0.timings.record_read(read.elapsed());
+
+				frame(PacketType::Response, buf.len(), Some(request_id));
+				if let Some(on_raw_recv) = on_raw_recv {
+					on_raw_recv(PacketType::Response, buf);
+				}
+
+				// The request stays pending until the stream ends
+				let mut pending = tx.0.pending.lock();
+				match pending.get(&request_id) {
+					Some(PendingResponse::Stream(stream)) => stream.push(buf.to_vec()),
+
+					Some(_) => {
+						let expecting_one = pending.remove(&request_id).unwrap();
+						drop(pending);
+						expecting_one.abandon(std::io::Error::new(
+							std::io::ErrorKind::InvalidData,
+							"Received a streamed response to a request that expects a single response",
+						));
+					}
+
+					// The request was cancelled, so the chunk is discarded
+					None => {}
+				}
+
+				Ok(None)
+			}
+
+			NONE_RESPONSE | STREAM_END => {
+				let request_id = {
+					let mut request_id = [0u8; 16];
+					rx.read_exact(&mut request_id)?;
+					Uuid::from_bytes(request_id)
+				};
+
+				frame(PacketType::Response, 0, Some(request_id));
+				tx.0.metrics.record_response_received();
+
+				let pending = tx.0.pending.lock().remove(&request_id);
+				if let Some(pending) = pending {
+					pending.deliver(None);
+				}
+
+				Ok(None)
+			}
+
+			#[cfg(windows)]
+			HANDLE => {
+				let handle = {
+					let mut handle = [0u8; size_of::<u64>()];
+					rx.read_exact(&mut handle)?;
+					u64::from_ne_bytes(handle)
+				};
+
+				Ok(Some(Event::handle(crate::handle::receive(tx.0.peer_process.as_ref(), handle)?)))
+			}
+
+			#[cfg(unix)]
+			HANDLE => {
+				// The file descriptor itself is sent over the handle socket, just before this packet
+				rx.read_exact(&mut [0u8; size_of::<u64>()])?;
+
+				let socket = tx.0.handle_socket.as_ref().ok_or_else(|| {
+					std::io::Error::new(
+						std::io::ErrorKind::InvalidData,
+						"Received a file descriptor, but there is no socket to receive it from",
+					)
+				})?;
+				Ok(Some(Event::handle(crate::handle::receive(socket)?)))
+			}
+
+			WINDOW_ACK => {
+				let acked = {
+					let mut acked = [0u8; size_of::<u64>()];
+					rx.read_exact(&mut acked)?;
+					u64::from_ne_bytes(acked)
+				};
+
+				tx.0.rpc_window.ack(acked);
+				Ok(None)
+			}
+
+			CREDIT => {
+				let granted = {
+					let mut granted = [0u8; size_of::<u64>()];
+					rx.read_exact(&mut granted)?;
+					u64::from_ne_bytes(granted)
+				};
+
+				tx.0.rpc_credits.grant(granted);
+				Ok(None)
+			}
+
+			// The peer won't send anything else, so stop as if the event loop had been shut down
+			GOODBYE => Err(peer_closed()),
+
+			UPGRADE => {
+				let (request_id, capability) = {
+					let mut upgrade = [0u8; 16 + 1];
+					rx.read_exact(&mut upgrade)?;
+					(Uuid::from_slice(&upgrade[..16]).unwrap(), upgrade[16])
+				};
+
+				if capability != Capability::ResyncMarkers as u8 {
+					return Err(std::io::Error::new(
+						std::io::ErrorKind::InvalidData,
+						"Peer requested an upgrade to a capability that can't be upgraded",
+					));
+				}
+
+				// Everything the peer sends after the upgrade request uses the new framing
+				*resync = true;
+
+				// ...and everything we send after acknowledging it does too
+				let mut state = tx.0.state.lock();
+				let mut header = [UPGRADE_ACK; 1 + 16];
+				header[1..].copy_from_slice(request_id.as_bytes());
+				ViaductTxState::send_packet(&mut state, &header, false, true)?;
+				state.resync = true;
+
+				Ok(None)
+			}
+
+			UPGRADE_ACK => {
+				let request_id = {
+					let mut request_id = [0u8; 16];
+					rx.read_exact(&mut request_id)?;
+					Uuid::from_bytes(request_id)
+				};
+
+				// Everything the peer sends after acknowledging the upgrade uses the new framing
+				*resync = true;
+
+				let pending = tx.0.pending.lock().remove(&request_id);
+				if let Some(pending) = pending {
+					pending.deliver(None);
+				}
+
+				Ok(None)
+			}
+
+			_ => Err(unknown_packet_type()),
+		}
+	}
+}
+
+/// How a received RPC or request is turned into an event: deserialized straight away for a [`ViaductEvent`], or left for the event handler to deserialize for a [`ViaductLazyEvent`].
+trait RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>: Sized
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Where the payloads of RPCs and requests can be received, other than the receive buffer.
+	type Target: AsMut<[u8]>;
+
+	fn rpc(payload: Payload<'_, Self::Target>, tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self;
+
+	fn request(
+		payload: Payload<'_, Self::Target>,
+		tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) -> Self;
+
+	fn handle(handle: crate::handle::SharedHandle) -> Self;
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx> for ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	type Target = NoTarget;
+
+	#[inline]
+	fn rpc(payload: Payload<'_, NoTarget>, tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		Self::Rpc(deserialize(payload.into_buf(), tx, "Failed to deserialize RpcRx"))
+	}
+
+	#[inline]
+	fn request(
+		payload: Payload<'_, NoTarget>,
+		tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) -> Self {
+		Self::Request {
+			request: deserialize(payload.into_buf(), tx, "Failed to deserialize RequestRx"),
+			responder,
+		}
+	}
+
+	#[inline]
+	fn handle(handle: crate::handle::SharedHandle) -> Self {
+		Self::Handle(handle)
+	}
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx> for ViaductLazyEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	type Target = NoTarget;
+
+	#[inline]
+	fn rpc(payload: Payload<'_, NoTarget>, _tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		Self::Rpc(LazyMessage::new(payload.into_buf().to_vec()))
+	}
+
+	#[inline]
+	fn request(
+		payload: Payload<'_, NoTarget>,
+		_tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) -> Self {
+		Self::Request {
+			request: LazyMessage::new(payload.into_buf().to_vec()),
+			responder,
+		}
+	}
+
+	#[inline]
+	fn handle(handle: crate::handle::SharedHandle) -> Self {
+		Self::Handle(handle)
+	}
+}
+
+/// An event received by [`ViaductRx::run_with_error_handler`], which may have failed to deserialize.
+enum CheckedEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	Event(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+	Malformed(DeserializeError<RpcRx, RequestRx>),
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx> for CheckedEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	type Target = NoTarget;
+
+	#[inline]
+	fn rpc(payload: Payload<'_, NoTarget>, tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		match try_deserialize(payload.into_buf(), tx) {
+			Ok(rpc) => Self::Event(ViaductEvent::Rpc(rpc)),
+			Err(err) => Self::Malformed(DeserializeError::Rpc(err)),
+		}
+	}
+
+	#[inline]
+	fn request(
+		payload: Payload<'_, NoTarget>,
+		tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) -> Self {
+		match try_deserialize(payload.into_buf(), tx) {
+			Ok(request) => Self::Event(ViaductEvent::Request { request, responder }),
+			Err(err) => Self::Malformed(DeserializeError::Request(err)),
+		}
+	}
+
+	#[inline]
+	fn handle(handle: crate::handle::SharedHandle) -> Self {
+		Self::Event(ViaductEvent::Handle(handle))
+	}
+}
+impl<M, RpcTx, RequestTx, RpcRx, RequestRx> RecvEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+	for ViaductMappedEvent<M, RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	M: AsMut<[u8]>,
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	type Target = M;
+
+	#[inline]
+	fn rpc(payload: Payload<'_, M>, tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		Self::Rpc(match payload {
+			Payload::Buffered(buf) => MappedPayload::Buffered(deserialize(buf, tx, "Failed to deserialize RpcRx")),
+			Payload::Mapped(target) => MappedPayload::Mapped(target),
+		})
+	}
+
+	#[inline]
+	fn request(
+		payload: Payload<'_, M>,
+		tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	) -> Self {
+		Self::Request {
+			request: match payload {
+				Payload::Buffered(buf) => MappedPayload::Buffered(deserialize(buf, tx, "Failed to deserialize RequestRx")),
+				Payload::Mapped(target) => MappedPayload::Mapped(target),
+			},
+			responder,
+		}
+	}
+
+	#[inline]
+	fn handle(handle: crate::handle::SharedHandle) -> Self {
+		Self::Handle(handle)
+	}
+}
+
+/// Deserializes a received RPC or request, recording how long it took.
+#[inline]
+fn deserialize<T, RpcTx, RequestTx, RpcRx, RequestRx>(buf: &[u8], tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>, msg: &str) -> T
+where
+	T: ViaductDeserialize,
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	try_deserialize(buf, tx).expect(msg)
+}
+
+fn try_deserialize<T, RpcTx, RequestTx, RpcRx, RequestRx>(buf: &[u8], tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Result<T, T::Error>
+where
+	T: ViaductDeserialize,
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	let deserialize = Stopwatch::start();
+	let message = T::from_pipeable(buf);
+	tx.0.timings.record_deserialize(deserialize.elapsed());
+	message
+}
+
+/// Chooses where the event loop receives the payloads of RPCs and requests.
+trait Destination {
+	type Target: AsMut<[u8]>;
+
+	/// Returns where to receive a payload of `len` bytes, or `None` to receive it into the receive buffer as usual.
+	fn destination(&mut self, packet_type: PacketType, len: usize) -> Option<Self::Target>;
+}
+
+/// Payloads are always received into the receive buffer.
+impl Destination for () {
+	type Target = NoTarget;
+
+	#[inline]
+	fn destination(&mut self, _packet_type: PacketType, _len: usize) -> Option<NoTarget> {
+		None
+	}
+}
+
+/// Payloads are received wherever the callback passed to [`ViaductRx::run_mapped`] says.
+struct MapDestination<Map>(Map);
+impl<M, Map> Destination for MapDestination<Map>
+where
+	M: AsMut<[u8]>,
+	Map: FnMut(PacketType, usize) -> Option<M>,
+{
+	type Target = M;
+
+	#[inline]
+	fn destination(&mut self, packet_type: PacketType, len: usize) -> Option<M> {
+		(self.0)(packet_type, len)
+	}
+}
+
+/// A destination that can never be chosen.
+enum NoTarget {}
+impl AsMut<[u8]> for NoTarget {
+	#[inline]
+	fn as_mut(&mut self) -> &mut [u8] {
+		match *self {}
+	}
+}
+
+/// Where the payload of an RPC or request ended up.
+enum Payload<'a, Target> {
+	Buffered(&'a mut Vec<u8>),
+	Mapped(Target),
+}
+impl<Target: AsMut<[u8]>> Payload<'_, Target> {
+	#[inline]
+	fn as_bytes(&mut self) -> &[u8] {
+		match self {
+			Self::Buffered(buf) => buf,
+			Self::Mapped(target) => target.as_mut(),
+		}
+	}
+}
+impl<'a> Payload<'a, NoTarget> {
+	#[inline]
+	fn into_buf(self) -> &'a mut Vec<u8> {
+		match self {
+			Self::Buffered(buf) => buf,
+			Self::Mapped(target) => match target {},
+		}
+	}
+}
+
+/// Writes all of `bufs`, in as few writes as the writer allows.
+///
+/// The parts of a packet are written together this way, so that a packet too large for the write buffer goes down the pipe in one `writev` on Unix, rather than one write per part.
+fn write_all_vectored(tx: &mut impl Write, mut bufs: &mut [IoSlice<'_>]) -> Result<(), std::io::Error> {
+	IoSlice::advance_slices(&mut bufs, 0);
+	while !bufs.is_empty() {
+		match tx.write_vectored(bufs) {
+			Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+			Ok(written) => IoSlice::advance_slices(&mut bufs, written),
+			Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(())
+}
+
+/// Reads the length that precedes a payload, refusing it if it's larger than `max_message_size`, before anything is allocated for it.
+///
+/// The payload is counted towards the bytes read in `metrics`, as the caller is about to read it.
+#[inline]
+fn recv_len(rx: &mut dyn Read, max_message_size: Option<usize>, metrics: &MetricsRecorder) -> Result<usize, std::io::Error> {
+	let mut len = [0u8; size_of::<u64>()];
+	rx.read_exact(&mut len)?;
+	check_message_size(u64::from_ne_bytes(len), max_message_size)?;
+	let len = usize::try_from(u64::from_ne_bytes(len)).expect("Viaduct packet was larger than what this architecture can handle");
+	metrics.record_read(len);
+	Ok(len)
+}
+
+#[inline]
+fn check_message_size(size: u64, max_message_size: Option<usize>) -> Result<(), std::io::Error> {
+	match max_message_size {
+		Some(limit) if size > limit as u64 => Err(ViaductError::ReceiveLimit { size, limit }.into()),
+		_ => Ok(()),
+	}
+}
+
+/// Receives the payload of an RPC or request into wherever `destination` chooses, falling back to `buf`.
+///
+/// Compressed payloads have to be decompressed in memory first, so they are copied into the destination afterwards.
+fn recv_mapped_payload<'a, Dest: Destination>(
+	rx: &mut dyn Read,
+	buf: &'a mut Vec<u8>,
+	compressed: bool,
+	max_message_size: Option<usize>,
+	metrics: &MetricsRecorder,
+	packet_type: PacketType,
+	destination: &mut Dest,
+) -> Result<Payload<'a, Dest::Target>, std::io::Error> {
+	let mut len = recv_len(rx, max_message_size, metrics)?;
+	if compressed {
+		buf.resize(len, 0);
+		rx.read_exact(buf)?;
+		decompress(buf, max_message_size)?;
+		len = buf.len();
+	}
+
+	let Some(mut target) = destination.destination(packet_type, len) else {
+		if !compressed {
+			buf.resize(len, 0);
+			rx.read_exact(buf)?;
+		}
+		return Ok(Payload::Buffered(buf));
+	};
+
+	let dest = target.as_mut();
+	if dest.len() != len {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			format!("The destination for a {len} byte payload was {} bytes long", dest.len()),
+		));
+	}
+
+	if compressed {
+		dest.copy_from_slice(buf);
+	} else {
+		rx.read_exact(dest)?;
+	}
+	Ok(Payload::Mapped(target))
+}
+
+/// Decompresses a payload the peer compressed, in place, refusing it if it would decompress to more than `max_message_size` bytes.
+pub(super) fn decompress(buf: &mut Vec<u8>, max_message_size: Option<usize>) -> Result<(), std::io::Error> {
+	#[cfg(feature = "compression")]
+	{
+		// The decompressed size is prepended, and is what gets allocated
+		if let Some(size) = buf.first_chunk::<4>() {
+			check_message_size(u32::from_le_bytes(*size).into(), max_message_size)?;
+		}
+
+		*buf = lz4_flex::decompress_size_prepended(buf).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+		Ok(())
+	}
+
+	#[cfg(not(feature = "compression"))]
+	{
+		let _ = (buf, max_message_size);
+		Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Received a compressed packet, but the compression feature is disabled",
+		))
+	}
+}
+
+/// Reaching the end of the stream means the peer has closed its side of the viaduct.
+#[inline]
+fn peer_gone(err: std::io::Error) -> std::io::Error {
+	if err.kind() == std::io::ErrorKind::UnexpectedEof && ViaductError::from_io(&err).is_none() {
+		ViaductError::PeerGone.into()
+	} else {
+		err
+	}
+}
+
+/// The payload of the error that receiving fails with once the event loop has been [shut down](ViaductRx::shutdown_handle).
+#[derive(Debug)]
+struct ShutdownRequested;
+impl std::fmt::Display for ShutdownRequested {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("The event loop was shut down")
+	}
+}
+impl std::error::Error for ShutdownRequested {}
+
+#[inline]
+fn shutdown_requested() -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::Interrupted, ShutdownRequested)
+}
+
+#[inline]
+fn is_shutdown(err: &std::io::Error) -> bool {
+	err.kind() == std::io::ErrorKind::Interrupted && err.get_ref().is_some_and(|err| err.is::<ShutdownRequested>())
+}
+
+/// The payload of the error that receiving fails with once the peer has [closed](ViaductTx::close) its side of the viaduct.
+#[derive(Debug)]
+struct PeerClosed;
+impl std::fmt::Display for PeerClosed {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Peer closed its side of the viaduct gracefully")
+	}
+}
+impl std::error::Error for PeerClosed {}
+
+#[inline]
+fn peer_closed() -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::BrokenPipe, PeerClosed)
+}
+
+#[inline]
+fn is_peer_closed(err: &std::io::Error) -> bool {
+	err.kind() == std::io::ErrorKind::BrokenPipe && err.get_ref().is_some_and(|err| err.is::<PeerClosed>())
+}
+
+/// The error for a packet type we don't recognise, which is most likely a sign that the stream has become desynchronized.
+#[derive(Debug)]
+struct UnknownPacketType;
+impl std::fmt::Display for UnknownPacketType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("Received an unknown packet type")
+	}
+}
+impl std::error::Error for UnknownPacketType {}
+
+#[inline]
+fn unknown_packet_type() -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::InvalidData, UnknownPacketType)
+}
+
+#[inline]
+fn is_unknown_packet_type(err: &std::io::Error) -> bool {
+	err.kind() == std::io::ErrorKind::InvalidData && err.get_ref().is_some_and(|err| err.is::<UnknownPacketType>())
+}
+
+/// Being shut down, or the peer closing its side of the viaduct gracefully, is how the event loop is meant to stop, so it isn't an error.
+#[inline]
+fn stopped(err: std::io::Error) -> Result<(), std::io::Error> {
+	if is_shutdown(&err) || is_peer_closed(&err) {
+		Ok(())
+	} else {
+		Err(err)
+	}
+}
+
+/// A handle that stops a viaduct's event loop, from any thread.
+///
+/// See [`ViaductRx::shutdown_handle`].
+#[derive(Clone)]
+pub struct ShutdownHandle(Arc<ShutdownSignal>);
+impl ShutdownHandle {
+	/// Stops the event loop once it has finished handling the packet it is receiving, if any, making it return `Ok(())`.
+	///
+	/// Calling this more than once, or after the event loop has already stopped, does nothing.
+	pub fn shutdown(&self) {
+		// The event loop also checks the flag, so there's nothing more to do if waking it fails.
+		#[cfg(unix)]
+		if !self.0.requested.swap(true, Ordering::AcqRel) {
+			let _ = self.0.wake.lock().write_all(&[0]);
+		}
+
+		#[cfg(windows)]
+		self.0.requested.store(true, Ordering::Release);
+	}
+
+	/// Returns whether [`ShutdownHandle::shutdown`] has been called.
+	#[inline]
+	pub fn is_shutdown(&self) -> bool {
+		self.0.requested.load(Ordering::Acquire)
+	}
+}
+impl std::fmt::Debug for ShutdownHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ShutdownHandle").field("shutdown", &self.is_shutdown()).finish()
+	}
+}
+
+struct ShutdownSignal {
+	requested: AtomicBool,
+
+	/// Written to once shutdown is requested, waking the event loop.
+	#[cfg(unix)]
+	wake: Mutex<UnnamedPipeWriter>,
+}
+
+/// The event loop's side of a [`ShutdownHandle`].
+pub(super) struct ShutdownListener {
+	signal: Arc<ShutdownSignal>,
+
+	#[cfg(unix)]
+	wake: UnnamedPipeReader,
+}
+
+#[inline]
+/// Opens the span that a request sent to the peer process is made in, which records whether there was a response and how long it took once it arrives.
+#[cfg(feature = "tracing")]
+fn request_span(request_id: Uuid, context: Option<&str>) -> tracing::Span {
+	tracing::debug_span!(
+		"viaduct_request",
+		%request_id,
+		context,
+		some = tracing::field::Empty,
+		elapsed = tracing::field::Empty
+	)
+}
+
+thread_local! {
+	/// The viaducts whose event loops are running on this thread, identified by the address of their [`ViaductTxInner`].
+	static EVENT_LOOPS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks a viaduct's event loop as running on the current thread until it's dropped, so that requests that only it could receive the response to are refused instead of hanging.
+struct EventLoopGuard(usize);
+impl EventLoopGuard {
+	fn enter<RpcTx, RequestTx, RpcRx, RequestRx>(tx: &ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self
+	where
+		RpcTx: ViaductSerialize,
+		RequestTx: ViaductSerialize,
+		RpcRx: ViaductDeserialize,
+		RequestRx: ViaductDeserialize,
+	{
+		let id = tx.event_loop_id();
+		EVENT_LOOPS.with_borrow_mut(|event_loops| event_loops.push(id));
+		Self(id)
+	}
+}
+impl Drop for EventLoopGuard {
+	fn drop(&mut self) {
+		EVENT_LOOPS.with_borrow_mut(|event_loops| {
+			if let Some(i) = event_loops.iter().rposition(|id| *id == self.0) {
+				event_loops.remove(i);
+			}
+		});
+	}
+}
+
+fn handle_event<RpcTx, RequestTx, RpcRx, RequestRx, EventHandler>(
+	event_handler: &mut EventHandler,
+	event: ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>,
+) where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+	EventHandler: FnMut(ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>),
+{
+	#[cfg(feature = "tracing")]
+	let _span = match &event {
+		ViaductEvent::Request { responder, .. } => {
+			Some(tracing::debug_span!("viaduct_request_received", request_id = %responder.request_id, context = responder.context()).entered())
+		}
+		_ => None,
+	};
+
+	#[cfg(feature = "tracing")]
+	tracing::trace!(
+		packet_type = ?match &event {
+			ViaductEvent::Rpc(_) => PacketType::Rpc,
+			ViaductEvent::Request { .. } => PacketType::Request,
+			ViaductEvent::Handle(_) => PacketType::Handle,
+		},
+		"viaduct event dispatched"
+	);
+
+	event_handler(event);
+}
+
+/// The events waiting to be handled by [`ViaductRx::run_prioritized`].
+struct PriorityQueue<T> {
+	state: Mutex<PriorityQueueState<T>>,
+	condvar: Condvar,
+}
+struct PriorityQueueState<T> {
+	events: BinaryHeap<Prioritized<T>>,
+	next_seq: u64,
+	closed: bool,
+}
+impl<T> Default for PriorityQueue<T> {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			state: Mutex::new(PriorityQueueState {
+				events: BinaryHeap::new(),
+				next_seq: 0,
+				closed: false,
+			}),
+			condvar: Condvar::new(),
+		}
+	}
+}
+impl<T> PriorityQueue<T> {
+	/// Queues an event, returning `false` if the queue has been closed.
+	fn push(&self, priority: Priority, event: T) -> bool {
+		let mut state = self.state.lock();
+		if state.closed {
+			return false;
+		}
+
+		let seq = state.next_seq;
+		state.next_seq += 1;
+		state.events.push(Prioritized { priority, seq, event });
+		self.condvar.notify_one();
+		true
+	}
+
+	/// Takes the highest priority event, waiting for one if the queue is empty, or returns `None` once the queue has been closed and emptied.
+	fn pop(&self) -> Option<T> {
+		let mut state = self.state.lock();
+		loop {
+			if let Some(prioritized) = state.events.pop() {
+				return Some(prioritized.event);
+			}
+			if state.closed {
+				return None;
+			}
+			self.condvar.wait(&mut state);
+		}
+	}
+
+	fn close(&self) {
+		self.state.lock().closed = true;
+		self.condvar.notify_all();
+	}
+}
+
+struct CloseOnDrop<'a, T>(&'a PriorityQueue<T>);
+impl<T> Drop for CloseOnDrop<'_, T> {
+	#[inline]
+	fn drop(&mut self) {
+		self.0.close();
+	}
+}
+
+/// An event in a [`PriorityQueue`], which orders higher priorities first, then earlier arrivals first.
+struct Prioritized<T> {
+	priority: Priority,
+	seq: u64,
+	event: T,
+}
+impl<T> PartialEq for Prioritized<T> {
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == std::cmp::Ordering::Equal
+	}
+}
+impl<T> Eq for Prioritized<T> {}
+impl<T> PartialOrd for Prioritized<T> {
+	#[inline]
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl<T> Ord for Prioritized<T> {
+	#[inline]
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+	}
+}
+
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Drop for ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	fn drop(&mut self) {
+		// The event loop has stopped, so any responders that are still around have most likely been leaked
+		self.tx.0.abandon_responders();
+
+		// ...and no more responses will be received
+		self.tx.0.close(CloseReason::LocalClose);
+	}
+}
+
+/// The type of a packet received over the viaduct.
+///
+/// See [`ViaductRx::peek_packet_type`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PacketType {
+	/// An RPC.
+	Rpc,
+
+	/// A request.
+	Request,
+
+	/// A handle or file descriptor shared by the peer process.
+	Handle,
+
+	/// A response to a request.
+	///
+	/// Responses are routed to their requesters internally, so this is only ever seen by the raw payload hooks ([`ViaductParent::on_raw_recv`](crate::ViaductParent::on_raw_recv) and friends).
+	Response,
+}
+impl PacketType {
+	/// The kind of payload that follows a packet header starting with `packet_type`.
+	fn of_payload(packet_type: u8) -> Self {
+		match packet_type {
+			RPC | WINDOWED_RPC | BATCH => Self::Rpc,
+			REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY => Self::Request,
+			_ => Self::Response,
+		}
+	}
+}
+
+/// Describes a packet received from the peer process, as seen by the frame hook (see [`ViaductParent::on_frame`](crate::ViaductParent::on_frame)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct FrameInfo {
+	/// What kind of packet was received.
+	///
+	/// A batch of RPCs (see [`ViaductTx::rpc_batch`]) is seen as a single [`PacketType::Rpc`] frame.
+	pub packet_type: PacketType,
+
+	/// The length of the packet's payload in bytes, after decompression.
+	///
+	/// This is zero for a response to a request that had no response.
+	pub len: usize,
+
+	/// The id of the request, for requests and responses.
+	///
+	/// A response has the same id as the request it responds to, so this can be used to pair them up.
+	pub request_id: Option<u128>,
+}
+
+/// How urgently a request should be handled by the peer process.
+///
+/// See [`ViaductTx::request_priority`] and [`ViaductRx::run_prioritized`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+	/// Handled after everything else.
+	Low,
+
+	/// The priority of requests sent without one, and of RPCs.
+	#[default]
+	Normal,
+
+	/// Handled before anything of normal priority.
+	High,
+
+	/// Handled before everything else.
+	Urgent,
+}
+impl Priority {
+	#[inline]
+	fn from_u8(priority: u8) -> Option<Self> {
+		match priority {
+			0 => Some(Self::Low),
+			1 => Some(Self::Normal),
+			2 => Some(Self::High),
+			3 => Some(Self::Urgent),
+			_ => None,
+		}
+	}
+}
+
+/// A frame whose packet type has been read, but whose contents haven't been received yet.
+pub(super) enum Frame {
+	/// The rest of the packet is still waiting to be read from the pipe.
+	Direct(u8),
+
+	/// The packet was fragmented and has been reassembled in full.
+	Reassembled(Vec<u8>),
+}
+impl Frame {
+	#[inline]
+	fn packet_type(&self) -> Option<u8> {
+		match self {
+			Self::Direct(packet_type) => Some(*packet_type & !COMPRESSED),
+			Self::Reassembled(packet) => packet.first().map(|packet_type| packet_type & !COMPRESSED),
+		}
+	}
+}
+
+/// The RPCs of a received batch that haven't been handed to the event loop yet.
+///
+/// A batch's payload is a sequence of RPCs, each preceded by its length.
+#[derive(Default)]
+pub(super) struct RpcBatch {
+	bytes: Vec<u8>,
+	pos: usize,
+}
+impl RpcBatch {
+	#[inline]
+	fn is_empty(&self) -> bool {
+		self.pos == self.bytes.len()
+	}
+
+	/// Starts handing out the RPCs of a newly received batch, swapping its payload out of `buf`.
+	#[inline]
+	fn replace(&mut self, buf: &mut Vec<u8>) {
+		std::mem::swap(&mut self.bytes, buf);
+		self.pos = 0;
+	}
+
+	/// Returns the next RPC in the batch, if there are any left.
+	fn next(&mut self) -> Result<Option<&[u8]>, std::io::Error> {
+		if self.is_empty() {
+			return Ok(None);
+		}
+
+		let rest = &self.bytes[self.pos..];
+		let rpc = rest
+			.get(..size_of::<u64>())
+			.and_then(|len| usize::try_from(u64::from_ne_bytes(len.try_into().unwrap())).ok())
+			.and_then(|len| rest.get(size_of::<u64>()..)?.get(..len));
+
+		match rpc {
+			Some(rpc) => {
+				self.pos += size_of::<u64>() + rpc.len();
+				Ok(Some(rpc))
+			}
+
+			None => {
+				// Don't trip over the rest of it again
+				self.pos = self.bytes.len();
+				Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Received a malformed RPC batch"))
+			}
+		}
+	}
+}
+
+/// Packets that are in the process of being reassembled from fragments.
+pub(super) struct Reassembly {
+	packets: HashMap<u64, Vec<u8>>,
+	bytes: usize,
+	max_bytes: usize,
+	max_packets: usize,
+	pool: Option<Arc<dyn BufferPool>>,
+}
+impl Reassembly {
+	#[inline]
+	pub(super) fn new(options: &ViaductOptions) -> Self {
+		Self {
+			packets: HashMap::new(),
+			bytes: 0,
+			max_bytes: options.max_reassembly_bytes,
+			max_packets: options.max_concurrent_fragments,
+			pool: options.buffer_pool.clone(),
+		}
+	}
+
+	/// Returns a reassembled packet's buffer to the pool, if there is one.
+	#[inline]
+	fn release(&self, packet: Vec<u8>) {
+		if let Some(pool) = &self.pool {
+			pool.release(packet);
+		}
+	}
+
+	/// Receives a fragment, returning the reassembled packet if it was the last one.
+	fn recv(&mut self, rx: &mut impl Read, end: bool) -> Result<Option<Vec<u8>>, std::io::Error> {
+		let (fragment_id, len) = {
+			let mut fragment_id = [0u8; size_of::<u64>()];
+			let mut len = [0u8; size_of::<u64>()];
+			rx.read_exact(&mut fragment_id)?;
+			rx.read_exact(&mut len)?;
+			(u64::from_ne_bytes(fragment_id), u64::from_ne_bytes(len))
+		};
+
+		let packets = self.packets.len() + usize::from(!self.packets.contains_key(&fragment_id));
+		let bytes = usize::try_from(len)
+			.ok()
+			.and_then(|len| self.bytes.checked_add(len))
+			.unwrap_or(usize::MAX);
+		if bytes > self.max_bytes || packets > self.max_packets {
+			return Err(ViaductError::ReassemblyLimit {
+				bytes,
+				max_bytes: self.max_bytes,
+				fragments: packets,
+				max_fragments: self.max_packets,
+			}
+			.into());
+		}
+
+		let pool = &self.pool;
+		let packet = self
+			.packets
+			.entry(fragment_id)
+			.or_insert_with(|| pool.as_ref().map(|pool| pool.acquire()).unwrap_or_default());
+		let start = packet.len();
+		packet.resize(start + len as usize, 0);
+		rx.read_exact(&mut packet[start..])?;
+		self.bytes = bytes;
+
+		if end {
+			let packet = self.packets.remove(&fragment_id).unwrap();
+			self.bytes -= packet.len();
+			Ok(Some(packet))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
+/// Where the reader hands a response over to the thread waiting for it.
+#[derive(Default)]
+pub(super) struct ResponseWaiter {
+	response: Mutex<Option<Result<Option<Vec<u8>>, std::io::Error>>>,
+	condvar: Condvar,
+}
+impl ResponseWaiter {
+	#[inline]
+	fn deliver(&self, response: Option<Vec<u8>>) {
+		*self.response.lock() = Some(Ok(response));
+		self.condvar.notify_one();
+	}
+
+	/// Wakes the waiting request with an error instead of a response.
+	#[inline]
+	fn abandon(&self, err: std::io::Error) {
+		*self.response.lock() = Some(Err(err));
+		self.condvar.notify_one();
+	}
+
+	/// Waits for the response to be delivered, returning `None` if `timeout_at` passes first.
+	fn wait(&self, timeout_at: Option<Instant>) -> Option<Result<Option<Vec<u8>>, std::io::Error>> {
+		let mut response = self.response.lock();
+		while response.is_none() {
+			match timeout_at {
+				Some(timeout_at) => {
+					if self.condvar.wait_until(&mut response, timeout_at).timed_out() {
+						break;
+					}
+				}
+				None => self.condvar.wait(&mut response),
+			}
+		}
+		response.take()
+	}
+}
+
+/// Where the reader hands a response over to the task awaiting it.
+///
+/// See [`ViaductTx::request_async`].
+#[cfg(feature = "tokio")]
+#[derive(Default)]
+pub(super) struct AsyncResponse {
+	response: Mutex<Option<Result<Option<Vec<u8>>, std::io::Error>>>,
+
+	/// Only touched while holding `response`, so a response can't slip in between a poll and the waker being registered.
+	waker: Mutex<Option<std::task::Waker>>,
+}
+#[cfg(feature = "tokio")]
+impl AsyncResponse {
+	#[inline]
+	fn deliver(&self, response: Result<Option<Vec<u8>>, std::io::Error>) {
+		let mut slot = self.response.lock();
+		*slot = Some(response);
+		let waker = self.waker.lock().take();
+		drop(slot);
+
+		if let Some(waker) = waker {
+			waker.wake();
+		}
+	}
+
+	fn poll(&self, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<Option<Vec<u8>>, std::io::Error>> {
+		let mut response = self.response.lock();
+		match response.take() {
+			Some(response) => std::task::Poll::Ready(response),
+			None => {
+				*self.waker.lock() = Some(cx.waker().clone());
+				std::task::Poll::Pending
+			}
+		}
+	}
+}
+
+/// Where the reader hands the chunks of a streamed response over to the thread iterating over them.
+///
+/// See [`ViaductTx::request_stream`].
+#[derive(Default)]
+pub(super) struct StreamReceiver {
+	state: Mutex<StreamState>,
+	condvar: Condvar,
+}
+#[derive(Default)]
+struct StreamState {
+	chunks: VecDeque<Vec<u8>>,
+
+	/// How the stream ended, once it has. The error is taken by the first call to [`StreamReceiver::next`] that finds it.
+	end: Option<Result<(), std::io::Error>>,
+}
+impl StreamReceiver {
+	#[inline]
+	fn push(&self, chunk: Vec<u8>) {
+		self.state.lock().chunks.push_back(chunk);
+		self.condvar.notify_one();
+	}
+
+	/// Ends the stream, with a final chunk if the peer sent a single response instead of a stream.
+	#[inline]
+	fn finish(&self, last: Option<Vec<u8>>, end: Result<(), std::io::Error>) {
+		let mut state = self.state.lock();
+		state.chunks.extend(last);
+		state.end = Some(end);
+		drop(state);
+		self.condvar.notify_one();
+	}
+
+	/// Waits for the next chunk, returning `None` once the stream has ended and every chunk has been taken.
+	fn next(&self) -> Option<Result<Vec<u8>, std::io::Error>> {
+		let mut state = self.state.lock();
+		loop {
+			if let Some(chunk) = state.chunks.pop_front() {
+				return Some(Ok(chunk));
+			}
+			match &mut state.end {
+				Some(end) => return std::mem::replace(end, Ok(())).err().map(Err),
+				None => self.condvar.wait(&mut state),
+			}
+		}
+	}
+}
+
+/// Whoever is waiting for the response to a request.
+pub(super) enum PendingResponse {
+	/// A thread blocked waiting for the response.
+	Waiter(Arc<ResponseWaiter>),
+
+	/// A task awaiting the response.
+	///
+	/// See [`ViaductTx::request_async`].
+	#[cfg(feature = "tokio")]
+	Async(Arc<AsyncResponse>),
+
+	/// A proxied request, whose response is forwarded to the original requester.
+	///
+	/// See [`ViaductRequestResponder::proxy_to`].
+	Forward(Box<dyn FnOnce(Option<Vec<u8>>) + Send + 'static>),
+
+	/// A thread iterating over a streamed response.
+	///
+	/// See [`ViaductTx::request_stream`].
+	Stream(Arc<StreamReceiver>),
+}
+impl PendingResponse {
+	#[inline]
+	fn deliver(self, response: Option<Vec<u8>>) {
+		match self {
+			Self::Waiter(waiter) => waiter.deliver(response),
+			#[cfg(feature = "tokio")]
+			Self::Async(waiter) => waiter.deliver(Ok(response)),
+			Self::Forward(forward) => forward(response),
+			Self::Stream(stream) => stream.finish(response, Ok(())),
+		}
+	}
+
+	/// Gives up on the response. A forwarded request's requester receives `None`.
+	#[inline]
+	fn abandon(self, err: std::io::Error) {
+		match self {
+			Self::Waiter(waiter) => waiter.abandon(err),
+			#[cfg(feature = "tokio")]
+			Self::Async(waiter) => waiter.deliver(Err(err)),
+			Self::Forward(_) => {}
+			Self::Stream(stream) => stream.finish(None, Err(err)),
+		}
+	}
+}
+
+/// Removes a request's pending entry when dropped, so that no path out of a request (errors, timeouts, panics) can leave it behind.
+struct PendingGuard<'a> {
+	pending: &'a Mutex<HashMap<Uuid, PendingResponse>>,
+	request_id: Uuid,
+}
+impl Drop for PendingGuard<'_> {
+	#[inline]
+	fn drop(&mut self) {
+		self.pending.lock().remove(&self.request_id);
+	}
+}
+
+/// Bounds how many request responders can be alive at once.
+pub(super) struct ResponderLimit {
+	max: usize,
+	outstanding: Mutex<usize>,
+	condvar: Condvar,
+}
+impl ResponderLimit {
+	#[inline]
+	pub(super) fn new(max: usize) -> Self {
+		Self {
+			max,
+			outstanding: Mutex::new(0),
+			condvar: Condvar::new(),
+		}
+	}
+
+	/// Waits until there's room for another responder, and takes it.
+	fn acquire(&self) {
+		let mut outstanding = self.outstanding.lock();
+		while *outstanding >= self.max {
+			self.condvar.wait(&mut outstanding);
+		}
+		*outstanding += 1;
+	}
+
+	#[inline]
+	fn release(&self) {
+		*self.outstanding.lock() -= 1;
+		self.condvar.notify_one();
+	}
+}
+
+/// Flow control for [`ViaductTx::rpc_windowed`].
+///
+/// Every so often, a windowed RPC asks the peer to acknowledge it, which the peer does with the total number of windowed RPCs it has received so far. The sender stops once the window is full of unacknowledged RPCs.
+pub(super) struct RpcWindow {
+	size: u64,
+
+	/// The number of windowed RPCs sent, and how many of them the peer has acknowledged.
+	sent: Mutex<(u64, u64)>,
+	condvar: Condvar,
+
+	/// Whether the event loop has stopped, so no more acknowledgements will arrive.
+	closed: AtomicBool,
+
+	/// The number of windowed RPCs received from the peer.
+	received: AtomicU64,
+}
+impl RpcWindow {
+	#[inline]
+	pub(super) fn new(size: usize) -> Self {
+		Self {
+			size: size as u64,
+			sent: Mutex::new((0, 0)),
+			condvar: Condvar::new(),
+			closed: AtomicBool::new(false),
+			received: AtomicU64::new(0),
+		}
+	}
+
+	/// Waits until there's room in the window for another RPC, and takes it, returning whether the peer should acknowledge it.
+	///
+	/// Returns `None` if the window is full and the viaduct has been closed, as there would be nothing to wait for.
+	fn acquire(&self) -> Option<bool> {
+		let mut sent = self.sent.lock();
+		while sent.0 - sent.1 >= self.size {
+			if self.closed.load(Ordering::Relaxed) {
+				return None;
+			}
+			self.condvar.wait(&mut sent);
+		}
+		sent.0 += 1;
+
+		// Ask for acknowledgements twice per window, so the sender can carry on while the second half is in flight
+		Some(sent.0.is_multiple_of((self.size / 2).max(1)))
+	}
+
+	/// Wakes up every sender waiting for room in the window, and stops any more from waiting.
+	fn close(&self) {
+		// Set while holding the lock, so that a sender can't miss it between checking and waiting
+		let _sent = self.sent.lock();
+		self.closed.store(true, Ordering::Relaxed);
+		self.condvar.notify_all();
+	}
+
+	#[inline]
+	fn cancel(&self) {
+		self.sent.lock().0 -= 1;
+		self.condvar.notify_all();
+	}
+
+	#[inline]
+	fn ack(&self, acked: u64) {
+		let mut sent = self.sent.lock();
+		sent.1 = sent.1.max(acked);
+		self.condvar.notify_all();
+	}
+}
+
+/// Credit-based flow control for RPCs, in both directions.
+///
+/// See [`ViaductParent::rpc_credits`](crate::ViaductParent::rpc_credits).
+pub(super) struct RpcCredits {
+	/// How many more RPC packets the peer will accept, if it limits them.
+	available: Option<AtomicU64>,
+
+	/// How many RPC packets we let the peer send ahead of our event loop, if we limit them.
+	limit: Option<u64>,
+
+	/// The number of RPC packets received since we last granted the peer more credits.
+	received: AtomicU64,
+}
+impl RpcCredits {
+	#[inline]
+	pub(super) fn new(limit: Option<u64>, peer_limit: Option<u64>) -> Self {
+		Self {
+			available: peer_limit.map(AtomicU64::new),
+			limit,
+			received: AtomicU64::new(0),
+		}
+	}
+
+	/// Spends a credit on an RPC packet, returning `false` if there are none left.
+	#[inline]
+	fn spend(&self) -> bool {
+		match &self.available {
+			Some(available) => available
+				.fetch_update(Ordering::AcqRel, Ordering::Acquire, |available| available.checked_sub(1))
+				.is_ok(),
+			None => true,
+		}
+	}
+
+	#[inline]
+	fn grant(&self, granted: u64) {
+		if let Some(available) = &self.available {
+			available.fetch_add(granted, Ordering::AcqRel);
+		}
+	}
+
+	/// Counts an RPC packet received from the peer, returning how many credits to grant it back, if it's time to.
+	fn received(&self) -> Option<u64> {
+		let limit = self.limit?;
+
+		// Grant credits twice per limit, so the peer can carry on while the second half is in flight
+		let received = self.received.fetch_add(1, Ordering::Relaxed) + 1;
+		if received >= (limit / 2).max(1) {
+			self.received.fetch_sub(received, Ordering::Relaxed);
+			Some(received)
+		} else {
+			None
+		}
+	}
+}
+
+#[inline]
+fn lock_until<T>(mutex: &Mutex<T>, timeout_at: Option<Instant>) -> Result<MutexGuard<'_, T>, std::io::Error> {
+	match timeout_at {
+		Some(timeout_at) => mutex
+			.try_lock_until(timeout_at)
+			.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::TimedOut)),
+		None => Ok(mutex.lock()),
+	}
+}
+
+/// The sending side of a viaduct.
+///
+/// This handle can be freely cloned and sent across threads.
+pub struct ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>(pub(super) Arc<ViaductTxInner<RpcTx, RequestTx, RpcRx, RequestRx>>)
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize;
+
+pub(super) struct ViaductTxInner<RpcTx, RequestTx, RpcRx, RequestRx> {
+	pub(super) state: Mutex<ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx>>,
+	pub(super) pending: Mutex<HashMap<Uuid, PendingResponse>>,
+	pub(super) pending_closed: AtomicBool,
+	pub(super) close_reason: Mutex<Option<CloseReason>>,
+	pub(super) timings: TimingRecorder,
+	pub(super) metrics: Arc<MetricsRecorder>,
+	pub(super) peer_capabilities: Capabilities,
+	pub(super) responders: Option<Mutex<HashSet<Uuid>>>,
+	pub(super) responder_limit: Option<ResponderLimit>,
+	pub(super) rpc_window: RpcWindow,
+	pub(super) rpc_credits: Arc<RpcCredits>,
+	pub(super) on_request_complete: Mutex<Option<LatencyHook>>,
+	#[cfg(windows)]
+	pub(super) peer_process: Option<std::os::windows::io::OwnedHandle>,
+	#[cfg(unix)]
+	pub(super) handle_socket: Option<std::os::unix::net::UnixDatagram>,
+	pub(super) child_handle: Option<ChildHandle>,
+	pub(super) _reaper_pipe: Option<ReaperPipe>,
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTxInner<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestTx: ViaductSerialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Stops tracking a responder that is about to respond, returning `false` if it was abandoned and mustn't respond after all.
+	#[inline]
+	fn claim_responder(&self, request_id: &Uuid) -> bool {
+		match &self.responders {
+			Some(responders) => responders.lock().remove(request_id),
+			None => true,
+		}
+	}
+
+	/// Returns whether the responder for `request_id` has been abandoned, and mustn't respond after all.
+	#[inline]
+	fn responder_abandoned(&self, request_id: &Uuid) -> bool {
+		match &self.responders {
+			Some(responders) => !responders.lock().contains(request_id),
+			None => false,
+		}
+	}
+
+	/// Counts an RPC packet received from the peer, granting it more credits if it's time to (see [`ViaductParent::rpc_credits`](crate::ViaductParent::rpc_credits)).
+	fn grant_rpc_credits(&self) -> Result<(), std::io::Error> {
+		if let Some(granted) = self.rpc_credits.received() {
+			let mut header = [CREDIT; 1 + size_of::<u64>()];
+			header[1..].copy_from_slice(&granted.to_ne_bytes());
+			ViaductTxState::send_packet(&mut self.state.lock(), &header, false, true)?;
+		}
+		Ok(())
+	}
+
+	/// Registers a request that is waiting for a response, unless the event loop that would receive it has stopped.
+	fn insert_pending(&self, request_id: Uuid, response: PendingResponse) -> Result<(), std::io::Error> {
+		let mut pending = self.pending.lock();
+		if self.pending_closed.load(Ordering::Relaxed) {
+			return Err(self.close_error());
+		}
+		pending.insert(request_id, response);
+		Ok(())
+	}
+
+	/// Wakes every request that is waiting for a response with an error from `err`.
+	fn fail_pending(&self, mut err: impl FnMut() -> std::io::Error) {
+		let pending = std::mem::take(&mut *self.pending.lock());
+		for pending in pending.into_values() {
+			pending.abandon(err());
+		}
+	}
+
+	/// Records why the viaduct was closed, unless it was already closed for another reason.
+	///
+	/// Unless the viaduct was only shut down, this also fails every request that is waiting for a response, and any sent from now on, because the event loop has stopped.
+	fn close(&self, reason: CloseReason) {
+		let shutdown = matches!(reason, CloseReason::Shutdown);
+		{
+			let mut close_reason = self.close_reason.lock();
+			match &*close_reason {
+				None => *close_reason = Some(reason),
+				Some(CloseReason::Shutdown) if !shutdown => *close_reason = Some(reason),
+				Some(_) => {}
+			}
+		}
+
+		// Even if the viaduct was only shut down, the event loop won't receive any more acknowledgements
+		self.rpc_window.close();
+
+		if shutdown {
+			return;
+		}
+
+		// Requests check this while holding the lock, so each one is either refused, or registered in time to be failed
+		self.pending_closed.store(true, Ordering::Relaxed);
+		self.fail_pending(|| self.close_error());
+	}
+
+	/// Returns the error that requests fail with because the viaduct was closed.
+	fn close_error(&self) -> std::io::Error {
+		match &*self.close_reason.lock() {
+			Some(reason) => reason.to_io(),
+			None => CloseReason::LocalClose.to_io(),
+		}
+	}
+
+	/// Sends a "no response" packet on behalf of every responder that is still outstanding.
+	fn abandon_responders(&self) {
+		let Some(responders) = &self.responders else { return };
+
+		let abandoned = std::mem::take(&mut *responders.lock());
+		if abandoned.is_empty() {
+			return;
+		}
+
+		#[cfg(feature = "tracing")]
+		tracing::warn!(count = abandoned.len(), "viaduct request responders leaked without responding");
+
+		let mut state = self.state.lock();
+		for request_id in abandoned {
+			let mut header = [NONE_RESPONSE; 1 + 16];
+			header[1..].copy_from_slice(request_id.as_bytes());
+			if ViaductTxState::send_packet(&mut state, &header, false, true).is_err() {
+				// The peer is gone, so nobody is waiting for the rest
+				break;
+			}
+		}
+	}
+}
+
+/// Where a viaduct reads its packets from.
+pub(super) enum PipeReader {
+	Pipe(UnnamedPipeReader),
+
+	/// An arbitrary byte source, swapped in by [`ViaductRx::run_from_reader`].
+	Reader(Box<dyn Read + Send>),
+}
+impl Read for PipeReader {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		match self {
+			#[cfg(unix)]
+			PipeReader::Pipe(pipe) => loop {
+				// In non-blocking mode, a packet that has started arriving is still read in full
+				match pipe.read(buf) {
+					Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => os::wait_readable(pipe)?,
+					result => return result,
+				}
+			},
+
+			#[cfg(windows)]
+			PipeReader::Pipe(pipe) => pipe.read(buf),
+
+			PipeReader::Reader(reader) => reader.read(buf),
+		}
+	}
+}
+
+/// Where a viaduct writes its packets to.
+pub(super) enum PipeSink {
+	Pipe(UnnamedPipeWriter),
+
+	/// An arbitrary byte sink, such as the in-memory pipe used by [`test_util::in_memory`](crate::test_util::in_memory).
+	#[cfg_attr(not(feature = "test-util"), allow(dead_code))]
+	Writer(Box<dyn Write + Send>),
+}
+impl Write for PipeSink {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		match self {
+			PipeSink::Pipe(pipe) => pipe.write(buf),
+			PipeSink::Writer(writer) => writer.write(buf),
+		}
+	}
+
+	#[inline]
+	fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+		match self {
+			#[cfg(unix)]
+			PipeSink::Pipe(pipe) => os::write_vectored(pipe, bufs),
+			#[cfg(windows)]
+			PipeSink::Pipe(pipe) => pipe.write_vectored(bufs),
+			PipeSink::Writer(writer) => writer.write_vectored(bufs),
+		}
+	}
+
+	#[inline]
+	fn flush(&mut self) -> std::io::Result<()> {
+		match self {
+			// See PipeWriter
+			PipeSink::Pipe(_) => Ok(()),
+			PipeSink::Writer(writer) => writer.flush(),
+		}
+	}
+}
+
+/// Writes straight to the pipe.
+///
+/// Flushing is a no-op, as there's nothing below us to flush - and flushing a pipe fails on some platforms, or blocks until the peer has read everything on others.
+///
+/// The pipe is closed by [`ViaductTx::shutdown_send`], after which writing fails.
+pub(super) struct PipeWriter(Option<PipeSink>);
+impl PipeWriter {
+	#[inline]
+	fn sink(&self) -> Result<&PipeSink, std::io::Error> {
+		self.0
+			.as_ref()
+			.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "The sending side of the viaduct was shut down"))
+	}
+}
+impl Write for PipeWriter {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		match &mut self.0 {
+			Some(sink) => sink.write(buf),
+			None => Err(std::io::Error::new(
+				std::io::ErrorKind::BrokenPipe,
+				"The sending side of the viaduct was shut down",
+			)),
+		}
+	}
+
+	#[inline]
+	fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+		match &mut self.0 {
+			Some(sink) => sink.write_vectored(bufs),
+			None => Err(std::io::Error::new(
+				std::io::ErrorKind::BrokenPipe,
+				"The sending side of the viaduct was shut down",
+			)),
+		}
+	}
+
+	#[inline]
+	fn flush(&mut self) -> std::io::Result<()> {
+		match &mut self.0 {
+			Some(sink) => sink.flush(),
+			None => Ok(()),
+		}
+	}
+}
+
+pub(super) struct ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx> {
+	pub(super) tx: BufWriter<PipeWriter>,
+	buf: Vec<u8>,
+	no_delay: bool,
+	max_fragment_size: Option<usize>,
+	max_send_size: Option<usize>,
+	next_fragment_id: u64,
+	resync: bool,
+	timestamps: bool,
+	on_raw_send: Option<RawHook>,
+	metrics: Arc<MetricsRecorder>,
+	rpc_credits: Arc<RpcCredits>,
+	#[cfg(feature = "compression")]
+	compression_threshold: Option<usize>,
+
+	/// Whether a file descriptor was shared without the packet that goes with it, so the peer would pair the next packet with the wrong one.
+	#[cfg(unix)]
+	handles_desynced: bool,
+
+	_phantom: PhantomData<(RpcTx, RequestTx, RpcRx, RequestRx)>,
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTxState<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestTx: ViaductSerialize,
+	RequestRx: ViaductDeserialize,
+{
+	#[inline]
+	pub(super) fn new(tx: PipeSink, options: &mut ViaductOptions, metrics: Arc<MetricsRecorder>, rpc_credits: Arc<RpcCredits>) -> Self {
+		Self {
+			buf: Vec::new(),
+			tx: BufWriter::new(PipeWriter(Some(tx))),
+			no_delay: !options.buffered_writes,
+			max_fragment_size: options.max_fragment_size,
+			max_send_size: options.max_send_size,
+			next_fragment_id: 0,
+			resync: options.resync_markers,
+			timestamps: options.timestamps,
+			on_raw_send: options.on_raw_send.take(),
+			metrics,
+			rpc_credits,
+			#[cfg(feature = "compression")]
+			compression_threshold: options.compression_threshold,
+			#[cfg(unix)]
+			handles_desynced: false,
+			_phantom: Default::default(),
+		}
+	}
+
+	/// Compresses `buf` in place if it's at least as large as the compression threshold, returning whether it was compressed.
+	///
+	/// Payloads that don't get any smaller are left alone.
+	#[cfg(feature = "compression")]
+	fn compress(&mut self) -> bool {
+		match self.compression_threshold {
+			Some(threshold) if self.buf.len() >= threshold => {
+				let compressed = lz4_flex::compress_prepend_size(&self.buf);
+				if compressed.len() >= self.buf.len() {
+					return false;
+				}
+				self.buf.clear();
+				self.buf.extend_from_slice(&compressed);
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Writes a packet made up of `header`, followed by the length-prefixed contents of `buf` if `payload` is set, down the wire.
+	///
+	/// If the packet is larger than the maximum fragment size, it is split into fragments, giving other threads a chance to send their own packets in between each one.
+	///
+	/// The packet is flushed immediately if `flush` is set (because the peer is waiting for it) or no-delay mode is enabled; otherwise it may be coalesced with later packets.
+	#[inline]
+	fn send_packet(state: &mut MutexGuard<'_, Self>, header: &[u8], payload: bool, flush: bool) -> Result<(), std::io::Error> {
+		Self::send_packet_with(state, header, payload, flush, true)
+	}
+
+	/// Like [`ViaductTxState::send_packet`], but the payload is only compressed (if it's above the compression threshold) if `compress` is set.
+	fn send_packet_with(state: &mut MutexGuard<'_, Self>, header: &[u8], payload: bool, flush: bool, compress: bool) -> Result<(), std::io::Error> {
+		let flush = flush || state.no_delay;
+
+		if let (true, Some(limit)) = (payload, state.max_send_size) {
+			if state.buf.len() > limit {
+				// Nothing has been written yet, so the stream is still intact
+				return Err(ViaductError::MessageTooLarge {
+					size: state.buf.len(),
+					limit,
+				}
+				.into());
+			}
+		}
+
+		if matches!(header[0], RPC | WINDOWED_RPC | BATCH) && !state.rpc_credits.spend() {
+			// Nothing has been written yet, so the RPC can be sent again once the peer has caught up
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::WouldBlock,
+				"The peer process can't accept any more RPCs until its event loop catches up",
+			));
+		}
+
+		if payload {
+			let ViaductTxState { buf, on_raw_send, .. } = &mut **state;
+			if let Some(on_raw_send) = on_raw_send {
+				on_raw_send(PacketType::of_payload(header[0]), buf);
+			}
+		}
+
+		#[cfg(feature = "compression")]
+		let compressed = payload && compress && state.compress();
+		#[cfg(not(feature = "compression"))]
+		let compressed = {
+			let _ = compress;
+			false
+		};
+
+		let flagged_header;
+		let header = if compressed {
+			flagged_header = [&[header[0] | COMPRESSED], &header[1..]].concat();
+			&flagged_header[..]
+		} else {
+			header
+		};
+
+		let len = header.len() + if payload { size_of::<u64>() + state.buf.len() } else { 0 };
+
+		let max_fragment_size = match state.max_fragment_size {
+			Some(max_fragment_size) if len > max_fragment_size => max_fragment_size,
+
+			_ => {
+				let ViaductTxState {
+					tx, buf, resync, timestamps, ..
+				} = &mut **state;
+				let timestamp = u64::to_ne_bytes(if *timestamps { Timestamp::now().as_nanos() } else { 0 });
+				let payload_len = u64::to_ne_bytes(buf.len() as _);
+				write_all_vectored(
+					tx,
+					&mut [
+						IoSlice::new(if *resync { &RESYNC_MARKER } else { &[] }),
+						IoSlice::new(if *timestamps { &timestamp } else { &[] }),
+						IoSlice::new(header),
+						IoSlice::new(if payload { &payload_len } else { &[] }),
+						IoSlice::new(if payload { buf } else { &[] }),
+					],
+				)?;
+				if flush {
+					tx.flush()?;
+				}
+				state.record_sent(header[0], payload);
+				return Ok(());
+			}
+		};
+
+		// Other threads will be using the buffer while we're sending fragments, so take a copy of the whole packet
+		let mut packet = Vec::with_capacity(len);
+		packet.extend_from_slice(header);
+		if payload {
+			packet.extend_from_slice(&u64::to_ne_bytes(state.buf.len() as _));
+			packet.extend_from_slice(&state.buf);
+		}
+
+		let fragment_id = state.next_fragment_id;
+		state.next_fragment_id = fragment_id.wrapping_add(1);
+
+		let mut fragments = packet.chunks(max_fragment_size).peekable();
+		while let Some(fragment) = fragments.next() {
+			let last = fragments.peek().is_none();
+
+			let ViaductTxState { tx, resync, timestamps, .. } = &mut **state;
+			let timestamp = u64::to_ne_bytes(if *timestamps { Timestamp::now().as_nanos() } else { 0 });
+			write_all_vectored(
+				tx,
+				&mut [
+					IoSlice::new(if *resync { &RESYNC_MARKER } else { &[] }),
+					IoSlice::new(if *timestamps { &timestamp } else { &[] }),
+					IoSlice::new(&[if last { FRAGMENT_END } else { FRAGMENT }]),
+					IoSlice::new(&u64::to_ne_bytes(fragment_id)),
+					IoSlice::new(&u64::to_ne_bytes(fragment.len() as _)),
+					IoSlice::new(fragment),
+				],
+			)?;
+			if flush {
+				tx.flush()?;
+			}
+
+			if !last {
+				// Let any other waiting threads send their packets
+				MutexGuard::bump(state);
+			}
+		}
+
+		state.record_sent(header[0], payload);
+		Ok(())
+	}
+
+	/// Counts a packet that has been sent towards the viaduct's metrics.
+	fn record_sent(&self, packet_type: u8, payload: bool) {
+		match packet_type & !COMPRESSED {
+			RPC | WINDOWED_RPC => self.metrics.record_rpcs_sent(1),
+			REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY => self.metrics.record_request_sent(),
+			_ => {}
+		}
+		if payload {
+			self.metrics.record_written(self.buf.len());
+		}
+	}
+}
+
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestTx: ViaductSerialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Returns the number of requests from the peer process that haven't been responded to yet, or `None` if responder tracking isn't enabled.
+	///
+	/// See [`ViaductParent::track_responders`](crate::ViaductParent::track_responders).
+	#[inline]
+	pub fn outstanding_responders(&self) -> Option<usize> {
+		self.0.responders.as_ref().map(|responders| responders.lock().len())
+	}
+
+	/// Returns whether `capability` can be used on this viaduct, i.e. both this process and the peer process advertised support for it during the handshake.
+	///
+	/// This allows optional features to be used only when the peer supports them. To refuse to connect to a peer that doesn't support a capability, use [`ViaductParent::require_capability`](crate::ViaductParent::require_capability) instead.
+	#[inline]
+	pub fn peer_supports(&self, capability: Capability) -> bool {
+		Capabilities::LOCAL.contains(capability) && self.0.peer_capabilities.contains(capability)
+	}
+
+	/// Sets whether RPCs are flushed down the pipe as soon as they are sent (the default), analogous to `TCP_NODELAY`.
+	///
+	/// This can also be set up front with [`ViaductParent::buffered_writes`](crate::ViaductParent::buffered_writes).
+	///
+	/// When disabled, RPCs may be coalesced into fewer, larger writes for throughput, and are only guaranteed to reach the peer once [`ViaductTx::flush`] is called, the write buffer fills up, or a request or response is sent. Requests and responses are always flushed immediately, as the other side is waiting for them.
+	pub fn set_no_delay(&self, no_delay: bool) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+		state.no_delay = no_delay;
+		if no_delay {
+			state.tx.flush()?;
+		}
+		Ok(())
+	}
+
+	/// Flushes any RPCs that have been coalesced while no-delay mode is disabled down the pipe.
+	///
+	/// See [`ViaductTx::set_no_delay`].
+	pub fn flush(&self) -> Result<(), std::io::Error> {
+		self.0.state.lock().tx.flush()
+	}
+
+	/// Closes the sending side of the viaduct, like `shutdown(SHUT_WR)` on a socket, after flushing anything that has been coalesced.
+	///
+	/// The peer's event loop will see the end of the stream once it has received everything sent before this, while this process can carry on receiving whatever the peer still has to send.
+	///
+	/// Nothing can be sent afterwards, from any clone of this [`ViaductTx`] - sending RPCs or new requests fails with a [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) error, as does responding to the peer's requests. Responses to requests that were sent before shutting down are still received.
+	pub fn shutdown_send(&self) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+		state.tx.flush()?;
+		drop(state.tx.get_mut().0.take());
+		drop(state);
+
+		self.0.close(CloseReason::Shutdown);
+		Ok(())
+	}
+
+	/// Tells the peer that nothing more is coming and closes the sending side of the viaduct, like [`ViaductTx::shutdown_send`].
+	///
+	/// Once the peer has received everything sent before this, its event loop returns `Ok(())` rather than a [`ViaductError::PeerGone`] error, and its close reason becomes [`CloseReason::PeerClosed`]. If the peer doesn't support [`Capability::GracefulClose`], the sending side is just shut down, so the peer sees the end of the stream instead.
+	///
+	/// As with [`ViaductTx::shutdown_send`], this closes the viaduct for every clone of this [`ViaductTx`]: sending RPCs or new requests from any of them fails with a [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) error afterwards.
+	pub fn close(self) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+		if self.peer_supports(Capability::GracefulClose) {
+			ViaductTxState::send_packet(&mut state, &[GOODBYE], false, true)?;
+		} else {
+			state.tx.flush()?;
+		}
+		drop(state.tx.get_mut().0.take());
+		drop(state);
+
+		self.0.close(CloseReason::Shutdown);
+		Ok(())
+	}
+
+	/// Closes the sending side of the viaduct without flushing it, for when the peer is gone or no longer wanted, and fails every request still waiting for a response.
+	pub(super) fn invalidate(&self, reason: CloseReason) {
+		drop(self.0.state.lock().tx.get_mut().0.take());
+		self.0.close(reason);
+	}
+
+	/// Abandons every request that is still waiting for a response, from any clone of this [`ViaductTx`].
+	///
+	/// Each abandoned request returns a [`ConnectionAborted`](std::io::ErrorKind::ConnectionAborted) error (and the requester of each abandoned [proxied](ViaductRequestResponder::proxy_to) request receives `None`), and any response the peer sends for it later is discarded. This is an escape hatch for getting a viaduct back into a clean state after something has gone wrong, such as a peer that has stopped answering; requests sent after the reset are unaffected.
+	pub fn reset(&self) {
+		self.fail_pending(|| std::io::Error::new(std::io::ErrorKind::ConnectionAborted, "The request was abandoned by ViaductTx::reset"));
+	}
+
+	/// Wakes every request that is waiting for a response, from any clone of this [`ViaductTx`], with an error made by `err`.
+	///
+	/// This is for tearing down a viaduct without leaving threads blocked in [`ViaductTx::request`] behind, for example with a [`ConnectionReset`](std::io::ErrorKind::ConnectionReset) error. The requester of each [proxied](ViaductRequestResponder::proxy_to) request receives `None` instead, and any response the peer sends later is discarded. Requests sent afterwards are unaffected; see also [`ViaductTx::reset`].
+	///
+	/// This happens automatically once the event loop stops, as no more responses can be received: waiting requests fail with an error that matches the viaduct's [close reason](ViaductTx::close_reason) - a [`ViaductError::PeerGone`] error when the peer closes its side of the viaduct, the error that stopped the event loop, or a [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) error when the [`ViaductRx`] is dropped for any other reason - and so does every request sent after that.
+	pub fn fail_pending(&self, err: impl FnMut() -> std::io::Error) {
+		self.0.fail_pending(err);
+	}
+
+	/// Returns why the viaduct was closed, or `None` if it is still open.
+	///
+	/// This is shared by both halves of the viaduct, so it is the same reason that the event loop stopped for and that requests fail with once it has: the peer closing its side of the viaduct, the [`ViaductRx`] being dropped, or an error that stopped the event loop. A viaduct whose sending side was [shut down](ViaductTx::shutdown_send) reports [`CloseReason::Shutdown`] until its event loop stops.
+	#[inline]
+	pub fn close_reason(&self) -> Option<CloseReason> {
+		self.0.close_reason.lock().clone()
+	}
+
+	/// Returns a handle to the child process this viaduct was built with by [`ViaductParent::build`](crate::ViaductParent::build), or `None` in the child process.
+	///
+	/// Unlike the [`Child`](std::process::Child), the handle can be cloned and kept anywhere, so the child process can be killed from wherever it's needed.
+	#[inline]
+	pub fn child_handle(&self) -> Option<ChildHandle> {
+		self.0.child_handle.clone()
+	}
+
+	/// Switches on a capability for the rest of the session, once both sides have agreed to it.
+	///
+	/// An upgrade request is exchanged with the peer process, after which the framing of every packet sent in either direction changes. This blocks until the peer has acknowledged the upgrade, so the peer's event loop must be running.
+	///
+	/// Currently only [`Capability::ResyncMarkers`] can be upgraded. Other capabilities are always in use when both sides support them, or are configured when the viaduct is built (such as [compression](crate::ViaductParent::compression_threshold)), so upgrading to them returns an error of kind [`Unsupported`](std::io::ErrorKind::Unsupported).
+	///
+	/// Returns a [`ViaductError::MissingCapability`] error if the peer doesn't support the capability.
+	pub fn negotiate_upgrade(&self, capability: Capability) -> Result<(), std::io::Error> {
+		if capability != Capability::ResyncMarkers {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				format!("{capability:?} can't be switched on mid-session"),
+			));
+		}
+
+		if !self.peer_supports(capability) {
+			return Err(ViaductError::MissingCapability {
+				required: capability,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		self.refuse_from_event_loop()?;
+
+		let request_id = Uuid::new_v4();
+		let waiter = Arc::new(ResponseWaiter::default());
+		self.0.insert_pending(request_id, PendingResponse::Waiter(waiter.clone()))?;
+
+		let _guard = PendingGuard {
+			pending: &self.0.pending,
+			request_id,
+		};
+
+		{
+			let mut state = self.0.state.lock();
+			if state.resync {
+				// Already upgraded
+				return Ok(());
+			}
+
+			let mut header = [UPGRADE; 1 + 16 + 1];
+			header[1..17].copy_from_slice(request_id.as_bytes());
+			header[17] = capability as u8;
+			ViaductTxState::send_packet(&mut state, &header, false, true)?;
+
+			// Everything we send after the upgrade request uses the new framing
+			state.resync = true;
+		}
+
+		waiter.wait(None).unwrap()?;
+		Ok(())
+	}
+
+	/// Shares a handle (such as a file, event or file mapping) with the peer process, which receives it as a [`ViaductEvent::Handle`].
+	///
+	/// The handle is duplicated with `DuplicateHandle`, so `handle` remains open in this process and should still be closed by you.
+	///
+	/// Requires the peer to support [`Capability::HandlePassing`], otherwise a [`ViaductError::MissingCapability`] error is returned. Only available on Windows.
+	#[cfg(windows)]
+	pub fn send_handle(&self, handle: std::os::windows::io::RawHandle) -> Result<(), std::io::Error> {
+		if !self.peer_supports(Capability::HandlePassing) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::HandlePassing,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		let handle = crate::handle::share(self.0.peer_process.as_ref(), handle)?;
+
+		let mut state = self.0.state.lock();
+		let mut header = [HANDLE; 1 + size_of::<u64>()];
+		header[1..].copy_from_slice(&handle.to_ne_bytes());
+		ViaductTxState::send_packet(&mut state, &header, false, true)
+	}
+
+	/// Shares a file descriptor (such as a file, socket or pipe) with the peer process, which receives it as a [`ViaductEvent::Handle`].
+	///
+	/// The file descriptor is duplicated into the peer process by passing it over a Unix domain socket alongside the viaduct, so `fd` remains open in this process.
+	///
+	/// Requires the peer to support [`Capability::HandlePassing`], otherwise a [`ViaductError::MissingCapability`] error is returned. Only available on Unix.
+	///
+	/// If the file descriptor is passed over the socket but its packet can't be sent, every file descriptor shared afterwards would reach the peer alongside the wrong packet, so this fails with an error of kind [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) from then on.
+	#[cfg(unix)]
+	pub fn send_fd(&self, fd: std::os::unix::io::BorrowedFd<'_>) -> Result<(), std::io::Error> {
+		if !self.peer_supports(Capability::HandlePassing) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::HandlePassing,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		let socket = self.0.handle_socket.as_ref().ok_or_else(|| {
+			std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"This viaduct isn't connected to a peer process that file descriptors can be shared with",
+			)
+		})?;
+
+		// Hold the lock while sending both, so that file descriptors arrive in the same order as their packets
+		let mut state = self.0.state.lock();
+		if state.handles_desynced {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::BrokenPipe,
+				"File descriptors can no longer be shared with the peer process, as sending one failed partway through",
+			));
+		}
+
+		// Don't share the file descriptor if its packet can't be sent anyway
+		state.tx.get_ref().sink()?;
+		crate::handle::share(socket, fd)?;
+
+		if let Err(err) = ViaductTxState::send_packet(&mut state, &[HANDLE; 1 + size_of::<u64>()], false, true) {
+			// The file descriptor is waiting on the socket, where the peer would take it for the next packet instead
+			state.handles_desynced = true;
+			return Err(err);
+		}
+		Ok(())
+	}
+
+	/// Sends an RPC to the peer process.
+	///
+	/// If the peer process limits RPCs with [`ViaductParent::rpc_credits`](crate::ViaductParent::rpc_credits) and has run out of credits, this fails with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock) and the RPC isn't sent. Use [`ViaductTx::rpc_ref`] or [`ViaductTx::try_rpc`] to keep hold of it so it can be sent again.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	#[inline]
+	pub fn rpc(&self, rpc: RpcTx) -> Result<(), std::io::Error> {
+		self.rpc_ref(&rpc)
+	}
+
+	/// Sends an RPC to the peer process, serializing it by reference.
+	///
+	/// This is the same as [`ViaductTx::rpc`], for when the RPC is borrowed, so it doesn't need to be cloned or moved just to be sent.
+	///
+	/// # Panics
+	///
+	/// See [`ViaductTx::rpc`].
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, doctest::*};
+	/// # let tx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().0;
+	/// let rpc = ExampleRpc::Cow;
+	/// for _ in 0..3 {
+	///     tx.rpc_ref(&rpc).unwrap();
+	/// }
+	/// ```
+	pub fn rpc_ref(&self, rpc: &RpcTx) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+
+		let serialize = Stopwatch::start();
+		rpc.to_pipeable({
+			state.buf.clear();
+			&mut state.buf
+		})
+		.expect("Failed to serialize RpcTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet(&mut state, &[RPC], true, false)?;
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		#[cfg(feature = "tracing")]
+		tracing::trace!(len = state.buf.len(), "viaduct rpc sent");
+
+		Ok(())
+	}
+
+	/// Sends an RPC to the peer process, unless another thread is in the middle of sending something.
+	///
+	/// [`ViaductTx::rpc`] waits for other threads to finish sending their RPCs, requests and responses, which isn't acceptable on threads that mustn't block for long, such as UI threads. This returns a [`TrySendError::WouldBlock`] error holding the RPC instead, without writing anything to the pipe, so it can be sent again later.
+	///
+	/// Writing the RPC can still block if the pipe is full. If the peer process limits RPCs with [`ViaductParent::rpc_credits`](crate::ViaductParent::rpc_credits), running out of credits returns a [`TrySendError::WouldBlock`] error too.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	pub fn try_rpc(&self, rpc: RpcTx) -> Result<(), TrySendError<RpcTx>> {
+		let Some(mut state) = self.0.state.try_lock() else {
+			return Err(TrySendError::WouldBlock(rpc));
+		};
+		if self.rpc_credits() == Some(0) {
+			// Credits are only spent while holding the lock, so there's no way they can run out between here and sending
+			return Err(TrySendError::WouldBlock(rpc));
+		}
+
+		let serialize = Stopwatch::start();
+		rpc.to_pipeable({
+			state.buf.clear();
+			&mut state.buf
+		})
+		.expect("Failed to serialize RpcTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet(&mut state, &[RPC], true, false)?;
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		#[cfg(feature = "tracing")]
+		tracing::trace!(len = state.buf.len(), "viaduct rpc sent");
+
+		Ok(())
+	}
+
+	/// Sends several RPCs to the peer process in a single packet.
+	///
+	/// The peer's event loop receives them as separate [`ViaductEvent::Rpc`] events, in order, just as if they had been sent one by one with [`ViaductTx::rpc`]. Sending them together amortizes the framing and write overhead of each RPC, which adds up for chatty workloads that send lots of small RPCs, such as per-frame game state updates. Nothing is sent if `rpcs` is empty.
+	///
+	/// The whole batch is written as one packet, so it counts towards [`ViaductParent::max_send_size`](crate::ViaductParent::max_send_size) and is fragmented and compressed as one. Requires the peer to support [`Capability::RpcBatch`], otherwise a [`ViaductError::MissingCapability`] error is returned.
+	///
+	/// A batch has a packet type of its own, rather than being a run of ordinary RPC packets, so it can't be mistaken for a single RPC. Its payload is each RPC in turn, serialized and preceded by its length in bytes as a native-endian `u64`, which the peer splits back up as it hands them to its event loop.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if any of the RPCs are unable to be deserialized.
+	pub fn rpc_batch(&self, rpcs: impl IntoIterator<Item = RpcTx>) -> Result<(), std::io::Error> {
+		if !self.peer_supports(Capability::RpcBatch) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::RpcBatch,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		let mut state = self.0.state.lock();
+
+		let serialize = Stopwatch::start();
+		let mut batch = Vec::new();
+		let mut count = 0;
+		for rpc in rpcs {
+			count += 1;
+			rpc.to_pipeable({
+				state.buf.clear();
+				&mut state.buf
+			})
+			.expect("Failed to serialize RpcTx");
+			batch.extend_from_slice(&u64::to_ne_bytes(state.buf.len() as _));
+			batch.extend_from_slice(&state.buf);
+		}
+		if batch.is_empty() {
+			return Ok(());
+		}
+		state.buf = batch;
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet(&mut state, &[BATCH], true, false)?;
+		self.0.timings.record_send(serialize, write.elapsed());
+		self.0.metrics.record_rpcs_sent(count);
+
+		Ok(())
+	}
+
+	/// Sends an RPC to the peer process without compressing it, even if it's above the compression threshold.
+	///
+	/// This is for RPCs whose contents won't compress any further, such as images, which would otherwise be compressed for nothing. See [`ViaductParent::compression_threshold`](crate::ViaductParent::compression_threshold).
+	///
+	/// Requires the `compression` feature.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	#[cfg(feature = "compression")]
+	pub fn rpc_uncompressed(&self, rpc: RpcTx) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+
+		let serialize = Stopwatch::start();
+		rpc.to_pipeable({
+			state.buf.clear();
+			&mut state.buf
+		})
+		.expect("Failed to serialize RpcTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet_with(&mut state, &[RPC], true, false, false)?;
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		Ok(())
+	}
+
+	/// Sends an RPC to the peer process, blocking while too many RPCs sent this way haven't been received by the peer yet.
+	///
+	/// This sits between [`ViaductTx::rpc`], which lets the sender get as far ahead of the peer as the pipe allows, and a request, which waits for the peer every time. The peer acknowledges windowed RPCs in batches as its event loop reads them, so the sender only waits once the window (see [`ViaductParent::rpc_window`](crate::ViaductParent::rpc_window)) is full of RPCs the peer hasn't got to yet. This bounds how far a producer can outrun its consumer, with much less overhead than waiting for each message.
+	///
+	/// Acknowledgements are sent by the peer's event loop and received by this process' event loop, so both must be running. Calling this from inside the viaduct's own event loop returns a [`ViaductError::RequestFromEventLoop`] error, and once the event loop has stopped, a full window fails with the viaduct's [close reason](ViaductTx::close_reason) instead of waiting forever. Requires the peer to support [`Capability::WindowedRpc`], otherwise a [`ViaductError::MissingCapability`] error is returned.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	pub fn rpc_windowed(&self, rpc: RpcTx) -> Result<(), std::io::Error> {
+		if !self.peer_supports(Capability::WindowedRpc) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::WindowedRpc,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		// Acknowledgements are received by our own event loop, which can't run while we wait for them
+		self.refuse_from_event_loop()?;
+
+		let Some(ack_requested) = self.0.rpc_window.acquire() else {
+			return Err(self.0.close_error());
+		};
+
+		let mut state = self.0.state.lock();
+
+		let serialize = Stopwatch::start();
+		rpc.to_pipeable({
+			state.buf.clear();
+			&mut state.buf
+		})
+		.expect("Failed to serialize RpcTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		if let Err(err) = ViaductTxState::send_packet(&mut state, &[WINDOWED_RPC, ack_requested as u8], true, false) {
+			// The RPC wasn't sent, so it will never be acknowledged
+			self.0.rpc_window.cancel();
+			return Err(err);
+		}
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		Ok(())
+	}
+
+	/// Sends an RPC to the peer process, unless it can't be sent before `deadline`, in which case it is dropped.
+	///
+	/// Returns `Ok(false)` if the RPC was dropped. This is the expected outcome when the peer falls behind, rather than an error, which makes this useful for streaming data that is worthless once it's stale.
+	///
+	/// The RPC is dropped if other threads hold up the viaduct past the deadline, if the peer process has run out of [credits](crate::ViaductParent::rpc_credits), or if the pipe is full until then. Once the RPC starts being written, it is written in full, even if that takes longer than the deadline, so keep RPCs sent this way small (a few KiB at most) - a pipe with some room in it can still block partway through a large write. On Windows, only the former is checked, as anonymous pipes can't be polled for room.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	pub fn rpc_deadline(&self, deadline: Instant, rpc: RpcTx) -> Result<bool, std::io::Error> {
+		let Ok(mut state) = lock_until(&self.0.state, Some(deadline)) else {
+			return Ok(false);
+		};
+		if self.rpc_credits() == Some(0) {
+			return Ok(false);
+		}
+
+		if let PipeSink::Pipe(pipe) = state.tx.get_ref().sink()? {
+			if !os::wait_writable(pipe, deadline)? {
+				return Ok(false);
+			}
+		}
+
+		let serialize = Stopwatch::start();
+		rpc.to_pipeable({
+			state.buf.clear();
+			&mut state.buf
+		})
+		.expect("Failed to serialize RpcTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet(&mut state, &[RPC], true, false)?;
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		#[cfg(feature = "tracing")]
+		tracing::trace!(len = state.buf.len(), "viaduct rpc sent");
+
+		Ok(true)
+	}
+
+	/// Serializes an RPC ahead of time, so that it can be sent with [`ViaductTx::send_prepared`] any number of times, over any viaduct with the same `RpcTx` type, without serializing it again.
+	///
+	/// This is useful for sending the same message to many child processes.
+	pub fn prepare_rpc(&self, rpc: &RpcTx) -> PreparedMessage<RpcTx> {
+		let mut bytes = Vec::new();
+		rpc.to_pipeable(&mut bytes).expect("Failed to serialize RpcTx");
+		PreparedMessage {
+			bytes: bytes.into(),
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Sends an RPC that was serialized ahead of time with [`ViaductTx::prepare_rpc`] to the peer process.
+	///
+	/// # Panics
+	///
+	/// This function won't panic, but the peer process will panic if the RPC is unable to be deserialized.
+	pub fn send_prepared(&self, rpc: &PreparedMessage<RpcTx>) -> Result<(), std::io::Error> {
+		let mut state = self.0.state.lock();
+
+		state.buf.clear();
+		state.buf.extend_from_slice(&rpc.bytes);
+
+		let write = Stopwatch::start();
+		ViaductTxState::send_packet(&mut state, &[RPC], true, false)?;
+		self.0.timings.record_send(Duration::ZERO, write.elapsed());
+
+		#[cfg(feature = "tracing")]
+		tracing::trace!(len = state.buf.len(), "viaduct rpc sent");
+
+		Ok(())
+	}
+
+	/// Sends a request to the peer process and awaits a response.
+	///
+	/// This will block the current thread.
+	///
+	/// Any number of threads can have requests outstanding at once. Each request is tracked by its own ID, so a thread only waits for its own response, and the viaduct is only locked while the request is being written.
+	///
+	/// If the event loop stops before the response arrives, such as when the peer process dies, the request fails with the error that stopped it instead of waiting forever: a [`ViaductError::PeerGone`] error if the peer went away, or an error of kind [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) if the [`ViaductRx`] was dropped. See [`ViaductTx::close_reason`].
+	///
+	/// The response is received by the event loop, so requests can't be sent from the thread running it, such as from inside an event handler passed to [`ViaductRx::run`]: they fail straight away with a [`ViaductError::RequestFromEventLoop`] error instead of deadlocking. Send them from another thread, or handle events with [`ViaductRx::run_pool`] or [`ViaductRx::run_prioritized`], whose event handlers run on other threads. This also applies to every other way of sending a request that waits for the response.
+	///
+	/// With the `tracing` feature enabled, the request is made inside a `viaduct_request` span carrying its ID, which records whether the response was `Some` and how long the round-trip took once it arrives.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	#[inline]
+	pub fn request<Response: ViaductDeserialize>(&self, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
+		self.request_inner(request, None, Priority::Normal, None)
+	}
+
+	/// Sends a request to the peer process with a [`Priority`], and awaits a response.
+	///
+	/// If the peer is handling events with [`ViaductRx::run_prioritized`], requests that are waiting to be handled are handled in order of priority, so an urgent request doesn't have to wait behind a queue of less important ones. Otherwise, the priority is only available to the peer's event handler, via [`ViaductRequestResponder::priority`].
+	///
+	/// Requires the peer to support [`Capability::RequestPriority`], otherwise a [`ViaductError::MissingCapability`] error is returned.
+	///
+	/// This will block the current thread.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	pub fn request_priority<Response: ViaductDeserialize>(&self, priority: Priority, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
+		if !self.peer_supports(Capability::RequestPriority) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::RequestPriority,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		self.request_inner(request, None, priority, None)
+	}
+
+	/// Sends a request to the peer process, tagged with an application-level correlation context, and awaits a response.
+	///
+	/// The context is sent alongside the request and is available to the peer via [`ViaductRequestResponder::context`]. With the `tracing` feature enabled, it is also recorded on the request's spans on both sides.
+	///
+	/// This will block the current thread.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	#[inline]
+	pub fn request_with_context<Response: ViaductDeserialize>(&self, context: &str, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
+		self.request_inner(request, Some(context), Priority::Normal, None)
+	}
+
+	/// Sends a request to the peer process and awaits a response, timing out after an [`Instant`](std::time::Instant) has passed.
+	///
+	/// This will block the current thread.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	#[inline]
+	pub fn request_timeout_at<Response: ViaductDeserialize>(
+		&self,
+		timeout_at: Instant,
+		request: RequestTx,
 	) -> Result<Option<Response>, std::io::Error> {
-		let mut response = self
-			.0
-			.response
-			.try_lock_until(timeout_at)
-			.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::TimedOut))?;
+		self.request_inner(request, None, Priority::Normal, Some(timeout_at))
+	}
+
+	/// Sends a request to the peer process and awaits a response, timing out after the given duration.
+	///
+	/// This will block the current thread.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	#[inline]
+	pub fn request_timeout<Response: ViaductDeserialize>(&self, timeout: Duration, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
+		self.request_timeout_at(Instant::now() + timeout, request)
+	}
 
+	/// Sends a request to the peer process and awaits a response, without blocking the current thread while waiting for it.
+	///
+	/// The event loop wakes the task once the response arrives, so it must be running, and any number of requests can be awaited at once. Sending the request still writes it to the pipe from the current thread, just like [`ViaductTx::rpc`]. Dropping the future abandons the request, in which case any response the peer sends for it is discarded.
+	///
+	/// Requires the `tokio` feature.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	#[cfg(feature = "tokio")]
+	pub async fn request_async<Response: ViaductDeserialize>(&self, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
 		// Get a request ID
 		let request_id = Uuid::new_v4();
 
-		response.pending.insert(request_id);
+		// Register the request before sending it, so that the reader knows who to hand the response to, however quickly it arrives
+		let waiter = Arc::new(AsyncResponse::default());
+		self.0.insert_pending(request_id, PendingResponse::Async(waiter.clone()))?;
 
-		// Send the request down the wire
-		{
-			let mut state = self
-				.0
-				.state
-				.try_lock_until(timeout_at)
-				.ok_or_else(|| std::io::Error::from(std::io::ErrorKind::TimedOut))?;
-			let ViaductTxState { buf, tx, .. } = &mut *state;
-
-			request
-				.to_pipeable({
-					buf.clear();
-					buf
-				})
-				.expect("Failed to serialize RequestTx");
-
-			tx.write_all(&[1])?;
-			tx.write_all(request_id.as_bytes())?;
-			tx.write_all(&u64::to_ne_bytes(buf.len() as _))?;
-			tx.write_all(&*buf)?;
-		}
-
-		if self
-			.0
-			.response_condvar
-			.wait_while_until(&mut response, |response| response.request_id() != Some(&request_id), timeout_at)
-			.timed_out()
+		// Don't leave a stale entry behind, whichever way we leave (including the future being dropped)
+		let _guard = PendingGuard {
+			pending: &self.0.pending,
+			request_id,
+		};
+
+		#[cfg(feature = "tracing")]
+		let span = request_span(request_id, None);
+
+		let sent_at = Instant::now();
 		{
-			response.pending.remove(&request_id);
-			return Err(std::io::Error::from(std::io::ErrorKind::TimedOut));
+			#[cfg(feature = "tracing")]
+			let _span = span.enter();
+			self.send_request(request_id, request, None, Priority::Normal, None)?;
+		}
+
+		let response = std::future::poll_fn(|cx| waiter.poll(cx)).await?;
+
+		#[cfg(feature = "tracing")]
+		let _span = span.enter();
+		Ok(self.complete_request(sent_at, response))
+	}
+
+	/// Sends a request to the peer process, which responds with a stream of chunks (see [`ViaductRequestResponder::respond_stream`]).
+	///
+	/// This returns as soon as the request has been sent. Iterating over the returned [`ResponseStream`] blocks until each chunk arrives, and ends once the peer ends the stream. If the peer responds with [`ViaductRequestResponder::respond`] instead, the stream has that one chunk, and if it doesn't respond at all, the stream is empty. Dropping the stream abandons the request, in which case any more chunks the peer sends for it are discarded.
+	///
+	/// Chunks are queued by the event loop until they are taken, so a slow consumer doesn't hold up the viaduct, but does use up memory.
+	///
+	/// Requires the peer to support [`Capability::StreamingResponses`], otherwise a [`ViaductError::MissingCapability`] error is returned.
+	///
+	/// # Panics
+	///
+	/// Iterating over the stream will panic if the peer process doesn't send the expected type (`Chunk`) as each chunk.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::ViaductChild;
+	/// # let tx = unsafe { ViaductChild::<(), u32, (), u32>::new().build() }.unwrap().split().0;
+	/// for chunk in tx.request_stream::<u32>(10).unwrap() {
+	///     println!("Received chunk {}", chunk.unwrap());
+	/// }
+	/// ```
+	pub fn request_stream<Chunk: ViaductDeserialize>(&self, request: RequestTx) -> Result<ResponseStream<'_, Chunk>, std::io::Error> {
+		if !self.peer_supports(Capability::StreamingResponses) {
+			return Err(ViaductError::MissingCapability {
+				required: Capability::StreamingResponses,
+				peer_supported: self.0.peer_capabilities.iter().collect(),
+			}
+			.into());
+		}
+
+		self.refuse_from_event_loop()?;
+
+		let request_id = Uuid::new_v4();
+
+		// Register the request before sending it, so that the reader knows who to hand the chunks to, however quickly they arrive
+		let receiver = Arc::new(StreamReceiver::default());
+		self.0.insert_pending(request_id, PendingResponse::Stream(receiver.clone()))?;
+
+		// Don't leave a stale entry behind, whichever way we leave (including the stream being dropped)
+		let guard = PendingGuard {
+			pending: &self.0.pending,
+			request_id,
+		};
+
+		self.send_request(request_id, request, None, Priority::Normal, None)?;
+
+		Ok(ResponseStream {
+			receiver,
+			timings: &self.0.timings,
+			_guard: guard,
+			_phantom: PhantomData,
+		})
+	}
+
+	fn request_inner<Response: ViaductDeserialize>(
+		&self,
+		request: RequestTx,
+		context: Option<&str>,
+		priority: Priority,
+		timeout_at: Option<Instant>,
+	) -> Result<Option<Response>, std::io::Error> {
+		self.refuse_from_event_loop()?;
+
+		// Get a request ID
+		let request_id = Uuid::new_v4();
+
+		#[cfg(feature = "tracing")]
+		let _span = request_span(request_id, context).entered();
+
+		// Register the request before sending it, so that the reader knows who to hand the response to, however quickly it arrives
+		let waiter = Arc::new(ResponseWaiter::default());
+		self.0.insert_pending(request_id, PendingResponse::Waiter(waiter.clone()))?;
+
+		// Don't leave a stale entry behind, whichever way we leave
+		let _guard = PendingGuard {
+			pending: &self.0.pending,
+			request_id,
+		};
+
+		let sent_at = Instant::now();
+		self.send_request(request_id, request, context, priority, timeout_at)?;
+
+		let response = match waiter.wait(timeout_at) {
+			Some(response) => response?,
+
+			None => {
+				if self.0.pending.lock().remove(&request_id).is_some() {
+					// Any late response for this request will be discarded by the reader, as it is no longer pending.
+					return Err(std::io::Error::from(std::io::ErrorKind::TimedOut));
+				}
+
+				// The reader claimed the request right as we timed out, and is about to hand the response over
+				waiter.wait(None).unwrap()?
+			}
+		};
+
+		Ok(self.complete_request(sent_at, response))
+	}
+
+	/// Identifies this viaduct's event loop in [`EVENT_LOOPS`].
+	#[inline]
+	fn event_loop_id(&self) -> usize {
+		Arc::as_ptr(&self.0) as *const () as usize
+	}
+
+	/// Fails with a [`ViaductError::RequestFromEventLoop`] error if this viaduct's event loop is running on the current thread, as waiting for a response here would block it forever.
+	fn refuse_from_event_loop(&self) -> Result<(), std::io::Error> {
+		let id = self.event_loop_id();
+		if EVENT_LOOPS.with_borrow(|event_loops| event_loops.contains(&id)) {
+			return Err(ViaductError::RequestFromEventLoop.into());
 		}
+		Ok(())
+	}
 
-		let (for_request_id, some) = response.for_request_id.take().unwrap();
-		debug_assert_eq!(for_request_id, request_id);
+	/// Reports a request's round-trip time, and deserializes its response.
+	///
+	/// With the `tracing` feature, this must be called from inside the request's span (see [`request_span`]), which the outcome is recorded on.
+	fn complete_request<Response: ViaductDeserialize>(&self, sent_at: Instant, response: Option<Vec<u8>>) -> Option<Response> {
+		let elapsed = sent_at.elapsed();
+		if let Some(on_request_complete) = &mut *self.0.on_request_complete.lock() {
+			on_request_complete(elapsed);
+		}
 
-		// Notify the condvar because the writer half might be waiting for the request ID to become None
-		self.0.response_condvar.notify_all();
+		#[cfg(feature = "tracing")]
+		{
+			let span = tracing::Span::current();
+			span.record("some", response.is_some());
+			span.record("elapsed", tracing::field::debug(elapsed));
+			tracing::debug!(some = response.is_some(), ?elapsed, "viaduct response received");
+		}
 
 		// Deserialize the response and return it
-		Ok(if some {
-			Some(Response::from_pipeable(&response.buf).expect("Failed to deserialize Response"))
-		} else {
-			None
+		response.map(|response| {
+			let deserialize = Stopwatch::start();
+			let response = Response::from_pipeable(&response).expect("Failed to deserialize Response");
+			self.0.timings.record_deserialize(deserialize.elapsed());
+			response
 		})
 	}
 
-	/// Sends a request to the peer process and awaits a response, timing out after the given duration.
+	fn send_request(
+		&self,
+		request_id: Uuid,
+		request: RequestTx,
+		context: Option<&str>,
+		priority: Priority,
+		timeout_at: Option<Instant>,
+	) -> Result<(), std::io::Error> {
+		let mut state = lock_until(&self.0.state, timeout_at)?;
+
+		let serialize = Stopwatch::start();
+		request
+			.to_pipeable({
+				state.buf.clear();
+				&mut state.buf
+			})
+			.expect("Failed to serialize RequestTx");
+		let serialize = serialize.elapsed();
+
+		let write = Stopwatch::start();
+		if let Some(context) = context {
+			let mut header = Vec::with_capacity(1 + 16 + size_of::<u64>() + context.len());
+			header.push(REQUEST_WITH_CONTEXT);
+			header.extend_from_slice(request_id.as_bytes());
+			header.extend_from_slice(&u64::to_ne_bytes(context.len() as _));
+			header.extend_from_slice(context.as_bytes());
+			ViaductTxState::send_packet(&mut state, &header, true, true)?;
+		} else if priority != Priority::Normal {
+			let mut header = [REQUEST_WITH_PRIORITY; 1 + 1 + 16];
+			header[1] = priority as u8;
+			header[2..].copy_from_slice(request_id.as_bytes());
+			ViaductTxState::send_packet(&mut state, &header, true, true)?;
+		} else {
+			let mut header = [REQUEST; 1 + 16];
+			header[1..].copy_from_slice(request_id.as_bytes());
+			ViaductTxState::send_packet(&mut state, &header, true, true)?;
+		}
+		self.0.timings.record_send(serialize, write.elapsed());
+
+		Ok(())
+	}
+
+	/// Calls `callback` with the round-trip time of every request sent from now on that receives a response, from any clone of this [`ViaductTx`].
 	///
-	/// This will block the current thread.
+	/// The round-trip is measured from just before the request is sent until its response (or "no response") arrives, not counting deserializing the response. Requests that time out or fail aren't reported. This is useful for keeping track of the latency distribution of requests, for example to set timeouts adaptively.
+	///
+	/// The callback runs on the thread that sent the request, and is never called by two threads at once, so keep it quick. Calling this again replaces the previous callback.
+	pub fn on_request_complete<F: FnMut(Duration) + Send + 'static>(&self, callback: F) {
+		*self.0.on_request_complete.lock() = Some(Box::new(callback));
+	}
+
+	/// Returns how many messages this viaduct has sent and received, and how many bytes they took up.
+	///
+	/// The counts start from zero when the viaduct is built, and are shared by every clone of this [`ViaductTx`] and by its [`ViaductRx`], so they cover the whole viaduct.
+	#[inline]
+	pub fn metrics(&self) -> crate::ViaductMetrics {
+		self.0.metrics.get()
+	}
+
+	/// Returns how many more RPC packets the peer process will accept before its event loop catches up, or `None` if it doesn't limit them.
+	///
+	/// Once this reaches zero, sending an RPC fails with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock) until the peer grants more credits. See [`ViaductParent::rpc_credits`](crate::ViaductParent::rpc_credits).
+	#[inline]
+	pub fn rpc_credits(&self) -> Option<u64> {
+		self.0.rpc_credits.available.as_ref().map(|available| available.load(Ordering::Acquire))
+	}
+
+	/// Returns the cumulative time this viaduct has spent serializing, writing, reading and deserializing packets.
+	///
+	/// Requires the `timing` feature.
+	#[cfg(feature = "timing")]
+	#[inline]
+	pub fn timings(&self) -> crate::ViaductTimings {
+		self.0.timings.get()
+	}
+}
+/// The chunks of a streamed response, as they arrive from the peer process.
+///
+/// Each item is a chunk, or the error that stopped the viaduct before the stream ended. See [`ViaductTx::request_stream`].
+pub struct ResponseStream<'a, Chunk> {
+	receiver: Arc<StreamReceiver>,
+	timings: &'a TimingRecorder,
+	_guard: PendingGuard<'a>,
+	_phantom: PhantomData<fn() -> Chunk>,
+}
+impl<Chunk: ViaductDeserialize> Iterator for ResponseStream<'_, Chunk> {
+	type Item = Result<Chunk, std::io::Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let chunk = match self.receiver.next()? {
+			Ok(chunk) => chunk,
+			Err(err) => return Some(Err(err)),
+		};
+
+		let deserialize = Stopwatch::start();
+		let chunk = Chunk::from_pipeable(&chunk).expect("Failed to deserialize response chunk");
+		self.timings.record_deserialize(deserialize.elapsed());
+		Some(Ok(chunk))
+	}
+}
+
+/// An RPC that has already been serialized, and can be sent any number of times over any viaduct whose `RpcTx` type matches.
+///
+/// See [`ViaductTx::prepare_rpc`].
+pub struct PreparedMessage<RpcTx> {
+	bytes: Arc<[u8]>,
+	_phantom: PhantomData<fn(&RpcTx)>,
+}
+impl<RpcTx> PreparedMessage<RpcTx> {
+	/// Returns the serialized message.
+	#[inline]
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+}
+impl<RpcTx> Clone for PreparedMessage<RpcTx> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Self {
+			bytes: self.bytes.clone(),
+			_phantom: PhantomData,
+		}
+	}
+}
+
+/// An RPC or request that was received, but hasn't been deserialized yet.
+///
+/// See [`ViaductRx::run_lazy`].
+pub struct LazyMessage<T> {
+	bytes: Vec<u8>,
+	_phantom: PhantomData<fn() -> T>,
+}
+impl<T> LazyMessage<T> {
+	#[inline]
+	fn new(bytes: Vec<u8>) -> Self {
+		Self {
+			bytes,
+			_phantom: PhantomData,
+		}
+	}
+
+	/// Returns the serialized message.
+	#[inline]
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	/// Returns the serialized message, taking ownership of its buffer.
+	#[inline]
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes
+	}
+}
+impl<T: ViaductDeserialize> LazyMessage<T> {
+	/// Deserializes the message.
 	///
 	/// # Panics
 	///
-	/// This function will panic if the peer process doesn't send the expected type (`Response`) as the response.
+	/// This function will panic if the message fails to deserialize.
 	#[inline]
-	pub fn request_timeout<Response: ViaductDeserialize>(&self, timeout: Duration, request: RequestTx) -> Result<Option<Response>, std::io::Error> {
-		self.request_timeout_at(Instant::now() + timeout, request)
+	pub fn decode(&self) -> T {
+		T::from_pipeable(&self.bytes).expect("Failed to deserialize lazy message")
+	}
+
+	/// Accesses the archived form of the message, without deserializing it.
+	///
+	/// The archived message borrows this [`LazyMessage`], so it can be used for as long as the message is kept around, on whichever thread it was sent to.
+	///
+	/// See [`ViaductArchived::from_pipeable_archived`](crate::ViaductArchived::from_pipeable_archived) for the alignment rkyv requires.
+	///
+	/// Requires the `rkyv` feature.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the message fails to validate.
+	#[cfg(feature = "rkyv")]
+	#[inline]
+	pub fn archived(&self) -> &rkyv::Archived<T>
+	where
+		T: crate::ViaductArchived,
+	{
+		T::from_pipeable_archived(&self.bytes).expect("Failed to access archived lazy message")
+	}
+}
+
+/// An RPC or request that the peer process sent, but this process failed to deserialize.
+///
+/// See [`ViaductRx::run_with_error_handler`].
+pub enum DeserializeError<RpcRx: ViaductDeserialize, RequestRx: ViaductDeserialize> {
+	/// An RPC failed to deserialize.
+	Rpc(RpcRx::Error),
+
+	/// A request failed to deserialize.
+	Request(RequestRx::Error),
+}
+impl<RpcRx: ViaductDeserialize, RequestRx: ViaductDeserialize> DeserializeError<RpcRx, RequestRx> {
+	/// The kind of packet that failed to deserialize.
+	#[inline]
+	pub fn packet_type(&self) -> PacketType {
+		match self {
+			Self::Rpc(_) => PacketType::Rpc,
+			Self::Request(_) => PacketType::Request,
+		}
+	}
+}
+impl<RpcRx: ViaductDeserialize, RequestRx: ViaductDeserialize> std::fmt::Debug for DeserializeError<RpcRx, RequestRx> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Rpc(err) => f.debug_tuple("Rpc").field(err).finish(),
+			Self::Request(err) => f.debug_tuple("Request").field(err).finish(),
+		}
 	}
 }
+
+/// The payload of an RPC or request received by [`ViaductRx::run_mapped`].
+pub enum MappedPayload<M, T> {
+	/// The payload was received into the destination returned by the callback, and hasn't been deserialized.
+	Mapped(M),
+
+	/// The callback didn't return a destination, so the payload was received into a buffer and deserialized as usual.
+	Buffered(T),
+}
+
 impl<RpcTx, RequestTx, RpcRx, RequestRx> Clone for ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
@@ -0,0 +1,43 @@
+use crate::os::ProcessKiller;
+use std::{fmt::Debug, process::Child, sync::Arc};
+
+/// A handle to a child process spawned by [`ViaductParent`](crate::ViaductParent), which can find out its process ID and kill it without needing its [`Child`].
+///
+/// Clones share the same handle, so they can be handed out to as many places as need them. Get one from [`ViaductTx::child_handle`](crate::ViaductTx::child_handle).
+///
+/// On Unix, the handle holds on to the child process' ID. Once the [`Child`] has been waited on, the process ID may be reused by another process, so don't kill the child process through this handle after waiting on it. On Windows, the handle holds on to a duplicate of the child process' handle, so it always refers to the same process.
+#[derive(Clone)]
+pub struct ChildHandle(Arc<ChildHandleInner>);
+
+struct ChildHandleInner {
+	pid: u32,
+	killer: ProcessKiller,
+}
+
+impl ChildHandle {
+	pub(super) fn new(child: &Child) -> Result<Self, std::io::Error> {
+		Ok(Self(Arc::new(ChildHandleInner {
+			pid: child.id(),
+			killer: ProcessKiller::new(child)?,
+		})))
+	}
+
+	/// Returns the child process' ID, like [`Child::id`].
+	#[inline]
+	pub fn pid(&self) -> u32 {
+		self.0.pid
+	}
+
+	/// Forces the child process to exit, like [`Child::kill`].
+	///
+	/// If the child process has already exited, this does nothing and returns `Ok(())`. The child process still needs to be waited on through its [`Child`], so that it doesn't linger as a zombie.
+	#[inline]
+	pub fn kill(&self) -> Result<(), std::io::Error> {
+		self.0.killer.kill()
+	}
+}
+impl Debug for ChildHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ChildHandle").field("pid", &self.0.pid).finish()
+	}
+}
@@ -1,4 +1,4 @@
-use crate::{ViaductDeserialize, ViaductRequestResponder, ViaductRx, ViaductSerialize, ViaductTx};
+use crate::{LazyMessage, PreparedMessage, Viaduct, ViaductDeserialize, ViaductRequestResponder, ViaductRx, ViaductSerialize, ViaductTx};
 use std::fmt::Debug;
 
 impl<RpcTx, RequestTx, RpcRx, RequestRx> Debug for ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>
@@ -39,3 +39,30 @@ where
 		f.debug_struct("ViaductRx").finish()
 	}
 }
+
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Debug for Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	#[inline]
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Viaduct").finish()
+	}
+}
+
+impl<RpcTx> Debug for PreparedMessage<RpcTx> {
+	#[inline]
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("PreparedMessage").field("len", &self.as_bytes().len()).finish()
+	}
+}
+
+impl<T> Debug for LazyMessage<T> {
+	#[inline]
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LazyMessage").field("len", &self.as_bytes().len()).finish()
+	}
+}
@@ -0,0 +1,294 @@
+use crate::Capability;
+use std::{fmt::Display, process::ExitStatus};
+
+/// Errors specific to Viaduct.
+///
+/// Viaduct's functions return [`std::io::Error`]s, which these errors are wrapped in. You can use [`ViaductError::from_io`] to get them back out.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ViaductError {
+	/// The peer sent more fragmented packets than we are willing to hold in memory while reassembling them.
+	///
+	/// See [`ViaductParent::max_reassembly_bytes`](crate::ViaductParent::max_reassembly_bytes) and [`ViaductParent::max_concurrent_fragments`](crate::ViaductParent::max_concurrent_fragments).
+	ReassemblyLimit {
+		/// The number of bytes that would have been held for reassembly.
+		bytes: usize,
+
+		/// The maximum number of bytes that can be held for reassembly.
+		max_bytes: usize,
+
+		/// The number of packets that would have been in the process of being reassembled.
+		fragments: usize,
+
+		/// The maximum number of packets that can be in the process of being reassembled at once.
+		max_fragments: usize,
+	},
+
+	/// The peer is using a different serialization backend to us, so we wouldn't be able to understand each other.
+	///
+	/// See [`backend_name`](crate::backend_name).
+	BackendMismatch {
+		/// The serialization backend we are using.
+		local: &'static str,
+
+		/// The serialization backend the peer is using.
+		peer: String,
+	},
+
+	/// The peer speaks a different version of the viaduct protocol to us, usually because it was built against a different version of Viaduct.
+	ProtocolMismatch {
+		/// The protocol version we speak.
+		local: u16,
+
+		/// The protocol version the peer speaks.
+		peer: u16,
+	},
+
+	/// The peer doesn't support a capability that we require.
+	///
+	/// See [`ViaductParent::require_capability`](crate::ViaductParent::require_capability).
+	MissingCapability {
+		/// The capability we require.
+		required: Capability,
+
+		/// The capabilities the peer supports.
+		peer_supported: Vec<Capability>,
+	},
+
+	/// The peer closed its side of the viaduct, usually because it exited or was killed.
+	///
+	/// Returned by the event loop (see [`ViaductRx::run`](crate::ViaductRx::run)) when it reaches the end of the stream, so a process that always runs the event loop doesn't need a reaper thread to notice that its peer has gone.
+	PeerGone,
+
+	/// A message was larger than the maximum size we are allowed to send, so it wasn't sent.
+	///
+	/// See [`ViaductParent::max_send_size`](crate::ViaductParent::max_send_size).
+	MessageTooLarge {
+		/// The size of the serialized message, in bytes.
+		size: usize,
+
+		/// The maximum size of a message that can be sent, in bytes.
+		limit: usize,
+	},
+
+	/// The peer sent a message larger than the maximum size we are willing to receive, so it wasn't received.
+	///
+	/// See [`ViaductParent::max_message_size`](crate::ViaductParent::max_message_size).
+	ReceiveLimit {
+		/// The size of the message, in bytes, as claimed by the peer.
+		size: u64,
+
+		/// The maximum size of a message that can be received, in bytes.
+		limit: usize,
+	},
+
+	/// The child process exited before completing the handshake, usually because it crashed on startup or never builds its side of the viaduct.
+	///
+	/// Returned by [`ViaductParent::build`](crate::ViaductParent::build) instead of a generic pipe error, to tell a child process that died apart from one that is just slow.
+	ChildExitedDuringHandshake {
+		/// The child process' exit status.
+		status: ExitStatus,
+	},
+
+	/// A request or windowed RPC was sent from the thread running the viaduct's event loop, such as from inside an event handler passed to [`ViaductRx::run`](crate::ViaductRx::run).
+	///
+	/// Only the event loop can receive the response, and it can't while it's waiting for the response, so the request would never complete. Likewise, a windowed RPC may need to wait for acknowledgements that only the event loop can receive. Nothing is sent; send it from another thread instead.
+	RequestFromEventLoop,
+
+	/// This process already built its side of a viaduct with [`ViaductChild`](crate::ViaductChild), which took the pipe handles the parent process passed to it.
+	///
+	/// Each handle can only be taken once, as the viaduct owns it and closes it when dropped, so building the child's side of the viaduct again (for example, in a retry loop) would use handles that may already be closed or reused. Returned by [`ViaductChild::build`](crate::ViaductChild::build) instead.
+	AlreadyBuilt,
+}
+impl ViaductError {
+	/// Returns the [`ViaductError`] wrapped in an [`std::io::Error`] returned by Viaduct, if there is one.
+	#[inline]
+	pub fn from_io(err: &std::io::Error) -> Option<&ViaductError> {
+		err.get_ref()?.downcast_ref()
+	}
+
+	fn kind(&self) -> std::io::ErrorKind {
+		match self {
+			Self::ReassemblyLimit { .. } | Self::ReceiveLimit { .. } => std::io::ErrorKind::InvalidData,
+			Self::BackendMismatch { .. } | Self::ProtocolMismatch { .. } | Self::MissingCapability { .. } => std::io::ErrorKind::Unsupported,
+			Self::PeerGone => std::io::ErrorKind::UnexpectedEof,
+			Self::MessageTooLarge { .. } | Self::RequestFromEventLoop => std::io::ErrorKind::InvalidInput,
+			Self::ChildExitedDuringHandshake { .. } => std::io::ErrorKind::BrokenPipe,
+			Self::AlreadyBuilt => std::io::ErrorKind::AlreadyExists,
+		}
+	}
+}
+impl Display for ViaductError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::ReassemblyLimit {
+				bytes,
+				max_bytes,
+				fragments,
+				max_fragments,
+			} => write!(
+				f,
+				"Peer exceeded the fragment reassembly limit ({bytes}/{max_bytes} bytes, {fragments}/{max_fragments} packets)"
+			),
+			Self::BackendMismatch { local, peer } => write!(f, "Peer is using the {peer:?} serialization backend, but we are using {local:?}"),
+			Self::ProtocolMismatch { local, peer } => write!(f, "Peer speaks version {peer} of the viaduct protocol, but we speak version {local}"),
+			Self::MissingCapability { required, peer_supported } => {
+				write!(
+					f,
+					"Peer doesn't support the required capability {required:?} (peer supports {peer_supported:?})"
+				)
+			}
+			Self::PeerGone => write!(f, "Peer closed its side of the viaduct"),
+			Self::MessageTooLarge { size, limit } => write!(f, "Message is too large to send ({size} bytes, limit is {limit} bytes)"),
+			Self::ReceiveLimit { size, limit } => write!(
+				f,
+				"Peer sent a message that is too large to receive ({size} bytes, limit is {limit} bytes)"
+			),
+			Self::ChildExitedDuringHandshake { status } => write!(f, "Child process exited before completing the handshake ({status})"),
+			Self::RequestFromEventLoop => write!(
+				f,
+				"Can't wait for a response on the thread running the viaduct's event loop, as the event loop is what receives it"
+			),
+			Self::AlreadyBuilt => write!(f, "This process has already taken the pipe handles passed to it by the parent process"),
+		}
+	}
+}
+impl std::error::Error for ViaductError {}
+impl From<ViaductError> for std::io::Error {
+	#[inline]
+	fn from(err: ViaductError) -> Self {
+		std::io::Error::new(err.kind(), err)
+	}
+}
+
+/// Why a viaduct was closed.
+///
+/// Both halves of a viaduct share the same close reason, which is the first thing that closed it. A viaduct that was only [shut down](CloseReason::Shutdown) can still receive, so its close reason is replaced once the event loop stops. See [`Viaduct::close_reason`](crate::Viaduct::close_reason).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum CloseReason {
+	/// The peer closed its side of the viaduct, usually because it exited or was killed.
+	PeerDropped,
+
+	/// The [`ViaductRx`](crate::ViaductRx) was dropped, so the event loop has stopped.
+	LocalClose,
+
+	/// The sending side of the viaduct was shut down with [`ViaductTx::shutdown_send`](crate::ViaductTx::shutdown_send).
+	Shutdown,
+
+	/// The peer closed its side of the viaduct gracefully with [`ViaductTx::close`](crate::ViaductTx::close).
+	PeerClosed,
+
+	/// The viaduct was replaced by a new one with [`ViaductRebuilder::rebuild`](crate::ViaductRebuilder::rebuild).
+	Rebuilt,
+
+	/// The event loop stopped because of a [`ViaductError`].
+	Error(ViaductError),
+
+	/// The event loop stopped because of an I/O error.
+	Io {
+		/// The kind of I/O error.
+		kind: std::io::ErrorKind,
+
+		/// The I/O error's message.
+		message: String,
+	},
+}
+impl CloseReason {
+	/// Works out why the viaduct was closed from the error that stopped the event loop.
+	pub(crate) fn from_io(err: &std::io::Error) -> Self {
+		match ViaductError::from_io(err) {
+			Some(ViaductError::PeerGone) => Self::PeerDropped,
+			Some(err) => Self::Error(err.clone()),
+			None => Self::Io {
+				kind: err.kind(),
+				message: err.to_string(),
+			},
+		}
+	}
+
+	/// Returns the error that requests fail with because of this close reason.
+	pub(crate) fn to_io(&self) -> std::io::Error {
+		match self {
+			Self::PeerDropped => ViaductError::PeerGone.into(),
+			Self::LocalClose => std::io::Error::new(std::io::ErrorKind::BrokenPipe, "The viaduct's event loop has stopped"),
+			Self::Shutdown => std::io::Error::new(std::io::ErrorKind::BrokenPipe, "The sending side of the viaduct was shut down"),
+			Self::PeerClosed => std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Peer closed its side of the viaduct"),
+			Self::Rebuilt => std::io::Error::new(std::io::ErrorKind::BrokenPipe, "The viaduct was replaced by a rebuilt one"),
+			Self::Error(err) => err.clone().into(),
+			Self::Io { kind, message } => std::io::Error::new(*kind, message.as_str()),
+		}
+	}
+}
+impl Display for CloseReason {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::PeerDropped => write!(f, "Peer closed its side of the viaduct"),
+			Self::LocalClose => write!(f, "The viaduct's event loop has stopped"),
+			Self::Shutdown => write!(f, "The sending side of the viaduct was shut down"),
+			Self::PeerClosed => write!(f, "Peer closed its side of the viaduct gracefully"),
+			Self::Rebuilt => write!(f, "The viaduct was replaced by a rebuilt one"),
+			Self::Error(err) => write!(f, "{err}"),
+			Self::Io { message, .. } => write!(f, "{message}"),
+		}
+	}
+}
+
+/// The error returned by [`ViaductTx::try_rpc`](crate::ViaductTx::try_rpc).
+pub enum TrySendError<T> {
+	/// Another thread was in the middle of sending something, so the RPC wasn't sent and is handed back.
+	WouldBlock(T),
+
+	/// Writing the RPC to the pipe failed.
+	Io(std::io::Error),
+}
+impl<T> TrySendError<T> {
+	/// Returns the RPC that wasn't sent, if another thread was in the middle of sending something.
+	#[inline]
+	pub fn into_inner(self) -> Option<T> {
+		match self {
+			Self::WouldBlock(rpc) => Some(rpc),
+			Self::Io(_) => None,
+		}
+	}
+}
+impl<T> std::fmt::Debug for TrySendError<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::WouldBlock(_) => f.write_str("WouldBlock(..)"),
+			Self::Io(err) => f.debug_tuple("Io").field(err).finish(),
+		}
+	}
+}
+impl<T> Display for TrySendError<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::WouldBlock(_) => write!(f, "Sending would block, as another thread is sending something"),
+			Self::Io(err) => write!(f, "{err}"),
+		}
+	}
+}
+impl<T> std::error::Error for TrySendError<T> {
+	#[inline]
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::WouldBlock(_) => None,
+			Self::Io(err) => Some(err),
+		}
+	}
+}
+impl<T> From<std::io::Error> for TrySendError<T> {
+	#[inline]
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+impl<T> From<TrySendError<T>> for std::io::Error {
+	#[inline]
+	fn from(err: TrySendError<T>) -> Self {
+		match err {
+			TrySendError::WouldBlock(_) => std::io::ErrorKind::WouldBlock.into(),
+			TrySendError::Io(err) => err,
+		}
+	}
+}
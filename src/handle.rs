@@ -0,0 +1,177 @@
+#[cfg(unix)]
+use std::os::unix::{
+	io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+	net::UnixDatagram,
+};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+#[cfg(windows)]
+use windows::Win32::{
+	Foundation::{DuplicateHandle, DUPLICATE_CLOSE_SOURCE, DUPLICATE_SAME_ACCESS, HANDLE},
+	System::Threading::GetCurrentProcess,
+};
+
+/// A handle that was shared by the peer process.
+#[cfg(windows)]
+pub(super) type SharedHandle = OwnedHandle;
+
+/// A file descriptor that was shared by the peer process.
+#[cfg(unix)]
+pub(super) type SharedHandle = OwnedFd;
+
+/// Duplicates `handle` from one process into another, returning its value in the target process.
+#[cfg(windows)]
+unsafe fn duplicate(source_process: HANDLE, handle: HANDLE, target_process: HANDLE, close_source: bool) -> Result<HANDLE, std::io::Error> {
+	let mut duplicated = HANDLE::default();
+	let options = if close_source {
+		DUPLICATE_SAME_ACCESS | DUPLICATE_CLOSE_SOURCE
+	} else {
+		DUPLICATE_SAME_ACCESS
+	};
+	if unsafe { DuplicateHandle(source_process, handle, target_process, &mut duplicated, 0, false, options) }.as_bool() {
+		Ok(duplicated)
+	} else {
+		Err(std::io::Error::last_os_error())
+	}
+}
+
+/// Prepares `handle` to be sent to the peer process, returning the value to send.
+///
+/// The parent knows the child's process handle, so it duplicates the handle straight into the child. The child can't do the same in reverse, so it duplicates the handle for itself, which the parent then takes out of the child when it's received (see [`receive`]).
+#[cfg(windows)]
+pub(super) fn share(peer_process: Option<&OwnedHandle>, handle: RawHandle) -> Result<u64, std::io::Error> {
+	let target_process = match peer_process {
+		Some(peer_process) => HANDLE(peer_process.as_raw_handle() as _),
+		None => unsafe { GetCurrentProcess() },
+	};
+	let shared = unsafe { duplicate(GetCurrentProcess(), HANDLE(handle as _), target_process, false)? };
+	Ok(shared.0 as u64)
+}
+
+/// Takes ownership of a handle that was sent by the peer process with [`share`].
+#[cfg(windows)]
+pub(super) fn receive(peer_process: Option<&OwnedHandle>, handle: u64) -> Result<OwnedHandle, std::io::Error> {
+	let handle = match peer_process {
+		Some(peer_process) => unsafe { duplicate(HANDLE(peer_process.as_raw_handle() as _), HANDLE(handle as _), GetCurrentProcess(), true)? },
+
+		// The parent has already duplicated the handle into this process
+		None => HANDLE(handle as _),
+	};
+	Ok(unsafe { OwnedHandle::from_raw_handle(handle.0 as RawHandle) })
+}
+
+/// Creates the pair of sockets that file descriptors are sent over, as `(parent, child)`.
+///
+/// File descriptors can't be written to a pipe, so they're sent as `SCM_RIGHTS` control messages over a datagram socket alongside the viaduct. Like the data pipes, the child's end is inherited by the child process, and ours isn't.
+#[cfg(unix)]
+pub(super) fn socket_pair() -> Result<(UnixDatagram, UnixDatagram), std::io::Error> {
+	let mut fds = [0; 2];
+	if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) } == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+	let (parent, child) = unsafe { (UnixDatagram::from_raw_fd(fds[0]), UnixDatagram::from_raw_fd(fds[1])) };
+	crate::os::disinherit(&parent)?;
+	Ok((parent, child))
+}
+
+/// Sends a duplicate of `fd` over `socket`, to be taken by the peer process with [`receive`].
+#[cfg(unix)]
+pub(super) fn share(socket: &UnixDatagram, fd: BorrowedFd<'_>) -> Result<(), std::io::Error> {
+	// At least one byte of real data has to be sent for the control message to be delivered
+	let mut byte = [0u8];
+	let mut iov = libc::iovec {
+		iov_base: byte.as_mut_ptr().cast(),
+		iov_len: byte.len(),
+	};
+
+	let mut control = [0u64; CONTROL_LEN];
+	let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+	msg.msg_iov = &mut iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control.as_mut_ptr().cast();
+	msg.msg_controllen = unsafe { libc::CMSG_SPACE(size_of::<RawFd>() as _) } as _;
+
+	unsafe {
+		let cmsg = libc::CMSG_FIRSTHDR(&msg);
+		(*cmsg).cmsg_level = libc::SOL_SOCKET;
+		(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+		(*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<RawFd>() as _) as _;
+		libc::CMSG_DATA(cmsg).cast::<RawFd>().write_unaligned(fd.as_raw_fd());
+	}
+
+	loop {
+		if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) } != -1 {
+			return Ok(());
+		}
+		let err = std::io::Error::last_os_error();
+		if err.kind() != std::io::ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+}
+
+/// Room for a control message carrying a single file descriptor, aligned for `cmsghdr`.
+#[cfg(unix)]
+const CONTROL_LEN: usize = 4;
+
+/// Takes ownership of a file descriptor that was sent by the peer process with [`share`].
+///
+/// The peer sends the file descriptor before the packet announcing it, so this doesn't block for long.
+#[cfg(unix)]
+pub(super) fn receive(socket: &UnixDatagram) -> Result<OwnedFd, std::io::Error> {
+	let mut byte = [0u8];
+	let mut iov = libc::iovec {
+		iov_base: byte.as_mut_ptr().cast(),
+		iov_len: byte.len(),
+	};
+
+	let mut control = [0u64; CONTROL_LEN];
+	let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+	msg.msg_iov = &mut iov;
+	msg.msg_iovlen = 1;
+	msg.msg_control = control.as_mut_ptr().cast();
+	msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+	// Don't let the file descriptor leak into child processes we spawn
+	#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+	let flags = libc::MSG_CMSG_CLOEXEC;
+	#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+	let flags = 0;
+
+	loop {
+		if unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) } != -1 {
+			break;
+		}
+		let err = std::io::Error::last_os_error();
+		if err.kind() != std::io::ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+
+	if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Received a truncated file descriptor",
+		));
+	}
+
+	let cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+	if cmsg.is_null() || unsafe { (*cmsg).cmsg_level != libc::SOL_SOCKET || (*cmsg).cmsg_type != libc::SCM_RIGHTS } {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Expected a file descriptor from the peer process, but none was received",
+		));
+	}
+
+	let fd = unsafe { OwnedFd::from_raw_fd(libc::CMSG_DATA(cmsg).cast::<RawFd>().read_unaligned()) };
+
+	#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+	{
+		let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+		if flags == -1 || unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, flags | libc::FD_CLOEXEC) } == -1 {
+			return Err(std::io::Error::last_os_error());
+		}
+	}
+
+	Ok(fd)
+}
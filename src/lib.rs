@@ -26,7 +26,8 @@
 //! ```no_run
 //! # use viaduct::{ViaductEvent, ViaductParent, doctest::*};
 //! let child = std::process::Command::new("child.exe");
-//! let ((tx, rx), mut child) = ViaductParent::new(child).unwrap().build().unwrap();
+//! let (viaduct, mut child) = ViaductParent::new(child).unwrap().build().unwrap();
+//! let (tx, rx) = viaduct.split();
 //!
 //! std::thread::spawn(move || {
 //!    rx.run(|event| match event {
@@ -47,6 +48,9 @@
 //!                responder.respond(Ok::<_, BackflipError>(())).unwrap();
 //!            },
 //!        }
+//!
+//!        // Handles shared by the peer process, and any kinds of event added in the future
+//!        _ => {}
 //!    }).unwrap();
 //! });
 //!
@@ -62,7 +66,7 @@
 //!
 //! ```no_run
 //! # use viaduct::{ViaductEvent, ViaductChild, doctest::*};
-//! let (tx, rx) = unsafe { ViaductChild::new().build() }.unwrap();
+//! let (tx, rx) = unsafe { ViaductChild::new().build() }.unwrap().split();
 //!
 //! std::thread::spawn(move || {
 //!    rx.run(|event| match event {
@@ -83,6 +87,9 @@
 //!                responder.respond(Ok::<_, BackflipError>(())).unwrap();
 //!            },
 //!        }
+//!
+//!        // Handles shared by the peer process, and any kinds of event added in the future
+//!        _ => {}
 //!    }).unwrap();
 //! });
 //!
@@ -106,15 +113,19 @@
 //!
 //! ## Serialization
 //!
-//! Viaduct currently supports serialization and deserialization of data using [`bytemuck`](https://docs.rs/bytemuck) (default), [`bincode`](https://docs.rs/bincode) or [`speedy`](https://docs.rs/speedy) at your choice, using the respective Cargo feature flags.
+//! Viaduct currently supports serialization and deserialization of data using [`bytemuck`](https://docs.rs/bytemuck) (default), [`bincode`](https://docs.rs/bincode), [`speedy`](https://docs.rs/speedy), [`postcard`](https://docs.rs/postcard), [`serde_json`](https://docs.rs/serde_json) or [`rkyv`](https://docs.rs/rkyv) at your choice, using the respective Cargo feature flags.
+//!
+//! The `json` feature is much slower than the others, but every message crosses the viaduct as human-readable JSON, which makes it handy for debugging.
 //!
 //! You can also manually implement the [`ViaductSerialize`] and [`ViaductDeserialize`] traits.
 //!
+//! Both processes must be compiled with the same serialization backend, which is checked when the viaduct is built (see [`backend_name`]).
+//!
 //! ## Initializing a viaduct
 //!
 //! A viaduct is started by calling [`ViaductParent::new`] as the parent process, which will spawn your child process.
 //!
-//! Your child process should then call [`ViaductChild::new`], [`ViaductChild::new_with_args_os`] or [`ViaductChild::new_with_args`] (see CAVEAT below) to bridge the connection between the parent and child.
+//! Your child process should then call [`ViaductChild::build`], [`ViaductChild::build_with_args_os`] or [`ViaductChild::build_with_args`] (see CAVEAT below) to bridge the connection between the parent and child.
 //!
 //! Then, you are ready to start...
 //!
@@ -126,13 +137,21 @@
 //!
 //! Requests/Responses are two-way messages, and are useful for sending requests to the other process and receiving data as a response.
 //!
-//! Requests will block any other thread trying to send requests and RPCs through the viaduct, until a response is received.
+//! Requests only block the thread that sent them until a response is received; other threads can carry on sending requests and RPCs through the viaduct in the meantime.
 //!
 //! ## CAVEAT: Don't use [`std::env::args_os`] or [`std::env::args`] in your child process!
 //!
 //! The child process should not use `args_os` or `args` to get its arguments, as these will contain data Viaduct needs to pass to the child process.
 //!
-//! Instead, use the argument iterator provided by [`ViaductChild::new_with_args_os`] or [`ViaductChild::new_with_args`] for `args_os` and `args` respectively.
+//! Instead, use the argument iterator provided by [`ViaductChild::build_with_args_os`] or [`ViaductChild::build_with_args`], or call [`viaduct::args_os`](args_os) or [`viaduct::args`](args) at any point after building the viaduct, for `args_os` and `args` respectively.
+//!
+//! Alternatively, use [`ViaductParent::pass_handles_in_env`] and [`ViaductChild::from_env`] to pass the handles in an environment variable instead, which leaves the child process' arguments alone.
+//!
+//! # Migrating from 0.4
+//!
+//! * [`ViaductParent::build`], [`ViaductChild::build`] and the other constructors return a [`Viaduct`] instead of a `(ViaductTx, ViaductRx)` tuple, as do the closures passed to [`split`]. Call [`Viaduct::split`] to take it apart, or convert it into the tuple with [`Into::into`].
+//! * [`ViaductEvent`], [`ViaductLazyEvent`] and [`ViaductMappedEvent`] have a new `Handle` variant for handles shared by the peer process, and are now `#[non_exhaustive]`, so matches on them need a wildcard arm.
+//! * [`ViaductEvent::dispatch`] returns an [`Option`], which is `None` if the event was a handle.
 
 #![deny(unsafe_op_in_unsafe_fn)]
 #![deny(missing_docs)]
@@ -142,34 +161,101 @@
 compile_error!("Unsupported platform");
 
 use interprocess::unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter};
-use parking_lot::{Condvar, Mutex};
+use parking_lot::Mutex;
 use std::{
 	ffi::{OsStr, OsString},
 	io::{Read, Write},
 	marker::PhantomData,
 	num::NonZeroU64,
-	process::{Child, Command},
-	sync::Arc,
+	process::{Child, Command, ExitStatus, Stdio},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, OnceLock,
+	},
+	time::{Duration, Instant},
 };
 
+mod macros;
+
 mod chan;
 pub use chan::*;
 
+mod error;
+pub use error::{CloseReason, TrySendError, ViaductError};
+
+mod options;
+use options::ViaductOptions;
+
+mod pool;
+pub use pool::{BufferPool, SimpleBufferPool};
+
 mod serde;
-pub use self::serde::{Never, ViaductDeserialize, ViaductSerialize};
+#[cfg(feature = "rkyv")]
+pub use self::serde::ViaductArchived;
+pub use self::serde::{backend_name, Never, RawBytes, ViaductDeserialize, ViaductSerialize};
+
+mod timing;
+pub use timing::Timestamp;
+#[cfg(feature = "timing")]
+pub use timing::ViaductTimings;
+
+mod metrics;
+use metrics::MetricsRecorder;
+pub use metrics::ViaductMetrics;
+
+mod rebuild;
+pub use rebuild::ViaductRebuilder;
+
+mod child;
+pub use child::ChildHandle;
+
+mod registry;
+
+#[cfg(feature = "tokio")]
+mod sink;
+#[cfg(feature = "tokio")]
+pub use sink::OutgoingMessage;
+
+mod capabilities;
+use capabilities::Capabilities;
+pub use capabilities::Capability;
 
 mod os;
 use os::RawPipe;
 
+mod affinity;
+
+#[cfg(unix)]
+mod limits;
+#[cfg(unix)]
+pub use limits::ResourceLimits;
+
+#[cfg(feature = "core_affinity")]
+pub use core_affinity;
+
 mod reaper;
-use reaper::{DroppablePipe, ReaperCallbackFn};
+use reaper::{DroppablePipe, ParentReaperCallbackFn, ReaperCallbackFn, ReaperPipe};
+
+#[cfg(windows)]
+mod named_pipe;
+
+#[cfg(unix)]
+mod tcp;
+
+mod handle;
 
 mod debugs;
 
 #[doc(hidden)]
 pub mod doctest;
 
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
 /// An event that was received over the viaduct.
+///
+/// New kinds of event may be added in future versions, so matching on this enum needs a wildcard arm. Unless the peer process shares handles (with `ViaductTx::send_handle` on Windows, or `ViaductTx::send_fd` on Unix), that arm is never reached.
+#[non_exhaustive]
 pub enum ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
@@ -192,18 +278,238 @@ where
 		/// Use [`ViaductRequestResponder::respond`] to respond to the request.
 		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
 	},
+
+	/// A handle was shared by the peer process with [`ViaductTx::send_handle`].
+	///
+	/// The handle is valid in this process, and is closed when dropped.
+	#[cfg(windows)]
+	Handle(std::os::windows::io::OwnedHandle),
+
+	/// A file descriptor was shared by the peer process with [`ViaductTx::send_fd`].
+	///
+	/// The file descriptor is valid in this process, and is closed when dropped.
+	#[cfg(unix)]
+	Handle(std::os::unix::io::OwnedFd),
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Hands this event to `rpc_fn` if it's an RPC, or to `request_fn` if it's a request.
+	///
+	/// This lets an event handler delegate to a separate closure for each kind of event.
+	///
+	/// Returns `None` if the event is a `ViaductEvent::Handle`, which neither closure can receive; the handle or file descriptor is closed. Match on the event yourself if the peer process shares handles or file descriptors.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run(|event| {
+	///     event.dispatch(
+	///         |rpc| println!("RPC received: {rpc:?}"),
+	///         |request, responder| {
+	///             println!("Request received: {request:?}");
+	///             responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///         },
+	///     );
+	/// }).unwrap();
+	/// ```
+	#[inline]
+	pub fn dispatch<R>(
+		self,
+		rpc_fn: impl FnOnce(RpcRx) -> R,
+		request_fn: impl FnOnce(RequestRx, ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>) -> R,
+	) -> Option<R> {
+		match self {
+			Self::Rpc(rpc) => Some(rpc_fn(rpc)),
+			Self::Request { request, responder } => Some(request_fn(request, responder)),
+
+			Self::Handle(_) => {
+				#[cfg(feature = "tracing")]
+				tracing::warn!("viaduct received a handle, which can't be dispatched");
+
+				None
+			}
+		}
+	}
+
+	/// Combines a closure for RPCs and a closure for requests into a single event handler, which can be passed to [`ViaductRx::run`] and friends.
+	///
+	/// This is the reverse of [`ViaductEvent::dispatch`], and likewise closes any handle that is received.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, ViaductEvent, doctest::*};
+	/// # let rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// rx.run(ViaductEvent::handler(
+	///     |rpc| println!("RPC received: {rpc:?}"),
+	///     |request, responder| {
+	///         println!("Request received: {request:?}");
+	///         responder.respond(Ok::<_, FrontflipError>(())).unwrap();
+	///     },
+	/// )).unwrap();
+	/// ```
+	#[inline]
+	pub fn handler(
+		mut rpc_fn: impl FnMut(RpcRx),
+		mut request_fn: impl FnMut(RequestRx, ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>),
+	) -> impl FnMut(Self) {
+		move |event| {
+			event.dispatch(&mut rpc_fn, &mut request_fn);
+		}
+	}
+}
+
+/// An event that was received over the viaduct, with its RPC or request still serialized.
+///
+/// See [`ViaductRx::run_lazy`]. Like [`ViaductEvent`], this enum is non-exhaustive.
+#[non_exhaustive]
+pub enum ViaductLazyEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// An RPC was received.
+	///
+	/// Use [`LazyMessage::decode`] to deserialize it.
+	Rpc(LazyMessage<RpcRx>),
+
+	/// A request was received.
+	///
+	/// Use [`LazyMessage::decode`] to deserialize it, and [`ViaductRequestResponder::respond`] to respond to it.
+	Request {
+		/// The request that was received.
+		request: LazyMessage<RequestRx>,
+
+		/// The responder that can be used to respond to the request.
+		///
+		/// Use [`ViaductRequestResponder::respond`] to respond to the request.
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	},
+
+	/// A handle was shared by the peer process with [`ViaductTx::send_handle`].
+	///
+	/// The handle is valid in this process, and is closed when dropped.
+	#[cfg(windows)]
+	Handle(std::os::windows::io::OwnedHandle),
+
+	/// A file descriptor was shared by the peer process with [`ViaductTx::send_fd`].
+	///
+	/// The file descriptor is valid in this process, and is closed when dropped.
+	#[cfg(unix)]
+	Handle(std::os::unix::io::OwnedFd),
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductLazyEvent<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Deserializes the RPC or request, turning this into a [`ViaductEvent`].
+	///
+	/// # Panics
+	///
+	/// This function will panic if the RPC or request fails to deserialize.
+	#[inline]
+	pub fn decode(self) -> ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx> {
+		match self {
+			Self::Rpc(rpc) => ViaductEvent::Rpc(rpc.decode()),
+			Self::Request { request, responder } => ViaductEvent::Request {
+				request: request.decode(),
+				responder,
+			},
+
+			Self::Handle(handle) => ViaductEvent::Handle(handle),
+		}
+	}
+}
+
+/// An event that was received over the viaduct, whose RPC or request may have been received into a destination of the event handler's choosing.
+///
+/// See [`ViaductRx::run_mapped`]. Like [`ViaductEvent`], this enum is non-exhaustive.
+#[non_exhaustive]
+pub enum ViaductMappedEvent<M, RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// An RPC was received.
+	Rpc(MappedPayload<M, RpcRx>),
+
+	/// A request was received.
+	///
+	/// Use [`ViaductRequestResponder::respond`] to respond to it.
+	Request {
+		/// The request that was received.
+		request: MappedPayload<M, RequestRx>,
+
+		/// The responder that can be used to respond to the request.
+		///
+		/// Use [`ViaductRequestResponder::respond`] to respond to the request.
+		responder: ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+	},
+
+	/// A handle was shared by the peer process with [`ViaductTx::send_handle`].
+	///
+	/// The handle is valid in this process, and is closed when dropped.
+	#[cfg(windows)]
+	Handle(std::os::windows::io::OwnedHandle),
+
+	/// A file descriptor was shared by the peer process with [`ViaductTx::send_fd`].
+	///
+	/// The file descriptor is valid in this process, and is closed when dropped.
+	#[cfg(unix)]
+	Handle(std::os::unix::io::OwnedFd),
 }
 
-fn verify_channel<R, F: FnOnce() -> Result<R, std::io::Error>>(
+/// Reads the peer's side of the handshake, failing with an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) if it doesn't arrive before the deadline.
+struct HandshakeReader<'a> {
+	pipe: &'a mut UnnamedPipeReader,
+	deadline: Option<Instant>,
+}
+impl Read for HandshakeReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		if let Some(deadline) = self.deadline {
+			if !os::wait_readable_timeout(self.pipe, deadline.saturating_duration_since(Instant::now()))? {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::TimedOut,
+					"Timed out waiting for the peer process to perform the handshake",
+				));
+			}
+		}
+		self.pipe.read(buf)
+	}
+}
+
+/// Performs the handshake, returning the peer's capabilities.
+fn verify_channel(
 	tx: &mut UnnamedPipeWriter,
 	rx: &mut UnnamedPipeReader,
-	ready: F,
-) -> Result<R, std::io::Error> {
+	local_capabilities: Capabilities,
+	options: &mut ViaductOptions,
+	deadline: Option<Instant>,
+) -> Result<Capabilities, std::io::Error> {
+	let rx = &mut HandshakeReader { pipe: rx, deadline };
+
 	tx.write_all(chan::HELLO)?;
+	tx.write_all(&u16::to_ne_bytes(chan::PROTOCOL_VERSION))?;
 	tx.write_all(&u16::to_ne_bytes(0x0102_u16))?;
 	tx.write_all(&u128::to_ne_bytes(core::mem::size_of::<usize>() as _))?;
-
-	let ready = ready()?;
+	tx.write_all(&u64::to_ne_bytes(local_capabilities.bits()))?;
+	tx.write_all(&u64::to_ne_bytes(options.rpc_credits.unwrap_or(0)))?;
+	tx.write_all(&[backend_name().len() as u8])?;
+	tx.write_all(backend_name().as_bytes())?;
 
 	let mut hello = [0u8; chan::HELLO.len()];
 	rx.read_exact(&mut hello)?;
@@ -214,6 +520,11 @@ fn verify_channel<R, F: FnOnce() -> Result<R, std::io::Error>>(
 		));
 	}
 
+	let mut version = [0u8; core::mem::size_of::<u16>()];
+	rx.read_exact(&mut version)?;
+	let version = u16::from_ne_bytes(version);
+
+	// Check the endianness first, as the version would be byte-swapped if it differs
 	let mut endianness = [0u8; core::mem::size_of::<u16>()];
 	rx.read_exact(&mut endianness)?;
 	let endianness = u16::from_ne_bytes(endianness);
@@ -224,6 +535,14 @@ fn verify_channel<R, F: FnOnce() -> Result<R, std::io::Error>>(
 		));
 	}
 
+	if version != chan::PROTOCOL_VERSION {
+		return Err(ViaductError::ProtocolMismatch {
+			local: chan::PROTOCOL_VERSION,
+			peer: version,
+		}
+		.into());
+	}
+
 	let mut usize_size = [0u8; core::mem::size_of::<u128>()];
 	rx.read_exact(&mut usize_size)?;
 	if u128::from_ne_bytes(usize_size) != core::mem::size_of::<usize>() as u128 {
@@ -233,28 +552,363 @@ fn verify_channel<R, F: FnOnce() -> Result<R, std::io::Error>>(
 		));
 	}
 
-	Ok(ready)
+	let mut capabilities = [0u8; core::mem::size_of::<u64>()];
+	rx.read_exact(&mut capabilities)?;
+	let capabilities = Capabilities::from_bits(u64::from_ne_bytes(capabilities));
+
+	// Zero means the peer doesn't limit the RPCs we send it
+	let mut rpc_credits = [0u8; core::mem::size_of::<u64>()];
+	rx.read_exact(&mut rpc_credits)?;
+	options.peer_rpc_credits = Some(u64::from_ne_bytes(rpc_credits)).filter(|credits| *credits != 0);
+
+	let mut backend = [0u8; u8::MAX as usize];
+	let backend = {
+		let mut len = [0u8];
+		rx.read_exact(&mut len)?;
+		let backend = &mut backend[..len[0] as usize];
+		rx.read_exact(backend)?;
+		String::from_utf8_lossy(backend)
+	};
+	if backend != backend_name() {
+		return Err(ViaductError::BackendMismatch {
+			local: backend_name(),
+			peer: backend.into_owned(),
+		}
+		.into());
+	}
+
+	if let Some(required) = options.required_capabilities.iter().find(|required| !capabilities.contains(*required)) {
+		return Err(ViaductError::MissingCapability {
+			required,
+			peer_supported: capabilities.iter().collect(),
+		}
+		.into());
+	}
+
+	Ok(capabilities)
 }
 
-fn channel<RpcTx, RequestTx, RpcRx, RequestRx>(tx: UnnamedPipeWriter, rx: UnnamedPipeReader) -> Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>
+fn channel<RpcTx, RequestTx, RpcRx, RequestRx>(
+	tx: PipeSink,
+	rx: PipeReader,
+	reaper_pipe: Option<ReaperPipe>,
+	peer_capabilities: Capabilities,
+	mut options: ViaductOptions,
+) -> Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>
 where
 	RpcTx: ViaductSerialize,
 	RequestTx: ViaductSerialize,
 	RpcRx: ViaductDeserialize,
 	RequestRx: ViaductDeserialize,
 {
+	#[cfg(feature = "compression")]
+	if !peer_capabilities.contains(Capability::Compression) {
+		// The peer wouldn't be able to decompress anything we send
+		options.compression_threshold = None;
+	}
+
+	let metrics = Arc::<MetricsRecorder>::default();
+	let credits = Arc::new(RpcCredits::new(options.rpc_credits, options.peer_rpc_credits));
 	let tx = ViaductTx(Arc::new(ViaductTxInner {
-		response_condvar: Condvar::new(),
-		response: Mutex::new(ViaductResponseState::default()),
-		state: Mutex::new(ViaductTxState::new(tx)),
+		pending: Default::default(),
+		pending_closed: Default::default(),
+		close_reason: Default::default(),
+		timings: Default::default(),
+		metrics: metrics.clone(),
+		peer_capabilities,
+		responders: options.track_responders.then(Default::default),
+		responder_limit: options.max_outstanding_responders.map(ResponderLimit::new),
+		rpc_window: RpcWindow::new(options.rpc_window),
+		rpc_credits: credits.clone(),
+		on_request_complete: Default::default(),
+		#[cfg(windows)]
+		peer_process: options.peer_process.take(),
+		#[cfg(unix)]
+		handle_socket: options.handle_socket.take(),
+		child_handle: options.child_handle.take(),
+		state: Mutex::new(ViaductTxState::new(tx, &mut options, metrics, credits)),
+		_reaper_pipe: reaper_pipe,
 	}));
 	let rx = ViaductRx {
 		buf: Vec::new(),
 		tx: tx.clone(),
 		rx,
+		reassembly: Reassembly::new(&options),
+		pool: options.buffer_pool.clone(),
+		peeked: None,
+		batch: Default::default(),
+		resync: options.resync_markers,
+		max_message_size: options.max_message_size,
+		marker_consumed: false,
+		nonblocking: false,
+		shutdown: None,
+		timestamps: options.timestamps,
+		timestamp: None,
+		on_raw_recv: options.on_raw_recv.take(),
+		on_frame: options.on_frame.take(),
+		registry: Default::default(),
 		_phantom: Default::default(),
 	};
-	(tx, rx)
+	Viaduct::join(tx, rx)
+}
+
+/// The parent's side of the data channel, before the child process has been spawned.
+enum DataPipes {
+	Unnamed {
+		tx: UnnamedPipeWriter,
+		rx: UnnamedPipeReader,
+		child_pipes: (UnnamedPipeWriter, UnnamedPipeReader),
+	},
+
+	#[cfg(windows)]
+	Named(Box<named_pipe::NamedPipes>),
+}
+
+/// How the child process should open its side of a data pipe, as passed in its arguments.
+enum PipeToken {
+	Handle(NonZeroU64),
+
+	#[cfg(windows)]
+	Named(String),
+}
+impl PipeToken {
+	fn parse(arg: &OsStr) -> Option<Self> {
+		let arg = arg.to_str()?;
+		match arg.parse::<NonZeroU64>() {
+			Ok(handle) => Some(Self::Handle(handle)),
+
+			#[cfg(windows)]
+			Err(_) => Some(Self::Named(arg.to_owned())),
+
+			#[cfg(not(windows))]
+			Err(_) => None,
+		}
+	}
+}
+
+/// The handles the parent process passes to the child process.
+struct PipeHandles {
+	parent_w: PipeToken,
+	child_r: PipeToken,
+	reaper_tx: NonZeroU64,
+	reaper_rx: NonZeroU64,
+
+	/// The child's end of the socket that file descriptors are shared over.
+	#[cfg(unix)]
+	handle_socket: Option<NonZeroU64>,
+}
+
+/// Parses the pipe handles that follow the handshake marker argument.
+///
+/// The handles are prefixed with how many of them there are, so that every one of them is stripped from the arguments even if the parent sent handles this version doesn't know about, which are ignored.
+fn parse_pipe_args<S: AsRef<OsStr>>(args: &mut impl Iterator<Item = S>) -> Result<PipeHandles, std::io::Error> {
+	let invalid = || std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not parse pipe handles");
+
+	let count = args
+		.next()
+		.and_then(|count| count.as_ref().to_str()?.parse::<usize>().ok())
+		.ok_or_else(invalid)?;
+
+	let handles = args.take(count).collect::<Vec<_>>();
+	if handles.len() != count {
+		return Err(invalid());
+	}
+
+	let [parent_w, child_r, reaper_tx, reaper_rx, ..] = handles.as_slice() else {
+		return Err(invalid());
+	};
+	let parse_handle = |handle: &S| {
+		handle
+			.as_ref()
+			.to_str()
+			.and_then(|handle| handle.parse::<NonZeroU64>().ok())
+			.ok_or_else(invalid)
+	};
+	Ok(PipeHandles {
+		parent_w: PipeToken::parse(parent_w.as_ref()).ok_or_else(invalid)?,
+		child_r: PipeToken::parse(child_r.as_ref()).ok_or_else(invalid)?,
+		reaper_tx: parse_handle(reaper_tx)?,
+		reaper_rx: parse_handle(reaper_rx)?,
+		#[cfg(unix)]
+		handle_socket: handles.get(4).map(parse_handle).transpose()?,
+	})
+}
+
+/// The argument that precedes the pipe handles in the child process' arguments, unless the parent process used [`ViaductParent::with_handshake_marker`].
+const DEFAULT_HANDSHAKE_MARKER: &str = "PIPER_START";
+
+/// The environment variable a custom handshake marker is passed to the child process in, when [`ViaductParent::with_handshake_marker`] is used.
+const HANDSHAKE_MARKER_ENV: &str = "VIADUCT_HANDSHAKE_MARKER";
+
+/// Returns the argument that precedes the pipe handles in this process' arguments.
+fn handshake_marker() -> OsString {
+	std::env::var_os(HANDSHAKE_MARKER_ENV).unwrap_or_else(|| OsString::from(DEFAULT_HANDSHAKE_MARKER))
+}
+
+/// The process arguments, with the arguments Viaduct uses to pass pipe handles removed.
+static ARGS: OnceLock<Vec<OsString>> = OnceLock::new();
+
+/// Whether [`ViaductChild`] has taken the pipe handles passed to this process, which can only happen once.
+static HANDLES_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Finds and parses the pipe handles in the process arguments, stashing the rest of the arguments for [`args_os`] and [`args`].
+fn strip_args() -> Result<PipeHandles, std::io::Error> {
+	let mut args = std::env::args_os();
+	let mut stripped = Vec::with_capacity(1);
+
+	// The marker is removed from the environment so that it isn't inherited by this process' own child processes
+	let sig = handshake_marker();
+	if std::env::var_os(HANDSHAKE_MARKER_ENV).is_some() {
+		std::env::remove_var(HANDSHAKE_MARKER_ENV);
+	}
+
+	let mut sig_found = false;
+	for arg in args.by_ref() {
+		if arg == sig {
+			sig_found = true;
+			break;
+		}
+		stripped.push(arg);
+	}
+	if !sig_found {
+		return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not find pipe handles"));
+	}
+
+	let pipes = parse_pipe_args(&mut args)?;
+
+	stripped.extend(args);
+	ARGS.get_or_init(|| stripped);
+
+	Ok(pipes)
+}
+
+/// The environment variable the pipe handles are passed to the child process in, when [`ViaductParent::pass_handles_in_env`] is used.
+const PIPES_ENV: &str = "VIADUCT_PIPES";
+
+/// Finds and parses the pipe handles in the [`PIPES_ENV`] environment variable, removing it so that it isn't inherited by this process' own child processes.
+fn take_env_handles() -> Result<PipeHandles, std::io::Error> {
+	let handles = std::env::var_os(PIPES_ENV).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not find pipe handles"))?;
+	std::env::remove_var(PIPES_ENV);
+
+	let handles = handles
+		.into_string()
+		.map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not parse pipe handles"))?;
+
+	parse_pipe_args(&mut handles.split_whitespace())
+}
+
+/// Takes the pipe handles passed to this process, failing with [`ViaductError::AlreadyBuilt`] if they have already been taken.
+fn take_handles(from_env: bool) -> Result<PipeHandles, std::io::Error> {
+	if HANDLES_TAKEN.load(Ordering::Acquire) {
+		return Err(ViaductError::AlreadyBuilt.into());
+	}
+
+	let handles = if from_env { take_env_handles()? } else { strip_args()? };
+
+	// The handles are still in our arguments, but the first viaduct owns them now
+	if HANDLES_TAKEN.swap(true, Ordering::AcqRel) {
+		return Err(ViaductError::AlreadyBuilt.into());
+	}
+
+	Ok(handles)
+}
+
+/// Returns the arguments this process was started with, like [`std::env::args_os`], but with the arguments Viaduct uses to pass pipe handles to the child process removed.
+///
+/// Until a viaduct has been built in this process with [`ViaductChild`], this is the same as [`std::env::args_os`].
+pub fn args_os() -> impl Iterator<Item = OsString> {
+	match ARGS.get() {
+		Some(args) => args.clone().into_iter(),
+		None => std::env::args_os().collect::<Vec<_>>().into_iter(),
+	}
+}
+
+/// Returns the arguments this process was started with, like [`std::env::args`], but with the arguments Viaduct uses to pass pipe handles to the child process removed.
+///
+/// Until a viaduct has been built in this process with [`ViaductChild`], this is the same as [`std::env::args`].
+///
+/// # Panics
+///
+/// The returned iterator will panic if any of the arguments are not valid Unicode.
+pub fn args() -> impl Iterator<Item = String> {
+	args_os().map(|arg| arg.into_string().expect("Program argument was not valid Unicode"))
+}
+
+/// Gives a child process that hung up during the handshake a moment to exit, returning its exit status if it does.
+fn exited_during_handshake(child: &mut Child) -> Option<ExitStatus> {
+	let deadline = Instant::now() + Duration::from_millis(500);
+	loop {
+		match child.try_wait() {
+			Ok(Some(status)) => return Some(status),
+			Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+			_ => return None,
+		}
+	}
+}
+
+/// Runs `child_fn` if this process is a child process spawned by Viaduct, or spawns this executable as a child process and runs `parent_fn` otherwise.
+///
+/// This is the usual way of using Viaduct from a single executable that is both the parent and the child process, without having to spawn [`std::env::current_exe`] and work out which side of the viaduct you're on yourself. Whether this process is the child is decided by the arguments Viaduct passes to child processes, so if building the child's side of the viaduct fails, an error is returned rather than another child process being spawned.
+///
+/// The types are given from the parent process' point of view; the child process' viaduct has them the other way round. To configure either side of the viaduct, use [`ViaductParent`] and [`ViaductChild`] directly.
+///
+/// # Safety
+///
+/// See [`ViaductChild::build`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use viaduct::{ViaductEvent, doctest::*};
+/// unsafe {
+///     viaduct::split::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest, _>(
+///         |viaduct, mut child| {
+///             let (tx, _rx) = viaduct.split();
+///             tx.rpc(ExampleRpc::Cow).unwrap();
+///             child.wait().unwrap();
+///         },
+///         |viaduct| {
+///             let (_tx, rx) = viaduct.split();
+///             rx.run(|event| {
+///                 if let ViaductEvent::Rpc(rpc) = event {
+///                     println!("RPC received: {rpc:?}");
+///                     std::process::exit(0);
+///                 }
+///             })
+///             .unwrap();
+///         },
+///     )
+/// }
+/// .unwrap();
+/// ```
+#[allow(clippy::type_complexity)]
+pub unsafe fn split<RpcTx, RequestTx, RpcRx, RequestRx, R>(
+	parent_fn: impl FnOnce(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child) -> R,
+	child_fn: impl FnOnce(Viaduct<RpcRx, RequestRx, RpcTx, RequestTx>) -> R,
+) -> Result<R, std::io::Error>
+where
+	RpcTx: ViaductSerialize + ViaductDeserialize,
+	RequestTx: ViaductSerialize + ViaductDeserialize,
+	RpcRx: ViaductSerialize + ViaductDeserialize,
+	RequestRx: ViaductSerialize + ViaductDeserialize,
+{
+	if std::env::var_os(PIPES_ENV).is_some() {
+		let viaduct = unsafe { ViaductChild::new().from_env().build() }?;
+		Ok(child_fn(viaduct))
+	} else if std::env::args_os().any(|arg| arg == handshake_marker()) {
+		let viaduct = unsafe { ViaductChild::new().build() }?;
+		Ok(child_fn(viaduct))
+	} else {
+		let (viaduct, child) = ViaductParent::new(Command::new(std::env::current_exe()?))?.build()?;
+		Ok(parent_fn(viaduct, child))
+	}
+}
+
+fn is_transient_spawn_error(err: &std::io::Error) -> bool {
+	matches!(
+		err.kind(),
+		std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted | std::io::ErrorKind::OutOfMemory
+	)
 }
 
 /// Interface for creating a viaduct on the **PARENT** process.
@@ -274,11 +928,17 @@ where
 	RequestRx: ViaductDeserialize,
 {
 	command: Command,
-	tx: ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>,
-	rx: ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>,
-	_reaper_rx: DroppablePipe<UnnamedPipeReader>,
+	data_pipes: DataPipes,
+	reaper_rx: DroppablePipe<UnnamedPipeReader>,
 	reaper_tx: DroppablePipe<UnnamedPipeWriter>,
-	with_reaper: Option<ReaperCallbackFn>,
+	with_reaper: Option<ParentReaperCallbackFn>,
+	spawn_retries: (u32, Duration),
+	handles_in_env: bool,
+	handshake_marker: Option<String>,
+	#[cfg(unix)]
+	handle_sockets: (std::os::unix::net::UnixDatagram, std::os::unix::net::UnixDatagram),
+	options: ViaductOptions,
+	_phantom: PhantomData<(RpcTx, RequestTx, RpcRx, RequestRx)>,
 }
 impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductParent<RpcTx, RequestTx, RpcRx, RequestRx>
 where
@@ -294,7 +954,7 @@ where
 	/// This function will panic if the [`Command`](std::process::Command) has arguments set.
 	///
 	/// You can set command arguments using the [`ViaductParent::arg`] and [`ViaductParent::args`] methods.
-	pub fn new(mut command: Command) -> Result<Self, std::io::Error> {
+	pub fn new(command: Command) -> Result<Self, std::io::Error> {
 		if command.get_args().next().is_some() {
 			panic!("Command must not have any arguments - to add arguments to your command please use the `arg` method and `args` method of this builder");
 		}
@@ -302,29 +962,47 @@ where
 		let (child_w, child_r) = interprocess::unnamed_pipe::pipe()?;
 		let (parent_w, parent_r) = interprocess::unnamed_pipe::pipe()?;
 
+		// The child process mustn't inherit our ends of the pipes, otherwise it would never see the end of the stream when we close them
+		os::disinherit(&child_w)?;
+		os::disinherit(&parent_r)?;
+
 		let (reaper_tx, reaper_rx) = interprocess::unnamed_pipe::pipe()?;
 		let (reaper_tx, reaper_rx) = (DroppablePipe::new(reaper_tx), DroppablePipe::new(reaper_rx));
 
-		command.arg("PIPER_START");
-		command.args(&[
-			(parent_w.raw() as usize as u64).to_string(),
-			(child_r.raw() as usize as u64).to_string(),
-			(reaper_tx.as_raw() as usize as u64).to_string(),
-			(reaper_rx.as_raw() as usize as u64).to_string(),
-		]);
-
-		let (tx, rx) = channel(child_w, parent_r);
-
 		Ok(Self {
 			command,
-			tx,
-			rx,
+			data_pipes: DataPipes::Unnamed {
+				tx: child_w,
+				rx: parent_r,
+				child_pipes: (parent_w, child_r),
+			},
 			with_reaper: None,
+			spawn_retries: (0, Duration::ZERO),
+			handles_in_env: false,
+			handshake_marker: None,
+			#[cfg(unix)]
+			handle_sockets: handle::socket_pair()?,
+			options: ViaductOptions::default(),
 			reaper_tx,
-			_reaper_rx: reaper_rx,
+			reaper_rx,
+			_phantom: Default::default(),
 		})
 	}
 
+	#[cfg(windows)]
+	/// Initializes the viaduct in the parent process, using a pair of named pipes instead of unnamed pipes.
+	///
+	/// The child process connects to the pipes by name rather than inheriting their handles, so this works even when the child process is launched through an intermediate process (such as a launcher or `runas`) that doesn't pass inherited handles on.
+	///
+	/// This is shorthand for calling [`ViaductParent::windows_named_pipe`] on a new builder.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the [`Command`](std::process::Command) has arguments set.
+	pub fn new_named(command: Command) -> Result<Self, std::io::Error> {
+		Self::new(command)?.windows_named_pipe()
+	}
+
 	/// Adds an argument to the [`Command`](std::process::Command)
 	pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
 		self.command.arg(arg.as_ref());
@@ -341,43 +1019,682 @@ where
 		self
 	}
 
-	#[inline]
-	/// Whether to spawn a reaper thread or not.
-	///
-	/// A reaper thread will occasionally check whether the child process has been killed and call your `callback` if it has.
+	/// Sets an environment variable for the child process, like [`Command::env`](std::process::Command::env).
+	pub fn env<K, V>(mut self, key: K, val: V) -> Self
+	where
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>,
+	{
+		self.command.env(key, val);
+		self
+	}
+
+	/// Sets several environment variables for the child process, like [`Command::envs`](std::process::Command::envs).
+	pub fn envs<I, K, V>(mut self, vars: I) -> Self
+	where
+		I: IntoIterator<Item = (K, V)>,
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>,
+	{
+		self.command.envs(vars);
+		self
+	}
+
+	/// Stops the child process from inheriting an environment variable from this process, like [`Command::env_remove`](std::process::Command::env_remove).
+	pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+		self.command.env_remove(key);
+		self
+	}
+
+	/// Stops the child process from inheriting any environment variables from this process, like [`Command::env_clear`](std::process::Command::env_clear).
 	///
-	/// This allows you to gracefully handle the child process being killed.
-	pub fn with_reaper<F: FnOnce() + Send + 'static>(mut self, callback: F) -> Self {
-		self.with_reaper = Some(Box::new(callback));
+	/// Only the variables set with [`ViaductParent::env`] and [`ViaductParent::envs`] afterwards are passed to the child process. The variables Viaduct uses to pass the pipe handles to the child process, with [`ViaductParent::pass_handles_in_env`] or [`ViaductParent::with_handshake_marker`], are still passed, as they are added when the viaduct is built.
+	pub fn env_clear(mut self) -> Self {
+		self.command.env_clear();
 		self
 	}
 
-	/// Spawns the child process and returns it along with a [`Viaduct`](crate::Viaduct).
-	#[allow(clippy::type_complexity)]
-	pub fn build(mut self) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child), std::io::Error> {
+	/// Sets the child process' `argv[0]`, which is the path of the executable by default.
+	///
+	/// This lets a program that spawns itself as the child process give it a recognisable name (for example, `my-app-worker`) in `ps` and similar tools, and lets the child process tell what it is by looking at its first argument. Viaduct's own arguments are still added after the arguments set with [`ViaductParent::arg`] and [`ViaductParent::args`], and are removed by [`args`](crate::args) and [`args_os`](crate::args_os) as usual.
+	///
+	/// Only supported on Unix.
+	#[cfg(unix)]
+	pub fn arg0<S: AsRef<OsStr>>(mut self, arg0: S) -> Self {
+		use std::os::unix::process::CommandExt;
+
+		self.command.arg0(arg0);
+		self
+	}
+
+	/// Passes the pipe handles to the child process in the `VIADUCT_PIPES` environment variable, instead of appending them to its arguments.
+	///
+	/// This leaves the child process' arguments exactly as they were given to [`ViaductParent::arg`] and [`ViaductParent::args`], which is useful if the child process parses its arguments with a library that would choke on Viaduct's, or can't use [`args`](crate::args) and [`args_os`](crate::args_os).
+	///
+	/// The child process must build its side of the viaduct with [`ViaductChild::from_env`].
+	pub fn pass_handles_in_env(mut self) -> Self {
+		self.handles_in_env = true;
+		self
+	}
+
+	/// Sets the argument that precedes the pipe handles in the child process' arguments, which defaults to `PIPER_START`.
+	///
+	/// The child process finds its pipe handles by scanning its arguments for this marker, so change it if `PIPER_START` could turn up among the arguments you pass to the child process yourself. The marker is passed to the child process in the `VIADUCT_HANDSHAKE_MARKER` environment variable, which is removed once the child process has built its side of the viaduct.
+	///
+	/// Has no effect with [`ViaductParent::pass_handles_in_env`], which leaves the child process' arguments alone.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `marker` is empty.
+	pub fn with_handshake_marker(mut self, marker: &str) -> Self {
+		assert!(!marker.is_empty(), "Handshake marker must not be empty");
+		self.handshake_marker = Some(marker.to_owned());
+		self
+	}
+
+	/// Sets the child process' standard input (stdin) handle.
+	///
+	/// If this is set to [`Stdio::piped()`](std::process::Stdio::piped), the [`ChildStdin`](std::process::ChildStdin) can be taken from the [`Child`](std::process::Child) returned by [`ViaductParent::build`], allowing you to stream data to the child alongside the viaduct.
+	pub fn stdin<T: Into<Stdio>>(mut self, cfg: T) -> Self {
+		self.command.stdin(cfg);
+		self
+	}
+
+	/// Sets the child process' standard output (stdout) handle.
+	pub fn stdout<T: Into<Stdio>>(mut self, cfg: T) -> Self {
+		self.command.stdout(cfg);
+		self
+	}
+
+	/// Sets the child process' standard error (stderr) handle.
+	pub fn stderr<T: Into<Stdio>>(mut self, cfg: T) -> Self {
+		self.command.stderr(cfg);
+		self
+	}
+
+	#[inline]
+	/// Whether to spawn a reaper thread or not.
+	///
+	/// A reaper thread will occasionally check whether the child process has been killed (or has dropped its side of the viaduct) and call your `callback` if it has.
+	///
+	/// This allows you to gracefully handle the child process being killed. The callback is passed the child process' exit status, so you can tell a crash apart from a clean exit. It is `None` if the child process didn't exit within half a second of dropping its side of the viaduct, or if its exit status couldn't be found out:
+	///
+	/// * On Unix, the exit status is read without reaping the child process, so you can still wait on the [`Child`] as usual. However, if the child process has already been reaped by the time the reaper thread gets to it, for example by [`Child::try_wait`] on another thread or because `SIGCHLD` is ignored, its exit status is gone.
+	/// * On Windows, the exit status is read from a handle to the child process, so it can always be found out once the child process has exited, whether or not the [`Child`] has been waited on.
+	///
+	/// If this process is always running the event loop, you may not need a reaper thread: the event loop returns a [`ViaductError::PeerGone`] error when the child process goes away.
+	pub fn with_reaper<F: FnOnce(Option<ExitStatus>) + Send + 'static>(mut self, callback: F) -> Self {
+		self.with_reaper = Some(Box::new(callback));
+		self
+	}
+
+	#[inline]
+	/// Sets how often the reaper thread (see [`ViaductParent::with_reaper`]) checks whether the child process is still alive, which defaults to 5 seconds.
+	///
+	/// This bounds how long it can take for the reaper callback to be called after the child process goes away. Intervals shorter than 10 milliseconds are rounded up to 10 milliseconds, so that the reaper thread doesn't spin.
+	pub fn with_reaper_interval(mut self, interval: Duration) -> Self {
+		self.options.reaper_interval = interval.max(reaper::MIN_REAPER_INTERVAL);
+		self
+	}
+
+	#[cfg(feature = "tokio")]
+	/// Spawns a reaper thread, just like [`with_reaper`](Self::with_reaper), but returns a [`oneshot::Receiver`](tokio::sync::oneshot::Receiver) that resolves with the child process' exit status when it dies instead of calling a callback.
+	///
+	/// This lets you `select!` on the child process dying alongside the rest of your async work.
+	///
+	/// Requires the `tokio` feature. Replaces any callback previously passed to [`with_reaper`](Self::with_reaper).
+	pub fn reaper_future(self) -> (Self, tokio::sync::oneshot::Receiver<Option<ExitStatus>>) {
+		let (reaped_tx, reaped_rx) = tokio::sync::oneshot::channel();
+		let this = self.with_reaper(move |status| {
+			reaped_tx.send(status).ok();
+		});
+		(this, reaped_rx)
+	}
+
+	#[inline]
+	/// Splits packets larger than `max_fragment_size` bytes into fragments when sending them to the child process.
+	///
+	/// Other threads can send their own packets in between each fragment, so that sending a large packet doesn't hold up the rest of the viaduct.
+	///
+	/// By default, packets are never fragmented.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `max_fragment_size` is zero.
+	pub fn max_fragment_size(mut self, max_fragment_size: usize) -> Self {
+		assert_ne!(max_fragment_size, 0, "Maximum fragment size must be greater than zero");
+		self.options.max_fragment_size = Some(max_fragment_size);
+		self
+	}
+
+	#[inline]
+	/// Refuses to send RPCs, requests and responses to the child process that serialize to more than `max_send_size` bytes.
+	///
+	/// Oversized messages aren't written at all; instead, a [`ViaductError::MessageTooLarge`] error is returned, so a message the child process would reject is caught where it's sent. If a response is too large, the requester receives `None` instead.
+	///
+	/// By default, there is no limit.
+	pub fn max_send_size(mut self, max_send_size: usize) -> Self {
+		self.options.max_send_size = Some(max_send_size);
+		self
+	}
+
+	#[inline]
+	/// Refuses to receive RPCs, requests and responses from the child process that are larger than `max_message_size` bytes, or `None` for no limit.
+	///
+	/// Each message is preceded by its length, which is checked against this limit before anything is allocated for it, so a corrupt or hostile child process can't make us run out of memory by claiming to send a huge message. Instead, the event loop returns a [`ViaductError::ReceiveLimit`] error. Compressed messages are checked against the size they decompress to.
+	///
+	/// Defaults to 1 GiB.
+	pub fn max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+		self.options.max_message_size = max_message_size;
+		self
+	}
+
+	#[cfg(feature = "compression")]
+	#[inline]
+	/// Compresses the payloads of RPCs, requests and responses sent to the child process that serialize to at least `threshold` bytes, using LZ4.
+	///
+	/// Small payloads aren't worth compressing, so they are always sent as they are, as are payloads that don't get any smaller when compressed. Whether each packet was compressed is recorded in the packet itself, so the child process decompresses exactly what needs decompressing; use [`ViaductTx::rpc_uncompressed`] to skip compression for an RPC that is already compressed, such as an image.
+	///
+	/// Only takes effect if the child process supports [`Capability::Compression`], i.e. it was also built with the `compression` feature; otherwise, nothing is compressed.
+	///
+	/// Requires the `compression` feature. By default, nothing is compressed.
+	pub fn compression_threshold(mut self, threshold: usize) -> Self {
+		self.options.compression_threshold = Some(threshold);
+		self
+	}
+
+	#[inline]
+	/// Sets the maximum number of bytes of fragmented packets from the child process that can be held in memory while they are being reassembled.
+	///
+	/// If the child process exceeds this limit, the event loop will return a [`ViaductError::ReassemblyLimit`] error.
+	///
+	/// Defaults to 1 GiB.
+	pub fn max_reassembly_bytes(mut self, max_reassembly_bytes: usize) -> Self {
+		self.options.max_reassembly_bytes = max_reassembly_bytes;
+		self
+	}
+
+	#[inline]
+	/// Sets the maximum number of fragmented packets from the child process that can be in the process of being reassembled at once.
+	///
+	/// If the child process exceeds this limit, the event loop will return a [`ViaductError::ReassemblyLimit`] error.
+	///
+	/// Defaults to 64.
+	pub fn max_concurrent_fragments(mut self, max_concurrent_fragments: usize) -> Self {
+		self.options.max_concurrent_fragments = max_concurrent_fragments;
+		self
+	}
+
+	#[inline]
+	/// Receives packets from the child process into buffers taken from `pool`, returning them to the pool once they have been processed.
+	///
+	/// This allows buffers to be recycled across viaducts, and between fragmented packets, rather than being allocated for each one.
+	///
+	/// By default, a single buffer is reused for receiving packets.
+	pub fn buffer_pool<P: BufferPool>(mut self, pool: P) -> Self {
+		self.options.buffer_pool = Some(Arc::new(pool));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the raw bytes of every RPC, request and response payload received from the child process, before it is deserialized.
+	///
+	/// This is a single place to log, audit or record incoming traffic without wrapping every [`ViaductDeserialize`] implementation. The hook runs on the event loop's thread, so keep it quick.
+	pub fn on_raw_recv<F: FnMut(PacketType, &[u8]) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_raw_recv = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the raw bytes of every RPC, request and response payload sent to the child process, after it has been serialized.
+	///
+	/// This is a single place to log, audit or record outgoing traffic without wrapping every [`ViaductSerialize`] implementation. The hook runs while the sending side of the viaduct is locked, so keep it quick and don't send anything from inside it.
+	pub fn on_raw_send<F: FnMut(PacketType, &[u8]) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_raw_send = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the packet type, payload length and request id (if any) of every RPC, request and response received from the child, before it is dispatched.
+	///
+	/// This is a single place to trace or count incoming traffic without touching every event handler. Unlike [`ViaductParent::on_raw_recv`], the payload itself isn't passed to the hook. The hook runs on the event loop's thread, so keep it quick.
+	pub fn on_frame<F: FnMut(FrameInfo) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_frame = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Inserts a resync marker before every frame sent to the child process, and expects one before every frame received from it.
+	///
+	/// If the stream ever becomes desynchronized (for example, because of a partial write), the event loop will scan forward to the next marker and carry on from there, discarding whatever was in between, rather than misinterpreting every packet that follows. Only a missing marker or an unknown packet type is handled this way; a malformed packet, or one that exceeds a limit such as [`ViaductParent::max_message_size`], still stops the event loop with an error.
+	///
+	/// **Both** processes must enable this, otherwise the viaduct will not work.
+	pub fn resync_markers(mut self) -> Self {
+		self.options.resync_markers = true;
+		self
+	}
+
+	#[inline]
+	/// Prepends a [`Timestamp`] of when it was sent to every frame sent to the child process, and expects one before every frame received from it.
+	///
+	/// The timestamps of received packets are passed to the event handler by [`ViaductRx::run_timestamped`], for measuring how long packets take to arrive. This adds 8 bytes to every frame, so it's off by default.
+	///
+	/// **Both** processes must enable this, otherwise the viaduct will not work.
+	pub fn timestamps(mut self) -> Self {
+		self.options.timestamps = true;
+		self
+	}
+
+	#[inline]
+	/// Buffers RPCs sent to the child, so that they can be coalesced into fewer, larger writes for throughput, instead of flushing each one down the pipe as soon as it's sent.
+	///
+	/// Buffered RPCs are only guaranteed to reach the child once [`ViaductTx::flush`] is called, the write buffer fills up, or a request or response is sent. Requests and responses are always flushed immediately, as the other side is waiting for them, so a request can never be left stuck in the buffer while its sender waits for the response.
+	///
+	/// This is the same as disabling no-delay mode with [`ViaductTx::set_no_delay`] straight after building the viaduct. Off by default.
+	pub fn buffered_writes(mut self, buffered: bool) -> Self {
+		self.options.buffered_writes = buffered;
+		self
+	}
+
+	#[inline]
+	/// Keeps track of requests from the child process that haven't been responded to yet.
+	///
+	/// If a [`ViaductRequestResponder`] is leaked (for example with [`std::mem::forget`], or by being stored somewhere that is never drained), neither a response nor a "no response" is ever sent, and the child process waits forever. With tracking enabled, a "no response" is sent on behalf of any outstanding responders when the [`ViaductRx`] is dropped, and [`ViaductTx::outstanding_responders`] can be used to keep an eye on them.
+	pub fn track_responders(mut self) -> Self {
+		self.options.track_responders = true;
+		self
+	}
+
+	#[inline]
+	/// Limits how many [`ViaductRequestResponder`]s for requests from the child process can be outstanding at once.
+	///
+	/// Once `max` responders are alive, the event loop stops delivering new requests until one of them responds or is dropped, which applies backpressure to the child process rather than letting responders pile up without bound.
+	///
+	/// Make sure responders are consumed somewhere other than the event loop's own thread, or it will wait forever for a responder it is still holding.
+	///
+	/// By default, there is no limit.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `max` is zero.
+	pub fn max_outstanding_responders(mut self, max: usize) -> Self {
+		assert_ne!(max, 0, "max_outstanding_responders must be greater than zero");
+		self.options.max_outstanding_responders = Some(max);
+		self
+	}
+
+	#[inline]
+	/// Sets how many RPCs sent with [`ViaductTx::rpc_windowed`] can be waiting to be received by the child process before the sender blocks.
+	///
+	/// Larger windows let the sender get further ahead, which smooths out bursts; smaller windows keep the sender closer to the child process' pace.
+	///
+	/// Defaults to 64.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `size` is zero.
+	pub fn rpc_window(mut self, size: usize) -> Self {
+		assert_ne!(size, 0, "rpc_window must be greater than zero");
+		self.options.rpc_window = size;
+		self
+	}
+
+	#[inline]
+	/// Enables credit-based flow control for RPCs sent by the child process, so that a slow event loop in this process can throttle it.
+	///
+	/// The child process starts with `credits` credits, and spends one on every RPC packet it sends (a batch sent with [`ViaductTx::rpc_batch`] is one packet). This process' event loop grants them back in batches as it reads the RPCs. Once the child process runs out, sending an RPC fails straight away with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock), rather than blocking in the kernel once the pipe fills up, and [`ViaductTx::rpc_credits`] lets it see how many credits it has left. Requests and responses aren't limited.
+	///
+	/// The credits are announced during the handshake, so only the receiving side needs to opt in. By default, RPCs aren't limited.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `credits` is zero.
+	pub fn rpc_credits(mut self, credits: usize) -> Self {
+		assert_ne!(credits, 0, "rpc_credits must be greater than zero");
+		self.options.rpc_credits = Some(credits as u64);
+		self
+	}
+
+	#[inline]
+	/// Gives up building the viaduct if spawning the child process and performing the handshake takes longer than `timeout`.
+	///
+	/// This is the same as building with [`ViaductParent::build_timeout`], for when the viaduct is built somewhere else, such as by a [`ViaductRebuilder`]. If the timeout is exceeded, the child process is killed and an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) is returned, so a mis-launched child process can't wedge [`ViaductParent::build`] forever.
+	///
+	/// By default, there is no timeout.
+	pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+		self.options.handshake_timeout = Some(timeout);
+		self
+	}
+
+	#[inline]
+	/// Requires the child process to support `capability`.
+	///
+	/// If it doesn't, building the viaduct will fail with a [`ViaductError::MissingCapability`] error, rather than carrying on without it.
+	pub fn require_capability(mut self, capability: Capability) -> Self {
+		self.options.required_capabilities.insert(capability);
+		self
+	}
+
+	#[cfg(feature = "core_affinity")]
+	#[inline]
+	/// Pins the reaper thread (see [`ViaductParent::with_reaper`]) to a specific CPU core, on a best-effort basis.
+	///
+	/// Use [`core_affinity::get_core_ids`] to list the available cores. To pin the event loop, see [`ViaductRx::run_on_core`].
+	pub fn reaper_core(mut self, core_id: core_affinity::CoreId) -> Self {
+		self.options.reaper_affinity.core_id = Some(core_id);
+		self
+	}
+
+	#[cfg(windows)]
+	/// Uses a pair of named pipes for the viaduct instead of unnamed pipes.
+	///
+	/// Each pipe is given a random unique name, which is passed to the child process in place of an inherited handle. Named pipes don't share the limitations of unnamed pipes on Windows; for example, they can be put into non-blocking mode.
+	///
+	/// When the viaduct is built, the parent process waits for the child process to connect to the pipes, and fails if any other process connects to them first.
+	pub fn windows_named_pipe(mut self) -> Result<Self, std::io::Error> {
+		self.data_pipes = DataPipes::Named(Box::new(named_pipe::NamedPipes::new()?));
+		Ok(self)
+	}
+
+	#[inline]
+	/// Retries spawning the child process up to `count` times, waiting `backoff` between each attempt.
+	///
+	/// Only errors that are likely to be transient (such as `EAGAIN` from `fork` when the system is under memory pressure) are retried; any other error is returned immediately.
+	pub fn spawn_retries(mut self, count: u32, backoff: Duration) -> Self {
+		self.spawn_retries = (count, backoff);
+		self
+	}
+
+	#[inline]
+	/// Limits the resources the child process can use, such as its memory and CPU time.
+	///
+	/// The limits are applied in the child process just before it executes the command, once its end of the viaduct has been set up, so they apply to everything the child process does. If they can't be applied, spawning the child process fails.
+	///
+	/// Only supported on Unix.
+	#[cfg(unix)]
+	pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+		use std::os::unix::process::CommandExt;
+
+		// Safety: applying the limits only makes system calls, which is safe to do between fork and exec
+		unsafe { self.command.pre_exec(move || limits.apply()) };
+		self
+	}
+
+	#[inline]
+	/// Spawns the child process detached from this one, so that it can carry on running after this process exits.
+	///
+	/// On Unix, the child process is started in a new session with `setsid`, so it no longer shares this process' controlling terminal and process group, and isn't sent signals such as `SIGINT` or `SIGHUP` meant for them; once this process exits, it is reparented to `init`. On Windows, the child process is created with `DETACHED_PROCESS` and `CREATE_NEW_PROCESS_GROUP`, so it has no console and doesn't receive this process' `Ctrl+C` events.
+	///
+	/// Once the viaduct is built, the child process is never killed on this process' behalf. It is still killed if building the viaduct fails, as it would be left waiting for a handshake that will never come.
+	///
+	/// The viaduct itself is of no use after this process exits: the child process' event loop will return a [`ViaductError::PeerGone`] error. Consider redirecting the child process' standard I/O handles with [`ViaductParent::stdin`], [`ViaductParent::stdout`] and [`ViaductParent::stderr`] as well, as they are otherwise inherited from this process.
+	pub fn detach_child(mut self) -> Self {
+		os::detach(&mut self.command);
+		self
+	}
+
+	/// Returns a [`ViaductRebuilder`], which builds a viaduct from the [`ViaductParent`] returned by `factory`, and can build it again with a new child process whenever the old one dies.
+	///
+	/// `factory` is called every time the viaduct is (re)built, and should set up the [`ViaductParent`] exactly as it would be set up for [`ViaductParent::build`], including its reaper callback.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductParent, doctest::{ExampleRequest, ExampleRpc}};
+	/// # use std::process::Command;
+	/// let mut rebuilder = ViaductParent::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::rebuilder(|| {
+	///     Ok(ViaductParent::new(Command::new("child.exe"))?.with_reaper(|status| eprintln!("Child process exited: {status:?}")))
+	/// });
+	///
+	/// loop {
+	///     let (viaduct, mut child) = rebuilder.rebuild().unwrap();
+	///     // ...
+	///     child.wait().unwrap();
+	/// }
+	/// ```
+	pub fn rebuilder<F>(factory: F) -> ViaductRebuilder<RpcTx, RequestTx, RpcRx, RequestRx>
+	where
+		F: FnMut() -> Result<Self, std::io::Error> + Send + 'static,
+	{
+		ViaductRebuilder::new(Box::new(factory))
+	}
+
+	/// Spawns the child process and returns it along with a [`Viaduct`](crate::Viaduct).
+	///
+	/// Any standard I/O handles configured with [`ViaductParent::stdin`], [`ViaductParent::stdout`] and [`ViaductParent::stderr`] can be taken from the returned [`Child`](std::process::Child).
+	///
+	/// If the child process exits before completing the handshake (for example, because it crashed on startup, or is the wrong executable and never builds its side of the viaduct), a [`ViaductError::ChildExitedDuringHandshake`] error is returned with its exit status.
+	///
+	/// This consumes the builder along with the pipes it created, so that they can't be handed to a second child process. To try again after a failure, start over with [`ViaductParent::new`]; to retry transient spawn failures automatically, see [`ViaductParent::spawn_retries`].
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductParent, doctest::*};
+	/// # use std::{io::Write, process::{Command, Stdio}};
+	/// let (viaduct, mut child) = ViaductParent::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new(Command::new("child.exe"))
+	///     .unwrap()
+	///     .stdin(Stdio::piped())
+	///     .build()
+	///     .unwrap();
+	/// let (tx, rx) = viaduct.split();
+	///
+	/// let mut stdin = child.stdin.take().unwrap();
+	/// stdin.write_all(b"Hello, child!").unwrap();
+	/// ```
+	#[allow(clippy::type_complexity)]
+	#[inline]
+	pub fn build(self) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child), std::io::Error> {
+		let deadline = self.options.handshake_timeout.map(|timeout| Instant::now() + timeout);
+		self.build_until(deadline)
+	}
+
+	/// Builds the viaduct, like [`ViaductParent::build`], but gives up if spawning the child process and performing the handshake takes longer than `timeout`.
+	///
+	/// If the timeout is exceeded, the child process is killed and an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) is returned. This makes sure that a broken or hung child process can't block the parent process (or a test run) forever.
+	///
+	/// This takes precedence over [`ViaductParent::with_handshake_timeout`].
+	#[allow(clippy::type_complexity)]
+	#[inline]
+	pub fn build_timeout(self, timeout: Duration) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child), std::io::Error> {
+		self.build_until(Some(Instant::now() + timeout))
+	}
+
+	#[cfg(unix)]
+	/// Builds the viaduct over a TCP connection instead of spawning the child process, for attaching to a process that is started some other way, such as under a debugger or on another machine.
+	///
+	/// Binds to `addr` and waits for a single connection from [`ViaductChild::connect_tcp`], then performs the handshake over it just like [`ViaductParent::build`]. The peer may be running on a different architecture, so the handshake also makes sure it uses the same endianness and size of `usize`.
+	///
+	/// The command isn't spawned, so its arguments and environment, and the options that only apply to a child process (such as [`ViaductParent::with_reaper`]), are ignored. Handles can't be shared over TCP, so the peer isn't told that this viaduct supports [`Capability::HandlePassing`].
+	///
+	/// [`ViaductParent::with_handshake_timeout`] also limits how long to wait for the peer to connect.
+	///
+	/// The connection isn't encrypted or authenticated, and any process that can reach `addr` can connect in place of the child process, so only listen on a trusted interface such as localhost.
+	pub fn listen_tcp<A: std::net::ToSocketAddrs>(mut self, addr: A) -> Result<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> {
+		let deadline = self.options.handshake_timeout.map(|timeout| Instant::now() + timeout);
+		let (mut tx, mut rx) = tcp::accept(addr, deadline)?;
+		let peer_capabilities = verify_channel(&mut tx, &mut rx, tcp::CAPABILITIES, &mut self.options, deadline)?;
+		Ok(channel(PipeSink::Pipe(tx), PipeReader::Pipe(rx), None, peer_capabilities, self.options))
+	}
+
+	#[allow(clippy::type_complexity)]
+	fn build_until(mut self, deadline: Option<Instant>) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child), std::io::Error> {
 		struct KillHandle(Option<Child>);
 		impl Drop for KillHandle {
 			#[inline]
 			fn drop(&mut self) {
 				if let Some(child) = &mut self.0 {
 					child.kill().ok();
+
+					// Reap it, so that it doesn't linger as a zombie
+					child.wait().ok();
 				}
 			}
 		}
 
-		let mut child = verify_channel(&mut self.tx.0.state.lock().tx, &mut self.rx.rx, move || {
-			Ok(KillHandle(Some(self.command.spawn()?)))
-		})?;
+		let (parent_w, child_r) = match &self.data_pipes {
+			DataPipes::Unnamed {
+				child_pipes: (parent_w, child_r),
+				..
+			} => (
+				OsString::from((parent_w.as_raw() as usize as u64).to_string()),
+				OsString::from((child_r.as_raw() as usize as u64).to_string()),
+			),
 
-		let child = child.0.take().unwrap();
+			#[cfg(windows)]
+			DataPipes::Named(pipes) => (OsString::from(pipes.parent_name()), OsString::from(pipes.child_name())),
+		};
 
-		if let Some(callback) = self.with_reaper {
-			unsafe { reaper::parent(self.reaper_tx, callback) };
+		#[allow(unused_mut)]
+		let mut handles = vec![
+			parent_w,
+			child_r,
+			OsString::from((self.reaper_tx.as_raw() as usize as u64).to_string()),
+			OsString::from((self.reaper_rx.as_raw() as usize as u64).to_string()),
+		];
+		#[cfg(unix)]
+		handles.push(OsString::from((self.handle_sockets.1.as_raw() as u64).to_string()));
+		if self.handles_in_env {
+			let mut env = OsString::from(handles.len().to_string());
+			for handle in &handles {
+				env.push(" ");
+				env.push(handle);
+			}
+			self.command.env(PIPES_ENV, env);
 		} else {
-			std::mem::forget(self.reaper_tx);
+			match &self.handshake_marker {
+				Some(marker) => {
+					self.command.env(HANDSHAKE_MARKER_ENV, marker);
+					self.command.arg(marker);
+				}
+				None => {
+					self.command.arg(DEFAULT_HANDSHAKE_MARKER);
+				}
+			}
+			self.command.arg(handles.len().to_string());
+			self.command.args(&handles);
 		}
 
-		Ok(((self.tx, self.rx), child))
+		let (mut retries, backoff) = self.spawn_retries;
+		let mut child = loop {
+			match self.command.spawn() {
+				Ok(child) => break KillHandle(Some(child)),
+				Err(err) if retries > 0 && is_transient_spawn_error(&err) && deadline.is_none_or(|deadline| Instant::now() + backoff < deadline) => {
+					retries -= 1;
+					std::thread::sleep(backoff);
+				}
+				Err(err) => return Err(err),
+			}
+		};
+
+		// The child process has inherited its end of the socket, so close ours and keep the other for sharing file descriptors
+		#[cfg(unix)]
+		{
+			let (parent_socket, child_socket) = self.handle_sockets;
+			drop(child_socket);
+			self.options.handle_socket = Some(parent_socket);
+		}
+
+		// Kill the child process if it doesn't finish the handshake in time, which unblocks us
+		let watchdog = match deadline {
+			Some(deadline) => {
+				let killer = os::ProcessKiller::new(child.0.as_ref().unwrap())?;
+				let (cancel_tx, cancel_rx) = std::sync::mpsc::channel::<()>();
+				let watchdog = std::thread::spawn(move || match cancel_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+					Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+						killer.kill().ok();
+						true
+					}
+					_ => false,
+				});
+				Some((cancel_tx, watchdog))
+			}
+			None => None,
+		};
+
+		let handshake = (|| {
+			let (mut tx, mut rx) = match self.data_pipes {
+				DataPipes::Unnamed { tx, rx, child_pipes } => {
+					// The child process has inherited its ends of the pipes, so close ours
+					drop(child_pipes);
+					(tx, rx)
+				}
+
+				#[cfg(windows)]
+				DataPipes::Named(pipes) => pipes.accept(child.0.as_mut().unwrap())?,
+			};
+
+			let peer_capabilities = verify_channel(&mut tx, &mut rx, Capabilities::LOCAL, &mut self.options, deadline)?;
+			Ok::<_, std::io::Error>((tx, rx, peer_capabilities))
+		})();
+
+		let timed_out = match watchdog {
+			Some((cancel_tx, watchdog)) => {
+				drop(cancel_tx);
+				watchdog.join().unwrap()
+			}
+			None => false,
+		};
+		if timed_out {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::TimedOut,
+				"Timed out spawning the child process and performing the handshake",
+			));
+		}
+
+		let (tx, rx, peer_capabilities) = match handshake {
+			Ok(handshake) => handshake,
+			Err(err)
+				if matches!(err.kind(), std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::BrokenPipe)
+					&& ViaductError::from_io(&err).is_none() =>
+			{
+				// The child process hung up on us, most likely because it exited, so tell the caller how it went
+				return Err(match exited_during_handshake(child.0.as_mut().unwrap()) {
+					Some(status) => ViaductError::ChildExitedDuringHandshake { status }.into(),
+					None => err,
+				});
+			}
+			Err(err) => return Err(err),
+		};
+
+		// Lets the reaper thread find out how the child process exited
+		let reaper = match self.with_reaper {
+			Some(callback) => Some((callback, os::ExitWatcher::new(child.0.as_ref().unwrap())?)),
+			None => None,
+		};
+
+		// Lets the rest of the program find and kill the child process once the caller has taken its `Child`
+		self.options.child_handle = Some(ChildHandle::new(child.0.as_ref().unwrap())?);
+
+		let child = child.0.take().unwrap();
+
+		// Handles are shared with the child by duplicating them into it, so hold on to its process handle for as long as the viaduct is alive
+		#[cfg(windows)]
+		{
+			use std::os::windows::io::AsHandle;
+			self.options.peer_process = Some(child.as_handle().try_clone_to_owned()?);
+		}
+
+		let reaper_pipe = if let Some((callback, exit_watcher)) = reaper {
+			unsafe {
+				reaper::parent(
+					self.reaper_tx,
+					callback,
+					self.options.reaper_affinity,
+					self.options.reaper_interval,
+					exit_watcher,
+				)
+			};
+			None
+		} else {
+			// Keep the reaper pipe open for as long as the viaduct is alive, so that the child's reaper isn't triggered
+			Some(ReaperPipe::Writer(self.reaper_tx))
+		};
+
+		Ok((
+			channel(PipeSink::Pipe(tx), PipeReader::Pipe(rx), reaper_pipe, peer_capabilities, self.options),
+			child,
+		))
 	}
 }
 
@@ -398,6 +1715,8 @@ where
 	RequestRx: ViaductDeserialize,
 {
 	with_reaper: Option<ReaperCallbackFn>,
+	handles_from_env: bool,
+	options: ViaductOptions,
 	_phantom: PhantomData<(RpcTx, RequestTx, RpcRx, RequestRx)>,
 }
 impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductChild<RpcTx, RequestTx, RpcRx, RequestRx>
@@ -413,112 +1732,324 @@ where
 	pub fn new() -> Self {
 		Self {
 			with_reaper: None,
+			handles_from_env: false,
+			options: ViaductOptions::default(),
 			_phantom: Default::default(),
 		}
 	}
 
+	#[inline]
+	/// Reads the pipe handles from the `VIADUCT_PIPES` environment variable, for parent processes that use [`ViaductParent::pass_handles_in_env`].
+	///
+	/// The process arguments are left untouched, and the environment variable is removed once it has been read so that this process' own child processes don't inherit it.
+	pub fn from_env(mut self) -> Self {
+		self.handles_from_env = true;
+		self
+	}
+
 	#[inline]
 	/// Whether to spawn a reaper thread or not.
 	///
-	/// A reaper thread will occasionally check whether the parent process has been killed and call your `callback` if it has.
+	/// A reaper thread will occasionally check whether the parent process has been killed (or has dropped its side of the viaduct) and call your `callback` if it has.
 	///
 	/// This allows you to gracefully handle the parent process being killed.
+	///
+	/// If this process is always running the event loop, you may not need a reaper thread: the event loop returns a [`ViaductError::PeerGone`] error when the parent process goes away.
 	pub fn with_reaper<F: FnOnce() + Send + 'static>(mut self, callback: F) -> Self {
 		self.with_reaper = Some(Box::new(callback));
 		self
 	}
 
+	#[inline]
+	/// Sets how often the reaper thread (see [`ViaductChild::with_reaper`]) checks whether the parent process is still alive, which defaults to 5 seconds.
+	///
+	/// This bounds how long it can take for the reaper callback to be called after the parent process goes away. Intervals shorter than 10 milliseconds are rounded up to 10 milliseconds, so that the reaper thread doesn't spin.
+	pub fn with_reaper_interval(mut self, interval: Duration) -> Self {
+		self.options.reaper_interval = interval.max(reaper::MIN_REAPER_INTERVAL);
+		self
+	}
+
+	#[cfg(feature = "tokio")]
+	/// Spawns a reaper thread, just like [`with_reaper`](Self::with_reaper), but returns a [`oneshot::Receiver`](tokio::sync::oneshot::Receiver) that resolves when the parent process dies instead of calling a callback.
+	///
+	/// This lets you `select!` on the parent process dying alongside the rest of your async work.
+	///
+	/// Requires the `tokio` feature. Replaces any callback previously passed to [`with_reaper`](Self::with_reaper).
+	pub fn reaper_future(self) -> (Self, tokio::sync::oneshot::Receiver<()>) {
+		let (reaped_tx, reaped_rx) = tokio::sync::oneshot::channel();
+		let this = self.with_reaper(move || {
+			reaped_tx.send(()).ok();
+		});
+		(this, reaped_rx)
+	}
+
+	#[inline]
+	/// Splits packets larger than `max_fragment_size` bytes into fragments when sending them to the parent process.
+	///
+	/// Other threads can send their own packets in between each fragment, so that sending a large packet doesn't hold up the rest of the viaduct.
+	///
+	/// By default, packets are never fragmented.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `max_fragment_size` is zero.
+	pub fn max_fragment_size(mut self, max_fragment_size: usize) -> Self {
+		assert_ne!(max_fragment_size, 0, "Maximum fragment size must be greater than zero");
+		self.options.max_fragment_size = Some(max_fragment_size);
+		self
+	}
+
+	#[inline]
+	/// Refuses to send RPCs, requests and responses to the parent process that serialize to more than `max_send_size` bytes.
+	///
+	/// Oversized messages aren't written at all; instead, a [`ViaductError::MessageTooLarge`] error is returned, so a message the parent process would reject is caught where it's sent. If a response is too large, the requester receives `None` instead.
+	///
+	/// By default, there is no limit.
+	pub fn max_send_size(mut self, max_send_size: usize) -> Self {
+		self.options.max_send_size = Some(max_send_size);
+		self
+	}
+
+	#[inline]
+	/// Refuses to receive RPCs, requests and responses from the parent process that are larger than `max_message_size` bytes, or `None` for no limit.
+	///
+	/// Each message is preceded by its length, which is checked against this limit before anything is allocated for it, so a corrupt or hostile parent process can't make us run out of memory by claiming to send a huge message. Instead, the event loop returns a [`ViaductError::ReceiveLimit`] error. Compressed messages are checked against the size they decompress to.
+	///
+	/// Defaults to 1 GiB.
+	pub fn max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+		self.options.max_message_size = max_message_size;
+		self
+	}
+
+	#[cfg(feature = "compression")]
+	#[inline]
+	/// Compresses the payloads of RPCs, requests and responses sent to the parent process that serialize to at least `threshold` bytes, using LZ4.
+	///
+	/// Small payloads aren't worth compressing, so they are always sent as they are, as are payloads that don't get any smaller when compressed. Whether each packet was compressed is recorded in the packet itself, so the parent process decompresses exactly what needs decompressing; use [`ViaductTx::rpc_uncompressed`] to skip compression for an RPC that is already compressed, such as an image.
+	///
+	/// Only takes effect if the parent process supports [`Capability::Compression`], i.e. it was also built with the `compression` feature; otherwise, nothing is compressed.
+	///
+	/// Requires the `compression` feature. By default, nothing is compressed.
+	pub fn compression_threshold(mut self, threshold: usize) -> Self {
+		self.options.compression_threshold = Some(threshold);
+		self
+	}
+
+	#[inline]
+	/// Sets the maximum number of bytes of fragmented packets from the parent process that can be held in memory while they are being reassembled.
+	///
+	/// If the parent process exceeds this limit, the event loop will return a [`ViaductError::ReassemblyLimit`] error.
+	///
+	/// Defaults to 1 GiB.
+	pub fn max_reassembly_bytes(mut self, max_reassembly_bytes: usize) -> Self {
+		self.options.max_reassembly_bytes = max_reassembly_bytes;
+		self
+	}
+
+	#[inline]
+	/// Sets the maximum number of fragmented packets from the parent process that can be in the process of being reassembled at once.
+	///
+	/// If the parent process exceeds this limit, the event loop will return a [`ViaductError::ReassemblyLimit`] error.
+	///
+	/// Defaults to 64.
+	pub fn max_concurrent_fragments(mut self, max_concurrent_fragments: usize) -> Self {
+		self.options.max_concurrent_fragments = max_concurrent_fragments;
+		self
+	}
+
+	#[inline]
+	/// Receives packets from the parent process into buffers taken from `pool`, returning them to the pool once they have been processed.
+	///
+	/// This allows buffers to be recycled across viaducts, and between fragmented packets, rather than being allocated for each one.
+	///
+	/// By default, a single buffer is reused for receiving packets.
+	pub fn buffer_pool<P: BufferPool>(mut self, pool: P) -> Self {
+		self.options.buffer_pool = Some(Arc::new(pool));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the raw bytes of every RPC, request and response payload received from the parent process, before it is deserialized.
+	///
+	/// This is a single place to log, audit or record incoming traffic without wrapping every [`ViaductDeserialize`] implementation. The hook runs on the event loop's thread, so keep it quick.
+	pub fn on_raw_recv<F: FnMut(PacketType, &[u8]) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_raw_recv = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the raw bytes of every RPC, request and response payload sent to the parent process, after it has been serialized.
+	///
+	/// This is a single place to log, audit or record outgoing traffic without wrapping every [`ViaductSerialize`] implementation. The hook runs while the sending side of the viaduct is locked, so keep it quick and don't send anything from inside it.
+	pub fn on_raw_send<F: FnMut(PacketType, &[u8]) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_raw_send = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Calls `hook` with the packet type, payload length and request id (if any) of every RPC, request and response received from the parent, before it is dispatched.
+	///
+	/// This is a single place to trace or count incoming traffic without touching every event handler. Unlike [`ViaductChild::on_raw_recv`], the payload itself isn't passed to the hook. The hook runs on the event loop's thread, so keep it quick.
+	pub fn on_frame<F: FnMut(FrameInfo) + Send + 'static>(mut self, hook: F) -> Self {
+		self.options.on_frame = Some(Box::new(hook));
+		self
+	}
+
+	#[inline]
+	/// Inserts a resync marker before every frame sent to the parent process, and expects one before every frame received from it.
+	///
+	/// If the stream ever becomes desynchronized (for example, because of a partial write), the event loop will scan forward to the next marker and carry on from there, discarding whatever was in between, rather than misinterpreting every packet that follows. Only a missing marker or an unknown packet type is handled this way; a malformed packet, or one that exceeds a limit such as [`ViaductChild::max_message_size`], still stops the event loop with an error.
+	///
+	/// **Both** processes must enable this, otherwise the viaduct will not work.
+	pub fn resync_markers(mut self) -> Self {
+		self.options.resync_markers = true;
+		self
+	}
+
+	#[inline]
+	/// Prepends a [`Timestamp`] of when it was sent to every frame sent to the parent process, and expects one before every frame received from it.
+	///
+	/// The timestamps of received packets are passed to the event handler by [`ViaductRx::run_timestamped`], for measuring how long packets take to arrive. This adds 8 bytes to every frame, so it's off by default.
+	///
+	/// **Both** processes must enable this, otherwise the viaduct will not work.
+	pub fn timestamps(mut self) -> Self {
+		self.options.timestamps = true;
+		self
+	}
+
+	#[inline]
+	/// Buffers RPCs sent to the parent, so that they can be coalesced into fewer, larger writes for throughput, instead of flushing each one down the pipe as soon as it's sent.
+	///
+	/// Buffered RPCs are only guaranteed to reach the parent once [`ViaductTx::flush`] is called, the write buffer fills up, or a request or response is sent. Requests and responses are always flushed immediately, as the other side is waiting for them, so a request can never be left stuck in the buffer while its sender waits for the response.
+	///
+	/// This is the same as disabling no-delay mode with [`ViaductTx::set_no_delay`] straight after building the viaduct. Off by default.
+	pub fn buffered_writes(mut self, buffered: bool) -> Self {
+		self.options.buffered_writes = buffered;
+		self
+	}
+
+	#[inline]
+	/// Keeps track of requests from the parent process that haven't been responded to yet.
+	///
+	/// If a [`ViaductRequestResponder`] is leaked (for example with [`std::mem::forget`], or by being stored somewhere that is never drained), neither a response nor a "no response" is ever sent, and the parent process waits forever. With tracking enabled, a "no response" is sent on behalf of any outstanding responders when the [`ViaductRx`] is dropped, and [`ViaductTx::outstanding_responders`] can be used to keep an eye on them.
+	pub fn track_responders(mut self) -> Self {
+		self.options.track_responders = true;
+		self
+	}
+
+	#[inline]
+	/// Limits how many [`ViaductRequestResponder`]s for requests from the parent process can be outstanding at once.
+	///
+	/// Once `max` responders are alive, the event loop stops delivering new requests until one of them responds or is dropped, which applies backpressure to the parent process rather than letting responders pile up without bound.
+	///
+	/// Make sure responders are consumed somewhere other than the event loop's own thread, or it will wait forever for a responder it is still holding.
+	///
+	/// By default, there is no limit.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `max` is zero.
+	pub fn max_outstanding_responders(mut self, max: usize) -> Self {
+		assert_ne!(max, 0, "max_outstanding_responders must be greater than zero");
+		self.options.max_outstanding_responders = Some(max);
+		self
+	}
+
+	#[inline]
+	/// Sets how many RPCs sent with [`ViaductTx::rpc_windowed`] can be waiting to be received by the parent process before the sender blocks.
+	///
+	/// Larger windows let the sender get further ahead, which smooths out bursts; smaller windows keep the sender closer to the parent process' pace.
+	///
+	/// Defaults to 64.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `size` is zero.
+	pub fn rpc_window(mut self, size: usize) -> Self {
+		assert_ne!(size, 0, "rpc_window must be greater than zero");
+		self.options.rpc_window = size;
+		self
+	}
+
+	#[inline]
+	/// Enables credit-based flow control for RPCs sent by the parent process, so that a slow event loop in this process can throttle it.
+	///
+	/// The parent process starts with `credits` credits, and spends one on every RPC packet it sends (a batch sent with [`ViaductTx::rpc_batch`] is one packet). This process' event loop grants them back in batches as it reads the RPCs. Once the parent process runs out, sending an RPC fails straight away with an error of kind [`WouldBlock`](std::io::ErrorKind::WouldBlock), rather than blocking in the kernel once the pipe fills up, and [`ViaductTx::rpc_credits`] lets it see how many credits it has left. Requests and responses aren't limited.
+	///
+	/// The credits are announced during the handshake, so only the receiving side needs to opt in. By default, RPCs aren't limited.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `credits` is zero.
+	pub fn rpc_credits(mut self, credits: usize) -> Self {
+		assert_ne!(credits, 0, "rpc_credits must be greater than zero");
+		self.options.rpc_credits = Some(credits as u64);
+		self
+	}
+
+	#[inline]
+	/// Gives up building the viaduct if the parent process doesn't perform the handshake within `timeout`.
+	///
+	/// If the timeout is exceeded, an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut) is returned, rather than waiting forever for a parent process that never writes to the pipes.
+	///
+	/// By default, there is no timeout.
+	pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+		self.options.handshake_timeout = Some(timeout);
+		self
+	}
+
+	#[inline]
+	/// Requires the parent process to support `capability`.
+	///
+	/// If it doesn't, building the viaduct will fail with a [`ViaductError::MissingCapability`] error, rather than carrying on without it.
+	pub fn require_capability(mut self, capability: Capability) -> Self {
+		self.options.required_capabilities.insert(capability);
+		self
+	}
+
+	#[cfg(feature = "core_affinity")]
+	#[inline]
+	/// Pins the reaper thread (see [`ViaductChild::with_reaper`]) to a specific CPU core, on a best-effort basis.
+	///
+	/// Use [`core_affinity::get_core_ids`] to list the available cores. To pin the event loop, see [`ViaductRx::run_on_core`].
+	pub fn reaper_core(mut self, core_id: core_affinity::CoreId) -> Self {
+		self.options.reaper_affinity.core_id = Some(core_id);
+		self
+	}
+
 	/// Initializes a viaduct in the child process.
 	///
 	/// Returns the viaduct.
 	///
+	/// The pipe handles passed by the parent process can only be taken once, so once they have been found, building another viaduct in this process returns a [`ViaductError::AlreadyBuilt`] error, even if building this one failed partway through.
+	///
 	/// # Safety
 	///
-	/// Undefined behaviour can result from manipulating the program's arguments in a way that disrupts Viaduct's handle exchange.
+	/// Undefined behaviour can result from manipulating the program's arguments (or, with [`ViaductChild::from_env`], its environment) in a way that disrupts Viaduct's handle exchange.
+	///
+	/// This removes the `VIADUCT_PIPES` (with [`ViaductChild::from_env`]) or `VIADUCT_HANDSHAKE_MARKER` environment variable, which isn't thread-safe on some platforms, so no other threads should be reading or writing the environment at the same time.
 	pub unsafe fn build(self) -> Result<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> {
-		let mut args = std::env::args_os();
-		{
-			let sig = OsStr::new("PIPER_START");
-			let mut sig_found = false;
-			for arg in args.by_ref() {
-				if arg == sig {
-					sig_found = true;
-					break;
-				}
-			}
-			if !sig_found {
-				return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not find pipe handles"));
-			}
-		}
-
-		let (parent_w, child_r, reaper_tx, reaper_rx) = match args
-			.next()
-			.and_then(|arg| Some((arg, args.next()?, args.next()?, args.next()?)))
-			.and_then(|pipes| {
-				Some((
-					pipes.0.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.1.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.2.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.3.to_str()?.parse::<NonZeroU64>().ok()?,
-				))
-			}) {
-			Some(pipes) => pipes,
-			_ => return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not parse pipe handles")),
-		};
+		let handles = take_handles(self.handles_from_env)?;
 
-		unsafe { Self::child_handshake(parent_w, child_r, reaper_tx, reaper_rx, self.with_reaper) }
+		unsafe { Self::child_handshake(handles, self.with_reaper, self.options) }
 	}
 
 	/// Initializes a viaduct in the child process.
 	///
-	/// Returns the viaduct and the process arguments.
+	/// Returns the viaduct and the process arguments, with the arguments Viaduct uses to pass pipe handles removed. These are also available from [`args_os`] afterwards.
 	///
 	/// # Safety
 	///
 	/// Undefined behaviour can result from manipulating the program's arguments in a way that disrupts Viaduct's handle exchange.
+	#[allow(clippy::type_complexity)]
 	pub unsafe fn build_with_args_os(self) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, impl Iterator<Item = OsString>), std::io::Error> {
-		let mut args = std::env::args_os();
-		let mut buffer = Vec::with_capacity(1);
-
-		{
-			let sig = OsStr::new("PIPER_START");
-			let mut sig_found = false;
-			for arg in args.by_ref() {
-				if arg == sig {
-					sig_found = true;
-					break;
-				}
-				buffer.push(arg);
-			}
-			if !sig_found {
-				return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not find pipe handles"));
-			}
-		}
-
-		let (parent_w, child_r, reaper_tx, reaper_rx) = match args
-			.next()
-			.and_then(|arg| Some((arg, args.next()?, args.next()?, args.next()?)))
-			.and_then(|pipes| {
-				Some((
-					pipes.0.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.1.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.2.to_str()?.parse::<NonZeroU64>().ok()?,
-					pipes.3.to_str()?.parse::<NonZeroU64>().ok()?,
-				))
-			}) {
-			Some(pipes) => pipes,
-			_ => return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not parse pipe handles")),
-		};
-
-		Ok((
-			unsafe { Self::child_handshake(parent_w, child_r, reaper_tx, reaper_rx, self.with_reaper)? },
-			buffer.into_iter().chain(args),
-		))
+		let viaduct = unsafe { self.build()? };
+		Ok((viaduct, args_os()))
 	}
 
 	/// Initializes a viaduct in the child process.
 	///
-	/// Returns the viaduct and the process arguments.
+	/// Returns the viaduct and the process arguments, with the arguments Viaduct uses to pass pipe handles removed. These are also available from [`args`] afterwards.
 	///
 	/// # Panics
 	///
@@ -527,55 +2058,46 @@ where
 	/// # Safety
 	///
 	/// Undefined behaviour can result from manipulating the program's arguments in a way that disrupts Viaduct's handle exchange.
+	#[allow(clippy::type_complexity)]
 	pub unsafe fn build_with_args(self) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, impl Iterator<Item = String>), std::io::Error> {
-		let mut args = std::env::args();
-		let mut buffer = Vec::with_capacity(1);
-
-		{
-			let mut sig_found = false;
-			for arg in args.by_ref() {
-				if arg == "PIPER_START" {
-					sig_found = true;
-					break;
-				}
-				buffer.push(arg);
-			}
-			if !sig_found {
-				return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not find pipe handles"));
-			}
-		}
-
-		let (parent_w, child_r, reaper_tx, reaper_rx) = match args
-			.next()
-			.and_then(|arg| Some((arg, args.next()?, args.next()?, args.next()?)))
-			.and_then(|pipes| {
-				Some((
-					pipes.0.parse::<NonZeroU64>().ok()?,
-					pipes.1.parse::<NonZeroU64>().ok()?,
-					pipes.2.parse::<NonZeroU64>().ok()?,
-					pipes.3.parse::<NonZeroU64>().ok()?,
-				))
-			}) {
-			Some(pipes) => pipes,
-			_ => return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Could not parse pipe handles")),
-		};
+		let viaduct = unsafe { self.build()? };
+		Ok((viaduct, args()))
+	}
 
-		Ok((
-			unsafe { Self::child_handshake(parent_w, child_r, reaper_tx, reaper_rx, self.with_reaper)? },
-			buffer.into_iter().chain(args),
-		))
+	#[cfg(unix)]
+	/// Initializes a viaduct in this process by connecting to a parent process listening with [`ViaductParent::listen_tcp`], instead of using the pipe handles passed by a parent process.
+	///
+	/// This process doesn't need to have been started by the parent process, so its arguments are left alone and [`ViaductChild::with_reaper`] is ignored.
+	///
+	/// [`ViaductChild::with_handshake_timeout`] also limits how long to wait for the connection to be established.
+	pub fn connect_tcp<A: std::net::ToSocketAddrs>(mut self, addr: A) -> Result<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> {
+		let deadline = self.options.handshake_timeout.map(|timeout| Instant::now() + timeout);
+		let (mut tx, mut rx) = tcp::connect(addr, deadline)?;
+		let peer_capabilities = verify_channel(&mut tx, &mut rx, tcp::CAPABILITIES, &mut self.options, deadline)?;
+		Ok(channel(PipeSink::Pipe(tx), PipeReader::Pipe(rx), None, peer_capabilities, self.options))
 	}
 
 	unsafe fn child_handshake(
-		parent_w: NonZeroU64,
-		child_r: NonZeroU64,
-		reaper_tx: NonZeroU64,
-		reaper_rx: NonZeroU64,
+		handles: PipeHandles,
 		with_reaper: Option<ReaperCallbackFn>,
+		#[allow(unused_mut)] mut options: ViaductOptions,
 	) -> Result<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> {
-		let parent_w = unsafe { UnnamedPipeWriter::from_raw(parent_w.get() as usize as _) };
-		let child_r = unsafe { UnnamedPipeReader::from_raw(child_r.get() as usize as _) };
-		let (tx, mut rx) = channel(parent_w, child_r);
+		let deadline = options.handshake_timeout.map(|timeout| Instant::now() + timeout);
+
+		let PipeHandles {
+			parent_w,
+			child_r,
+			reaper_tx,
+			reaper_rx,
+			#[cfg(unix)]
+			handle_socket,
+		} = handles;
+
+		#[cfg(unix)]
+		{
+			options.handle_socket =
+				handle_socket.map(|handle_socket| unsafe { std::os::unix::net::UnixDatagram::from_raw(handle_socket.get() as _) });
+		}
 
 		let reaper_tx = DroppablePipe::new(unsafe { UnnamedPipeWriter::from_raw(reaper_tx.get() as usize as _) });
 		let reaper_rx = DroppablePipe::new(unsafe { UnnamedPipeReader::from_raw(reaper_rx.get() as usize as _) });
@@ -584,16 +2106,64 @@ where
 		// This closes the handle that the child process inherited
 		drop(reaper_tx);
 
+		let mut parent_w = match parent_w {
+			PipeToken::Handle(handle) => unsafe { UnnamedPipeWriter::from_raw(handle.get() as usize as _) },
+
+			#[cfg(windows)]
+			PipeToken::Named(name) => named_pipe::connect_writer(&name)?,
+		};
+		let mut child_r = match child_r {
+			PipeToken::Handle(handle) => unsafe { UnnamedPipeReader::from_raw(handle.get() as usize as _) },
+
+			#[cfg(windows)]
+			PipeToken::Named(name) => named_pipe::connect_reader(&name)?,
+		};
+
 		// Verify the channel is OK
-		verify_channel(&mut tx.0.state.lock().tx, &mut rx.rx, || Ok(()))?;
+		let peer_capabilities = verify_channel(&mut parent_w, &mut child_r, Capabilities::LOCAL, &mut options, deadline)?;
 
 		// Start the reaper thread
-		if let Some(callback) = with_reaper {
-			unsafe { reaper::child(reaper_rx, callback) };
+		let reaper_pipe = if let Some(callback) = with_reaper {
+			unsafe { reaper::child(reaper_rx, callback, options.reaper_affinity, options.reaper_interval) };
+			None
 		} else {
-			std::mem::forget(reaper_rx);
-		}
+			// Keep the reaper pipe open for as long as the viaduct is alive, so that the parent's reaper isn't triggered
+			Some(ReaperPipe::Reader(reaper_rx))
+		};
+
+		Ok(channel(
+			PipeSink::Pipe(parent_w),
+			PipeReader::Pipe(child_r),
+			reaper_pipe,
+			peer_capabilities,
+			options,
+		))
+	}
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductChild<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize + Send + 'static,
+	RequestTx: ViaductSerialize + Send + 'static,
+	RpcRx: ViaductDeserialize + Send + 'static,
+	RequestRx: ViaductDeserialize + Send + 'static,
+{
+	/// Initializes a viaduct in the child process, performing the handshake with the parent process on a background thread.
+	///
+	/// This returns as soon as the pipe handles have been found in the process arguments, so that the handshake can overlap with the rest of the child process' initialization. Join the returned handle once you need the viaduct.
+	///
+	/// Returns an error straight away if this process wasn't started by [`ViaductParent`], or a viaduct has already been built from its handles, just like [`ViaductChild::build`]. Errors from the handshake itself are returned when the handle is joined.
+	///
+	/// # Safety
+	///
+	/// Undefined behaviour can result from manipulating the program's arguments in a way that disrupts Viaduct's handle exchange.
+	#[allow(clippy::type_complexity)]
+	pub unsafe fn build_deferred(
+		self,
+	) -> Result<std::thread::JoinHandle<Result<Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error>>, std::io::Error> {
+		let handles = take_handles(self.handles_from_env)?;
 
-		Ok((tx, rx))
+		std::thread::Builder::new()
+			.name("viaduct handshake".to_string())
+			.spawn(move || unsafe { Self::child_handshake(handles, self.with_reaper, self.options) })
 	}
 }
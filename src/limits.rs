@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+
+/// Limits on the resources a child process can use, applied when it is spawned.
+///
+/// See [`ViaductParent::resource_limits`](crate::ViaductParent::resource_limits).
+///
+/// Only supported on Unix.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceLimits {
+	rlimits: Vec<(RlimitResource, libc::rlim_t)>,
+
+	#[cfg(target_os = "linux")]
+	cgroup: Option<CString>,
+}
+
+#[cfg(target_os = "linux")]
+type RlimitResource = libc::__rlimit_resource_t;
+
+#[cfg(not(target_os = "linux"))]
+type RlimitResource = libc::c_int;
+
+impl ResourceLimits {
+	/// Creates a set of limits that doesn't limit anything.
+	#[inline]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[inline]
+	fn rlimit(mut self, resource: RlimitResource, limit: u64) -> Self {
+		let limit = libc::rlim_t::try_from(limit).unwrap_or(libc::RLIM_INFINITY);
+		self.rlimits.retain(|(existing, _)| *existing != resource);
+		self.rlimits.push((resource, limit));
+		self
+	}
+
+	/// Limits the size of the child process' virtual memory, in bytes (`RLIMIT_AS`).
+	///
+	/// Allocations beyond this limit fail, which usually aborts the child process.
+	#[inline]
+	pub fn address_space(self, bytes: u64) -> Self {
+		self.rlimit(libc::RLIMIT_AS, bytes)
+	}
+
+	/// Limits the amount of CPU time the child process can use, rounded up to the nearest second (`RLIMIT_CPU`).
+	///
+	/// The child process is sent `SIGXCPU` when it reaches this limit, which kills it by default.
+	#[inline]
+	pub fn cpu_time(self, time: Duration) -> Self {
+		self.rlimit(libc::RLIMIT_CPU, time.as_secs() + u64::from(time.subsec_nanos() != 0))
+	}
+
+	/// Limits the number of files the child process can have open at once (`RLIMIT_NOFILE`).
+	///
+	/// The child process' end of the viaduct is already open when this is applied, so it doesn't need to fit within the limit.
+	#[inline]
+	pub fn open_files(self, count: u64) -> Self {
+		self.rlimit(libc::RLIMIT_NOFILE, count)
+	}
+
+	/// Limits the size of the files the child process can write, in bytes (`RLIMIT_FSIZE`).
+	#[inline]
+	pub fn file_size(self, bytes: u64) -> Self {
+		self.rlimit(libc::RLIMIT_FSIZE, bytes)
+	}
+
+	/// Limits the size of the core dump the child process produces if it crashes, in bytes (`RLIMIT_CORE`). Use zero to disable core dumps.
+	#[inline]
+	pub fn core_size(self, bytes: u64) -> Self {
+		self.rlimit(libc::RLIMIT_CORE, bytes)
+	}
+
+	/// Moves the child process into the cgroup at `path` (for example, `/sys/fs/cgroup/sandbox`), so that it's subject to the cgroup's limits.
+	///
+	/// The cgroup must already exist, and the parent process must be allowed to move processes into it.
+	///
+	/// Only supported on Linux.
+	#[cfg(target_os = "linux")]
+	#[inline]
+	pub fn cgroup(mut self, path: impl AsRef<Path>) -> Self {
+		let procs = path.as_ref().join("cgroup.procs");
+		self.cgroup = Some(CString::new(procs.as_os_str().as_bytes()).expect("cgroup path contains a null byte"));
+		self
+	}
+
+	/// Applies the limits to the current process.
+	///
+	/// This runs in the child process between `fork` and `exec`, so it must not allocate.
+	pub(super) fn apply(&self) -> Result<(), std::io::Error> {
+		#[cfg(target_os = "linux")]
+		if let Some(procs) = &self.cgroup {
+			// Writing 0 moves the writing process
+			let fd = unsafe { libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+			if fd == -1 {
+				return Err(std::io::Error::last_os_error());
+			}
+			let written = unsafe { libc::write(fd, b"0".as_ptr() as *const _, 1) };
+			let err = std::io::Error::last_os_error();
+			unsafe { libc::close(fd) };
+			if written != 1 {
+				return Err(err);
+			}
+		}
+
+		for &(resource, limit) in &self.rlimits {
+			let rlimit = libc::rlimit {
+				rlim_cur: limit,
+				rlim_max: limit,
+			};
+			if unsafe { libc::setrlimit(resource, &rlimit) } == -1 {
+				return Err(std::io::Error::last_os_error());
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,75 @@
+/// Declares type aliases for both sides of a viaduct from its four message types, so that the parent and child processes can't disagree about them.
+///
+/// Every type in [`Viaduct`](crate::Viaduct), [`ViaductParent`](crate::ViaductParent) and [`ViaductChild`](crate::ViaductChild) is given from the point of view of the process it's in, so the child process' types are the parent process' the other way round. Getting this wrong compiles just fine on each side, but the viaducts won't understand each other. Instead, name each message type once, by which way it travels, and use the generated aliases in both processes:
+///
+/// | Alias | Type |
+/// |---|---|
+/// | `ParentViaduct` / `ChildViaduct` | [`Viaduct`](crate::Viaduct) |
+/// | `ParentViaductTx` / `ChildViaductTx` | [`ViaductTx`](crate::ViaductTx) |
+/// | `ParentViaductRx` / `ChildViaductRx` | [`ViaductRx`](crate::ViaductRx) |
+/// | `ParentViaductEvent` / `ChildViaductEvent` | [`ViaductEvent`](crate::ViaductEvent) |
+/// | `ParentBuilder` | [`ViaductParent`](crate::ViaductParent) |
+/// | `ChildBuilder` | [`ViaductChild`](crate::ViaductChild) |
+///
+/// The aliases are given the visibility before `type`, so they can be declared once in a library shared by both processes.
+///
+/// # Example
+///
+/// ```no_run
+/// # use viaduct::{ViaductEvent, doctest::*};
+/// viaduct::declare_viaduct! {
+///     pub type {
+///         parent_to_child_rpc: ExampleRpc,
+///         parent_to_child_request: ExampleRequest,
+///         child_to_parent_rpc: ExampleRpc,
+///         child_to_parent_request: ExampleRequest,
+///     }
+/// }
+///
+/// // In the parent process
+/// let (viaduct, mut child): (ParentViaduct, _) = ParentBuilder::new(std::process::Command::new("child.exe")).unwrap().build().unwrap();
+///
+/// // In the child process
+/// let viaduct: ChildViaduct = unsafe { ChildBuilder::new().build() }.unwrap();
+/// ```
+#[macro_export]
+macro_rules! declare_viaduct {
+	(
+		$vis:vis type {
+			parent_to_child_rpc: $parent_rpc:ty,
+			parent_to_child_request: $parent_request:ty,
+			child_to_parent_rpc: $child_rpc:ty,
+			child_to_parent_request: $child_request:ty $(,)?
+		}
+	) => {
+		/// The parent process' side of the viaduct.
+		$vis type ParentViaduct = $crate::Viaduct<$parent_rpc, $parent_request, $child_rpc, $child_request>;
+
+		/// The child process' side of the viaduct.
+		$vis type ChildViaduct = $crate::Viaduct<$child_rpc, $child_request, $parent_rpc, $parent_request>;
+
+		/// The sending half of the parent process' side of the viaduct.
+		$vis type ParentViaductTx = $crate::ViaductTx<$parent_rpc, $parent_request, $child_rpc, $child_request>;
+
+		/// The sending half of the child process' side of the viaduct.
+		$vis type ChildViaductTx = $crate::ViaductTx<$child_rpc, $child_request, $parent_rpc, $parent_request>;
+
+		/// The receiving half of the parent process' side of the viaduct.
+		$vis type ParentViaductRx = $crate::ViaductRx<$parent_rpc, $parent_request, $child_rpc, $child_request>;
+
+		/// The receiving half of the child process' side of the viaduct.
+		$vis type ChildViaductRx = $crate::ViaductRx<$child_rpc, $child_request, $parent_rpc, $parent_request>;
+
+		/// An event received by the parent process from the child process.
+		$vis type ParentViaductEvent = $crate::ViaductEvent<$parent_rpc, $parent_request, $child_rpc, $child_request>;
+
+		/// An event received by the child process from the parent process.
+		$vis type ChildViaductEvent = $crate::ViaductEvent<$child_rpc, $child_request, $parent_rpc, $parent_request>;
+
+		/// Builds the parent process' side of the viaduct.
+		$vis type ParentBuilder = $crate::ViaductParent<$parent_rpc, $parent_request, $child_rpc, $child_request>;
+
+		/// Builds the child process' side of the viaduct.
+		$vis type ChildBuilder = $crate::ViaductChild<$child_rpc, $child_request, $parent_rpc, $parent_request>;
+	};
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Cumulative counts of the messages a viaduct has sent and received, and the bytes they took up.
+///
+/// Byte counts are of payloads as they crossed the pipe (after compression, if any), not counting the few bytes of framing around each packet.
+///
+/// See [`ViaductTx::metrics`](crate::ViaductTx::metrics).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ViaductMetrics {
+	/// RPCs sent to the peer process, including every RPC in a batch.
+	pub rpcs_sent: u64,
+
+	/// Requests sent to the peer process.
+	pub requests_sent: u64,
+
+	/// Responses received from the peer process, including responses that had no payload.
+	pub responses_received: u64,
+
+	/// Payload bytes written to the pipe.
+	pub bytes_written: u64,
+
+	/// Payload bytes read from the pipe.
+	pub bytes_read: u64,
+}
+
+/// Records [`ViaductMetrics`] for a viaduct, shared by both of its halves.
+#[derive(Default)]
+pub(super) struct MetricsRecorder {
+	rpcs_sent: AtomicU64,
+	requests_sent: AtomicU64,
+	responses_received: AtomicU64,
+	bytes_written: AtomicU64,
+	bytes_read: AtomicU64,
+}
+impl MetricsRecorder {
+	#[inline]
+	pub(super) fn get(&self) -> ViaductMetrics {
+		ViaductMetrics {
+			rpcs_sent: self.rpcs_sent.load(Ordering::Relaxed),
+			requests_sent: self.requests_sent.load(Ordering::Relaxed),
+			responses_received: self.responses_received.load(Ordering::Relaxed),
+			bytes_written: self.bytes_written.load(Ordering::Relaxed),
+			bytes_read: self.bytes_read.load(Ordering::Relaxed),
+		}
+	}
+
+	#[inline]
+	pub(super) fn record_rpcs_sent(&self, count: u64) {
+		self.rpcs_sent.fetch_add(count, Ordering::Relaxed);
+	}
+
+	#[inline]
+	pub(super) fn record_request_sent(&self) {
+		self.requests_sent.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[inline]
+	pub(super) fn record_response_received(&self) {
+		self.responses_received.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[inline]
+	pub(super) fn record_written(&self, bytes: usize) {
+		self.bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	#[inline]
+	pub(super) fn record_read(&self, bytes: usize) {
+		self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+}
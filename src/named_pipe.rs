@@ -0,0 +1,107 @@
+use crate::os::RawPipe;
+use interprocess::{
+	os::windows::named_pipe::{ByteReaderPipeStream, ByteWriterPipeStream, PipeListener, PipeListenerOptions, PipeStream},
+	unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter},
+};
+use std::{
+	ffi::OsString,
+	mem::ManuallyDrop,
+	num::NonZeroU8,
+	os::windows::io::{AsRawHandle, RawHandle},
+	process::Child,
+	time::Duration,
+};
+
+/// The parent's side of a data channel made of named pipes, which the child connects to by name rather than inheriting a handle.
+pub(super) struct NamedPipes {
+	name: String,
+	to_child: PipeListener<ByteWriterPipeStream>,
+	from_child: PipeListener<ByteReaderPipeStream>,
+}
+impl NamedPipes {
+	pub(super) fn new() -> Result<Self, std::io::Error> {
+		let name = format!("viaduct-{}", uuid::Uuid::new_v4());
+		Ok(Self {
+			to_child: Self::listener(format!("{name}-child"))?,
+			from_child: Self::listener(format!("{name}-parent"))?,
+			name,
+		})
+	}
+
+	fn listener<Stream: PipeStream>(name: String) -> Result<PipeListener<Stream>, std::io::Error> {
+		PipeListenerOptions::new()
+			.name(OsString::from(name))
+			.instance_limit(NonZeroU8::new(1))
+			.create()
+	}
+
+	/// The name of the pipe the child writes to.
+	pub(super) fn parent_name(&self) -> String {
+		format!("{}-parent", self.name)
+	}
+
+	/// The name of the pipe the child reads from.
+	pub(super) fn child_name(&self) -> String {
+		format!("{}-child", self.name)
+	}
+
+	/// Waits for the child process to connect to both pipes.
+	pub(super) fn accept(self, child: &mut Child) -> Result<(UnnamedPipeWriter, UnnamedPipeReader), std::io::Error> {
+		let (tx, rx) = std::thread::scope(|scope| {
+			let accepting = scope.spawn(|| Ok::<_, std::io::Error>((self.to_child.accept()?, self.from_child.accept()?)));
+
+			while !accepting.is_finished() {
+				if child.try_wait()?.is_some() {
+					// Connect to the pipes ourselves so that the accepting thread is unblocked
+					let _unblock = (
+						ByteReaderPipeStream::connect(self.child_name()),
+						ByteWriterPipeStream::connect(self.parent_name()),
+					);
+					accepting.join().ok();
+
+					return Err(std::io::Error::new(
+						std::io::ErrorKind::BrokenPipe,
+						"Child process exited before connecting to the named pipes",
+					));
+				}
+				std::thread::sleep(Duration::from_millis(10));
+			}
+
+			accepting.join().unwrap()
+		})?;
+
+		// The pipe names are guessable once they're on the child's command line, so make sure it was actually the child that connected
+		if tx.client_process_id()? != child.id() || rx.client_process_id()? != child.id() {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::PermissionDenied,
+				"Named pipe was connected to by a process other than the child process",
+			));
+		}
+
+		Ok(unsafe {
+			(
+				UnnamedPipeWriter::from_raw(into_raw_handle(tx)),
+				UnnamedPipeReader::from_raw(into_raw_handle(rx)),
+			)
+		})
+	}
+}
+
+/// Connects to the parent's named pipes from the child process.
+pub(super) fn connect_writer(name: &str) -> Result<UnnamedPipeWriter, std::io::Error> {
+	let stream = ByteWriterPipeStream::connect(name)?;
+	Ok(unsafe { UnnamedPipeWriter::from_raw(into_raw_handle(stream)) })
+}
+
+/// Connects to the parent's named pipes from the child process.
+pub(super) fn connect_reader(name: &str) -> Result<UnnamedPipeReader, std::io::Error> {
+	let stream = ByteReaderPipeStream::connect(name)?;
+	Ok(unsafe { UnnamedPipeReader::from_raw(into_raw_handle(stream)) })
+}
+
+/// Takes ownership of a named pipe stream's handle, so that it can be used just like an unnamed pipe.
+fn into_raw_handle<Stream: AsRawHandle>(stream: Stream) -> RawHandle {
+	// interprocess' `IntoRawHandle` implementation for named pipe streams still closes the handle when the stream is dropped,
+	// so we have to leak the stream instead
+	ManuallyDrop::new(stream).as_raw_handle()
+}
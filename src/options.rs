@@ -0,0 +1,78 @@
+use crate::{affinity::ThreadAffinity, capabilities::Capabilities, pool::BufferPool, ChildHandle, FrameInfo, PacketType};
+use std::{sync::Arc, time::Duration};
+
+/// Observes the raw bytes of a packet's payload as it is sent or received.
+pub(super) type RawHook = Box<dyn FnMut(PacketType, &[u8]) + Send + 'static>;
+
+/// Observes every packet received, before it is dispatched.
+pub(super) type FrameHook = Box<dyn FnMut(FrameInfo) + Send + 'static>;
+
+/// Observes the round-trip time of a completed request.
+pub(super) type LatencyHook = Box<dyn FnMut(Duration) + Send + 'static>;
+
+/// Options shared by the parent and child builders, which configure the viaduct itself.
+pub(super) struct ViaductOptions {
+	pub(super) max_fragment_size: Option<usize>,
+	pub(super) max_send_size: Option<usize>,
+	pub(super) max_message_size: Option<usize>,
+	pub(super) max_reassembly_bytes: usize,
+	pub(super) max_concurrent_fragments: usize,
+	pub(super) buffer_pool: Option<Arc<dyn BufferPool>>,
+	pub(super) resync_markers: bool,
+	pub(super) timestamps: bool,
+	pub(super) buffered_writes: bool,
+	pub(super) track_responders: bool,
+	pub(super) max_outstanding_responders: Option<usize>,
+	pub(super) rpc_window: usize,
+	pub(super) rpc_credits: Option<u64>,
+	pub(super) peer_rpc_credits: Option<u64>,
+	pub(super) handshake_timeout: Option<Duration>,
+	pub(super) required_capabilities: Capabilities,
+	pub(super) reaper_affinity: ThreadAffinity,
+	pub(super) reaper_interval: Duration,
+	pub(super) on_raw_recv: Option<RawHook>,
+	pub(super) on_raw_send: Option<RawHook>,
+	pub(super) on_frame: Option<FrameHook>,
+	#[cfg(feature = "compression")]
+	pub(super) compression_threshold: Option<usize>,
+	#[cfg(windows)]
+	pub(super) peer_process: Option<std::os::windows::io::OwnedHandle>,
+	#[cfg(unix)]
+	pub(super) handle_socket: Option<std::os::unix::net::UnixDatagram>,
+	pub(super) child_handle: Option<ChildHandle>,
+}
+impl Default for ViaductOptions {
+	#[inline]
+	fn default() -> Self {
+		Self {
+			max_fragment_size: None,
+			max_send_size: None,
+			max_message_size: Some(1024 * 1024 * 1024),
+			max_reassembly_bytes: 1024 * 1024 * 1024,
+			max_concurrent_fragments: 64,
+			buffer_pool: None,
+			resync_markers: false,
+			timestamps: false,
+			buffered_writes: false,
+			track_responders: false,
+			max_outstanding_responders: None,
+			rpc_window: 64,
+			rpc_credits: None,
+			peer_rpc_credits: None,
+			handshake_timeout: None,
+			required_capabilities: Capabilities::default(),
+			reaper_affinity: ThreadAffinity::default(),
+			reaper_interval: Duration::from_secs(5),
+			on_raw_recv: None,
+			on_raw_send: None,
+			on_frame: None,
+			#[cfg(feature = "compression")]
+			compression_threshold: None,
+			#[cfg(windows)]
+			peer_process: None,
+			#[cfg(unix)]
+			handle_socket: None,
+			child_handle: None,
+		}
+	}
+}
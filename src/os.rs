@@ -2,7 +2,6 @@ use interprocess::unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter};
 
 pub(super) trait RawPipe: Sized {
 	type Raw: std::fmt::Debug;
-	fn raw(self) -> Self::Raw;
 	fn as_raw(&self) -> Self::Raw;
 	fn close(self);
 	unsafe fn from_raw(raw: Self::Raw) -> Self;
@@ -11,11 +10,6 @@ pub(super) trait RawPipe: Sized {
 impl RawPipe for UnnamedPipeReader {
 	type Raw = std::os::windows::io::RawHandle;
 
-	fn raw(self) -> Self::Raw {
-		use std::os::windows::prelude::IntoRawHandle;
-		self.into_raw_handle()
-	}
-
 	fn as_raw(&self) -> Self::Raw {
 		use std::os::windows::prelude::AsRawHandle;
 		self.as_raw_handle()
@@ -35,11 +29,6 @@ impl RawPipe for UnnamedPipeReader {
 impl RawPipe for UnnamedPipeWriter {
 	type Raw = std::os::windows::io::RawHandle;
 
-	fn raw(self) -> Self::Raw {
-		use std::os::windows::prelude::IntoRawHandle;
-		self.into_raw_handle()
-	}
-
 	fn as_raw(&self) -> Self::Raw {
 		use std::os::windows::prelude::AsRawHandle;
 		self.as_raw_handle()
@@ -59,11 +48,6 @@ impl RawPipe for UnnamedPipeWriter {
 impl RawPipe for UnnamedPipeReader {
 	type Raw = std::os::unix::io::RawFd;
 
-	fn raw(self) -> Self::Raw {
-		use std::os::unix::prelude::IntoRawFd;
-		self.into_raw_fd()
-	}
-
 	fn as_raw(&self) -> Self::Raw {
 		use std::os::unix::prelude::AsRawFd;
 		self.as_raw_fd()
@@ -83,10 +67,24 @@ impl RawPipe for UnnamedPipeReader {
 impl RawPipe for UnnamedPipeWriter {
 	type Raw = std::os::unix::io::RawFd;
 
-	fn raw(self) -> Self::Raw {
+	fn as_raw(&self) -> Self::Raw {
+		use std::os::unix::prelude::AsRawFd;
+		self.as_raw_fd()
+	}
+
+	fn close(self) {
 		use std::os::unix::prelude::IntoRawFd;
-		self.into_raw_fd()
+		unsafe { libc::close(self.into_raw_fd()) };
+	}
+
+	unsafe fn from_raw(raw: Self::Raw) -> Self {
+		use std::os::unix::prelude::FromRawFd;
+		unsafe { Self::from_raw_fd(raw) }
 	}
+}
+#[cfg(unix)]
+impl RawPipe for std::os::unix::net::UnixDatagram {
+	type Raw = std::os::unix::io::RawFd;
 
 	fn as_raw(&self) -> Self::Raw {
 		use std::os::unix::prelude::AsRawFd;
@@ -103,3 +101,453 @@ impl RawPipe for UnnamedPipeWriter {
 		unsafe { Self::from_raw_fd(raw) }
 	}
 }
+
+/// Waits until `pipe` has room for more data, returning `false` if `deadline` passes first.
+#[cfg(unix)]
+pub(super) fn wait_writable(pipe: &UnnamedPipeWriter, deadline: std::time::Instant) -> Result<bool, std::io::Error> {
+	let mut pollfd = libc::pollfd {
+		fd: pipe.as_raw(),
+		events: libc::POLLOUT,
+		revents: 0,
+	};
+	loop {
+		// Round up, so that we don't spin when there's less than a millisecond to go
+		let timeout = deadline.saturating_duration_since(std::time::Instant::now()) + std::time::Duration::from_nanos(999_999);
+		let timeout = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+
+		match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+			-1 => {
+				let err = std::io::Error::last_os_error();
+				if err.kind() != std::io::ErrorKind::Interrupted {
+					return Err(err);
+				}
+			}
+
+			0 if std::time::Instant::now() >= deadline => return Ok(false),
+
+			0 => continue,
+
+			// Errors and hangups are reported by the write itself
+			_ => return Ok(true),
+		}
+	}
+}
+
+/// Waits until `pipe` has room for more data, returning `false` if `deadline` passes first.
+///
+/// Anonymous pipes on Windows can't be polled for writability, so this always succeeds, unless the deadline has already passed.
+#[cfg(windows)]
+pub(super) fn wait_writable(_pipe: &UnnamedPipeWriter, deadline: std::time::Instant) -> Result<bool, std::io::Error> {
+	Ok(std::time::Instant::now() < deadline)
+}
+
+/// Switches `pipe` between blocking and non-blocking reads.
+#[cfg(unix)]
+pub(super) fn set_nonblocking(pipe: &UnnamedPipeReader, nonblocking: bool) -> Result<(), std::io::Error> {
+	let fd = pipe.as_raw();
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+	if flags == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	let flags = if nonblocking {
+		flags | libc::O_NONBLOCK
+	} else {
+		flags & !libc::O_NONBLOCK
+	};
+	if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+/// Switches `pipe` between blocking and non-blocking reads.
+///
+/// Anonymous pipes on Windows can't be switched to non-blocking mode, so this does nothing; the caller checks [`poll_readable`] before reading instead.
+#[cfg(windows)]
+pub(super) fn set_nonblocking(_pipe: &UnnamedPipeReader, _nonblocking: bool) -> Result<(), std::io::Error> {
+	Ok(())
+}
+
+/// Returns whether `pipe` has data to read, or has been closed, without blocking.
+#[cfg(unix)]
+pub(super) fn poll_readable(pipe: &UnnamedPipeReader) -> Result<bool, std::io::Error> {
+	let mut pollfd = libc::pollfd {
+		fd: pipe.as_raw(),
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	loop {
+		match unsafe { libc::poll(&mut pollfd, 1, 0) } {
+			-1 => {
+				let err = std::io::Error::last_os_error();
+				if err.kind() != std::io::ErrorKind::Interrupted {
+					return Err(err);
+				}
+			}
+
+			// Errors and hangups are reported by the read itself
+			ready => return Ok(ready != 0),
+		}
+	}
+}
+
+/// Returns whether `pipe` has data to read, or has been closed, without blocking.
+#[cfg(windows)]
+pub(super) fn poll_readable(pipe: &UnnamedPipeReader) -> Result<bool, std::io::Error> {
+	use windows::Win32::{Foundation::HANDLE, System::Pipes::PeekNamedPipe};
+	let mut available = 0;
+	if unsafe {
+		PeekNamedPipe(
+			HANDLE(pipe.as_raw() as _),
+			std::ptr::null_mut(),
+			0,
+			std::ptr::null_mut(),
+			&mut available,
+			std::ptr::null_mut(),
+		)
+	}
+	.as_bool()
+	{
+		Ok(available != 0)
+	} else {
+		// The pipe has most likely been closed, which the read itself will report
+		Ok(true)
+	}
+}
+
+/// Waits until `pipe` has data to read, or has been closed.
+#[cfg(unix)]
+pub(super) fn wait_readable(pipe: &UnnamedPipeReader) -> Result<(), std::io::Error> {
+	let mut pollfd = libc::pollfd {
+		fd: pipe.as_raw(),
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	loop {
+		if unsafe { libc::poll(&mut pollfd, 1, -1) } != -1 {
+			return Ok(());
+		}
+
+		let err = std::io::Error::last_os_error();
+		if err.kind() != std::io::ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+}
+
+/// Waits until `pipe` has data to read, or has been closed, returning `false` if `timeout` elapses first.
+#[cfg(unix)]
+pub(super) fn wait_readable_timeout(pipe: &UnnamedPipeReader, timeout: std::time::Duration) -> Result<bool, std::io::Error> {
+	let deadline = std::time::Instant::now() + timeout;
+	let mut pollfd = libc::pollfd {
+		fd: pipe.as_raw(),
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	loop {
+		// Round up, so we don't wake up just before the deadline and spin
+		let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+		let timeout_ms = remaining.as_nanos().div_ceil(1_000_000).min(libc::c_int::MAX as u128) as libc::c_int;
+		match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+			-1 => {
+				let err = std::io::Error::last_os_error();
+				if err.kind() != std::io::ErrorKind::Interrupted {
+					return Err(err);
+				}
+			}
+
+			ready => return Ok(ready != 0),
+		}
+	}
+}
+
+/// Waits until `pipe` has data to read, or has been closed, returning `false` if `timeout` elapses first.
+///
+/// Anonymous pipes on Windows can't be waited on, so this checks every millisecond.
+#[cfg(windows)]
+pub(super) fn wait_readable_timeout(pipe: &UnnamedPipeReader, timeout: std::time::Duration) -> Result<bool, std::io::Error> {
+	let deadline = std::time::Instant::now() + timeout;
+	loop {
+		if poll_readable(pipe)? {
+			return Ok(true);
+		}
+
+		let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+		if remaining.is_zero() {
+			return Ok(false);
+		}
+		std::thread::sleep(remaining.min(std::time::Duration::from_millis(1)));
+	}
+}
+
+/// Waits until `pipe` has data to read, or has been closed, returning `false` instead if `wake` has data to read first.
+#[cfg(unix)]
+pub(super) fn wait_readable_unless(pipe: &UnnamedPipeReader, wake: &UnnamedPipeReader) -> Result<bool, std::io::Error> {
+	let mut pollfds = [
+		libc::pollfd {
+			fd: pipe.as_raw(),
+			events: libc::POLLIN,
+			revents: 0,
+		},
+		libc::pollfd {
+			fd: wake.as_raw(),
+			events: libc::POLLIN,
+			revents: 0,
+		},
+	];
+	loop {
+		if unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as _, -1) } != -1 {
+			return Ok(pollfds[1].revents == 0);
+		}
+
+		let err = std::io::Error::last_os_error();
+		if err.kind() != std::io::ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+}
+
+/// Waits until `pipe` has data to read, or has been closed, returning `false` instead if `woken` returns `true` first.
+///
+/// Anonymous pipes on Windows can't be waited on alongside anything else, so this checks both every millisecond.
+#[cfg(windows)]
+pub(super) fn wait_readable_unless(pipe: &UnnamedPipeReader, woken: impl Fn() -> bool) -> Result<bool, std::io::Error> {
+	loop {
+		if woken() {
+			return Ok(false);
+		}
+		if poll_readable(pipe)? {
+			return Ok(true);
+		}
+		std::thread::sleep(std::time::Duration::from_millis(1));
+	}
+}
+
+/// Sleeps for `timeout`, waking up early if the read end of `pipe` is closed.
+#[cfg(unix)]
+pub(super) fn wait_hangup<Pipe: RawPipe<Raw = std::os::unix::io::RawFd>>(pipe: &Pipe, timeout: std::time::Duration) {
+	// Errors and hangups are always reported, even though we aren't waiting for anything else
+	let mut pollfd = libc::pollfd {
+		fd: pipe.as_raw(),
+		events: 0,
+		revents: 0,
+	};
+	let timeout = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+
+	// If this is interrupted, the caller just checks the pipe a little early
+	unsafe { libc::poll(&mut pollfd, 1, timeout) };
+}
+
+/// Sleeps for `timeout`.
+///
+/// Anonymous pipes on Windows can't be polled, so this can't wake up early when the read end of `pipe` is closed.
+#[cfg(windows)]
+pub(super) fn wait_hangup<Pipe: RawPipe>(_pipe: &Pipe, timeout: std::time::Duration) {
+	std::thread::sleep(timeout);
+}
+
+/// Kills a child process from another thread, without needing its [`Child`](std::process::Child).
+///
+/// The child must not have been waited on yet, otherwise its process ID may have been reused.
+pub(super) struct ProcessKiller {
+	#[cfg(unix)]
+	pid: libc::pid_t,
+
+	#[cfg(windows)]
+	process: std::os::windows::io::OwnedHandle,
+}
+impl ProcessKiller {
+	pub(super) fn new(child: &std::process::Child) -> Result<Self, std::io::Error> {
+		#[cfg(unix)]
+		return Ok(Self { pid: child.id() as _ });
+
+		#[cfg(windows)]
+		return Ok(Self {
+			process: std::os::windows::io::AsHandle::as_handle(child).try_clone_to_owned()?,
+		});
+	}
+
+	/// Kills the child process, succeeding if it has already exited, like [`Child::kill`](std::process::Child::kill).
+	pub(super) fn kill(&self) -> Result<(), std::io::Error> {
+		#[cfg(unix)]
+		{
+			// A child process that has exited but hasn't been waited on yet can still be "killed", so this only fails once it has been reaped
+			if unsafe { libc::kill(self.pid, libc::SIGKILL) } == -1 {
+				let err = std::io::Error::last_os_error();
+				if err.raw_os_error() != Some(libc::ESRCH) {
+					return Err(err);
+				}
+			}
+			Ok(())
+		}
+
+		#[cfg(windows)]
+		{
+			use std::os::windows::io::AsRawHandle;
+			use windows::Win32::{
+				Foundation::{HANDLE, WAIT_OBJECT_0},
+				System::Threading::{TerminateProcess, WaitForSingleObject},
+			};
+
+			let process = HANDLE(self.process.as_raw_handle() as _);
+			if !unsafe { TerminateProcess(process, 1) }.as_bool() {
+				// Terminating a process that has already exited fails, but it's as dead as we wanted it to be
+				let err = std::io::Error::last_os_error();
+				if unsafe { WaitForSingleObject(process, 0) } != WAIT_OBJECT_0.0 {
+					return Err(err);
+				}
+			}
+			Ok(())
+		}
+	}
+}
+
+/// Finds out a child process' exit status from another thread, without needing its [`Child`](std::process::Child) or reaping it.
+pub(super) struct ExitWatcher {
+	#[cfg(unix)]
+	pid: libc::pid_t,
+
+	#[cfg(windows)]
+	process: std::os::windows::io::OwnedHandle,
+}
+impl ExitWatcher {
+	pub(super) fn new(child: &std::process::Child) -> Result<Self, std::io::Error> {
+		#[cfg(unix)]
+		return Ok(Self { pid: child.id() as _ });
+
+		#[cfg(windows)]
+		return Ok(Self {
+			process: std::os::windows::io::AsHandle::as_handle(child).try_clone_to_owned()?,
+		});
+	}
+
+	/// Returns the child process' exit status, or `None` if it is still running.
+	///
+	/// On Unix, this fails if the child process has already been reaped.
+	#[cfg(unix)]
+	pub(super) fn try_exit_status(&self) -> Result<Option<std::process::ExitStatus>, std::io::Error> {
+		use std::os::unix::process::ExitStatusExt;
+
+		// WNOWAIT leaves the child process to be reaped by whoever owns its `Child`
+		let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+		if unsafe { libc::waitid(libc::P_PID, self.pid as _, &mut info, libc::WEXITED | libc::WNOHANG | libc::WNOWAIT) } == -1 {
+			return Err(std::io::Error::last_os_error());
+		}
+		if unsafe { info.si_pid() } == 0 {
+			return Ok(None);
+		}
+
+		// Put the status back together in the form `waitpid` would have returned it in
+		let status = unsafe { info.si_status() };
+		Ok(Some(std::process::ExitStatus::from_raw(match info.si_code {
+			libc::CLD_EXITED => (status & 0xff) << 8,
+			libc::CLD_DUMPED => status | 0x80,
+			_ => status,
+		})))
+	}
+
+	/// Returns the child process' exit status, or `None` if it is still running.
+	#[cfg(windows)]
+	pub(super) fn try_exit_status(&self) -> Result<Option<std::process::ExitStatus>, std::io::Error> {
+		use std::os::windows::{io::AsRawHandle, process::ExitStatusExt};
+		use windows::Win32::{
+			Foundation::{HANDLE, WAIT_OBJECT_0},
+			System::Threading::{GetExitCodeProcess, WaitForSingleObject},
+		};
+
+		let process = HANDLE(self.process.as_raw_handle() as _);
+		if unsafe { WaitForSingleObject(process, 0) } != WAIT_OBJECT_0.0 {
+			return Ok(None);
+		}
+
+		let mut code = 0;
+		if !unsafe { GetExitCodeProcess(process, &mut code) }.as_bool() {
+			return Err(std::io::Error::last_os_error());
+		}
+		Ok(Some(std::process::ExitStatus::from_raw(code)))
+	}
+}
+
+/// Configures `command` to spawn a process that is detached from this one, in its own session.
+#[cfg(unix)]
+pub(super) fn detach(command: &mut std::process::Command) {
+	use std::os::unix::process::CommandExt;
+
+	// Safety: setsid is async-signal-safe, so it can be called between fork and exec
+	unsafe {
+		command.pre_exec(|| {
+			if libc::setsid() == -1 {
+				return Err(std::io::Error::last_os_error());
+			}
+			Ok(())
+		})
+	};
+}
+
+/// Configures `command` to spawn a process that is detached from this one, without a console and in its own process group.
+#[cfg(windows)]
+pub(super) fn detach(command: &mut std::process::Command) {
+	use std::os::windows::process::CommandExt;
+	use windows::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+	command.creation_flags(DETACHED_PROCESS.0 | CREATE_NEW_PROCESS_GROUP.0);
+}
+
+/// Writes as much of `bufs` to `pipe` as it can in a single `writev`, returning how many bytes were written.
+#[cfg(unix)]
+pub(super) fn write_vectored(pipe: &UnnamedPipeWriter, bufs: &[std::io::IoSlice<'_>]) -> Result<usize, std::io::Error> {
+	// IoSlice is guaranteed to be ABI compatible with iovec on Unix
+	let iovcnt = bufs.len().min(libc::c_int::MAX as usize) as libc::c_int;
+	let written = unsafe { libc::writev(pipe.as_raw(), bufs.as_ptr().cast(), iovcnt) };
+	if written == -1 {
+		Err(std::io::Error::last_os_error())
+	} else {
+		Ok(written as usize)
+	}
+}
+
+/// Stops `pipe` from being inherited by child processes spawned from now on.
+#[cfg(unix)]
+pub(super) fn disinherit<Pipe: RawPipe<Raw = std::os::unix::io::RawFd>>(pipe: &Pipe) -> Result<(), std::io::Error> {
+	let fd = pipe.as_raw();
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+	if flags == -1 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+	Ok(())
+}
+
+/// Stops `pipe` from being inherited by child processes spawned from now on.
+#[cfg(windows)]
+pub(super) fn disinherit<Pipe: RawPipe<Raw = std::os::windows::io::RawHandle>>(pipe: &Pipe) -> Result<(), std::io::Error> {
+	use windows::Win32::Foundation::{SetHandleInformation, HANDLE, HANDLE_FLAGS, HANDLE_FLAG_INHERIT};
+	if unsafe { SetHandleInformation(HANDLE(pipe.as_raw() as _), HANDLE_FLAG_INHERIT.0, HANDLE_FLAGS(0)) }.as_bool() {
+		Ok(())
+	} else {
+		Err(std::io::Error::last_os_error())
+	}
+}
+
+/// Reads the system-wide monotonic clock, in nanoseconds.
+///
+/// Unlike [`Instant`](std::time::Instant), readings can be compared between processes on the same machine.
+#[cfg(unix)]
+pub(super) fn monotonic_nanos() -> u64 {
+	let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+	unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time) };
+	time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+/// Reads the system-wide monotonic clock, in nanoseconds.
+///
+/// Unlike [`Instant`](std::time::Instant), readings can be compared between processes on the same machine.
+#[cfg(windows)]
+pub(super) fn monotonic_nanos() -> u64 {
+	use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+	let (mut counter, mut frequency) = (0, 0);
+	unsafe {
+		QueryPerformanceCounter(&mut counter);
+		QueryPerformanceFrequency(&mut frequency);
+	}
+	(counter as u128 * 1_000_000_000 / frequency.max(1) as u128) as u64
+}
@@ -0,0 +1,57 @@
+use parking_lot::Mutex;
+
+/// A pool of buffers that the receiving side of a viaduct can recycle, rather than allocating a new buffer for each packet.
+///
+/// See [`ViaductParent::buffer_pool`](crate::ViaductParent::buffer_pool) and [`ViaductChild::buffer_pool`](crate::ViaductChild::buffer_pool).
+pub trait BufferPool: Send + Sync + 'static {
+	/// Takes a buffer out of the pool.
+	///
+	/// Viaduct will clear and resize the buffer as needed.
+	fn acquire(&self) -> Vec<u8>;
+
+	/// Returns a buffer to the pool once Viaduct is done with it.
+	fn release(&self, buf: Vec<u8>);
+}
+
+/// A simple [`BufferPool`] which holds onto up to a fixed number of buffers.
+#[derive(Debug)]
+pub struct SimpleBufferPool {
+	buffers: Mutex<Vec<Vec<u8>>>,
+	max_buffers: usize,
+}
+impl SimpleBufferPool {
+	/// Creates a new, empty pool which will hold onto up to `max_buffers` buffers.
+	#[inline]
+	pub fn new(max_buffers: usize) -> Self {
+		Self {
+			buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+			max_buffers,
+		}
+	}
+}
+impl BufferPool for SimpleBufferPool {
+	#[inline]
+	fn acquire(&self) -> Vec<u8> {
+		self.buffers.lock().pop().unwrap_or_default()
+	}
+
+	#[inline]
+	fn release(&self, mut buf: Vec<u8>) {
+		let mut buffers = self.buffers.lock();
+		if buffers.len() < self.max_buffers {
+			buf.clear();
+			buffers.push(buf);
+		}
+	}
+}
+impl<P: BufferPool> BufferPool for std::sync::Arc<P> {
+	#[inline]
+	fn acquire(&self) -> Vec<u8> {
+		(**self).acquire()
+	}
+
+	#[inline]
+	fn release(&self, buf: Vec<u8>) {
+		(**self).release(buf)
+	}
+}
@@ -1,12 +1,29 @@
-use crate::os::RawPipe;
+use crate::{
+	affinity::ThreadAffinity,
+	os::{ExitWatcher, RawPipe},
+};
 use interprocess::unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter};
 use std::{
 	io::{Read, Write},
-	time::Duration,
+	process::ExitStatus,
+	time::{Duration, Instant},
 };
 
 pub(super) type ReaperCallbackFn = Box<dyn FnOnce() + Send + 'static>;
 
+/// The parent's reaper callback, which is told how the child process exited.
+pub(super) type ParentReaperCallbackFn = Box<dyn FnOnce(Option<ExitStatus>) + Send + 'static>;
+
+/// The shortest interval the reaper thread is allowed to check on the peer process at, so that it doesn't spin.
+pub(super) const MIN_REAPER_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Our end of the reaper pipe, which is kept alive alongside the viaduct if a reaper thread wasn't requested.
+#[allow(dead_code)] // Only held so that the pipe is closed when the viaduct is dropped
+pub(super) enum ReaperPipe {
+	Writer(DroppablePipe<UnnamedPipeWriter>),
+	Reader(DroppablePipe<UnnamedPipeReader>),
+}
+
 pub(super) struct DroppablePipe<Pipe: RawPipe>(Option<Pipe>);
 impl<Pipe: RawPipe> DroppablePipe<Pipe> {
 	#[inline]
@@ -17,10 +34,6 @@ impl<Pipe: RawPipe> DroppablePipe<Pipe> {
 impl<Pipe: RawPipe> RawPipe for DroppablePipe<Pipe> {
 	type Raw = Pipe::Raw;
 
-	fn raw(mut self) -> Self::Raw {
-		self.0.take().unwrap().raw()
-	}
-
 	fn as_raw(&self) -> Self::Raw {
 		self.0.as_ref().unwrap().as_raw()
 	}
@@ -70,26 +83,64 @@ impl<Pipe: RawPipe + Read> Read for DroppablePipe<Pipe> {
 	}
 }
 
-pub(crate) unsafe fn child(mut reaper_pipe: DroppablePipe<UnnamedPipeReader>, callback: ReaperCallbackFn) {
+pub(crate) unsafe fn child(
+	mut reaper_pipe: DroppablePipe<UnnamedPipeReader>,
+	callback: ReaperCallbackFn,
+	affinity: ThreadAffinity,
+	interval: Duration,
+) {
 	std::thread::spawn(move || {
+		affinity.apply();
+
 		loop {
 			match reaper_pipe.read(&mut [0]) {
 				Ok(0) | Err(_) => break,
-				_ => std::thread::sleep(Duration::from_secs(5)),
+				_ => std::thread::sleep(interval),
 			}
 		}
+
+		// Close the pipe first, so the callback doesn't see it as still open
+		drop(reaper_pipe);
 		callback();
 	});
 }
 
-pub(crate) unsafe fn parent(mut reaper_pipe: DroppablePipe<UnnamedPipeWriter>, callback: ReaperCallbackFn) {
+pub(crate) unsafe fn parent(
+	mut reaper_pipe: DroppablePipe<UnnamedPipeWriter>,
+	callback: ParentReaperCallbackFn,
+	affinity: ThreadAffinity,
+	interval: Duration,
+	child: ExitWatcher,
+) {
 	std::thread::spawn(move || {
+		affinity.apply();
+
 		loop {
 			match reaper_pipe.write(&[0]) {
 				Ok(0) | Err(_) => break,
-				_ => std::thread::sleep(Duration::from_secs(5)),
+
+				// The next write will fail once the child's end is closed, so we can go straight to it when that happens
+				_ => crate::os::wait_hangup(&reaper_pipe, interval),
 			}
 		}
-		callback();
+		let status = exit_status(child);
+
+		// Close the pipe first, so the callback doesn't see it as still open
+		drop(reaper_pipe);
+		callback(status);
 	});
 }
+
+/// Gives a child process that hung up on the reaper a moment to exit, returning its exit status if it does.
+///
+/// Consumes the watcher, so that it's closed before the reaper calls back.
+fn exit_status(child: ExitWatcher) -> Option<ExitStatus> {
+	let deadline = Instant::now() + Duration::from_millis(500);
+	loop {
+		match child.try_exit_status() {
+			Ok(Some(status)) => return Some(status),
+			Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+			_ => return None,
+		}
+	}
+}
@@ -0,0 +1,48 @@
+use crate::{CloseReason, Viaduct, ViaductDeserialize, ViaductParent, ViaductSerialize, ViaductTx};
+use std::process::Child;
+
+/// Makes the [`ViaductParent`] that each viaduct built by a [`ViaductRebuilder`] comes from.
+type ParentFactory<RpcTx, RequestTx, RpcRx, RequestRx> =
+	Box<dyn FnMut() -> Result<ViaductParent<RpcTx, RequestTx, RpcRx, RequestRx>, std::io::Error> + Send>;
+
+/// Builds a viaduct, and builds it again with a freshly spawned child process whenever it is needed, such as after the child process has crashed.
+///
+/// A [`ViaductParent`] is used up by building it, so the rebuilder is given a function that sets up a new one (with the same command, reaper callback and other configuration) every time the viaduct is built. See [`ViaductParent::rebuilder`].
+pub struct ViaductRebuilder<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	factory: ParentFactory<RpcTx, RequestTx, RpcRx, RequestRx>,
+	current: Option<ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>>,
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRebuilder<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	#[inline]
+	pub(super) fn new(factory: ParentFactory<RpcTx, RequestTx, RpcRx, RequestRx>) -> Self {
+		Self { factory, current: None }
+	}
+
+	/// Spawns a new child process with fresh pipes and builds a viaduct to it, invalidating the viaduct built last time, if any.
+	///
+	/// The old viaduct's sending side is closed, so sending anything through any clone of its [`ViaductTx`] fails with a [`BrokenPipe`](std::io::ErrorKind::BrokenPipe) error, and its close reason becomes [`CloseReason::Rebuilt`] unless it had already been closed for another reason. In-flight requests on the old viaduct are dropped rather than drained or resent: every request still waiting for a response fails straight away with the old viaduct's close reason (usually [`ViaductError::PeerGone`](crate::ViaductError::PeerGone), if its event loop noticed the child process die first). The old [`ViaductRx`](crate::ViaductRx)'s event loop stops on its own once the old child process has exited.
+	///
+	/// The old child process is left alone, as it belongs to whoever holds its [`Child`]. If it's still running, kill it first.
+	#[allow(clippy::type_complexity)]
+	pub fn rebuild(&mut self) -> Result<(Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, Child), std::io::Error> {
+		if let Some(old) = self.current.take() {
+			old.invalidate(CloseReason::Rebuilt);
+		}
+
+		let (viaduct, child) = (self.factory)()?.build()?;
+		self.current = Some(viaduct.tx().clone());
+		Ok((viaduct, child))
+	}
+}
@@ -0,0 +1,156 @@
+use crate::{ViaductDeserialize, ViaductEvent, ViaductRequestResponder, ViaductRx, ViaductSerialize};
+
+type RpcHandler<RpcRx> = Box<dyn FnMut(RpcRx) -> Result<(), RpcRx> + Send>;
+
+type RequestHandler<RpcTx, RequestTx, RpcRx, RequestRx> = Box<
+	dyn FnMut(
+			RequestRx,
+			ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>,
+		) -> Result<(), (RequestRx, ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>)>
+		+ Send,
+>;
+
+/// The handlers registered with [`ViaductRx::on`] and [`ViaductRx::on_request`].
+pub(super) struct Registry<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	rpc: Vec<RpcHandler<RpcRx>>,
+	request: Vec<RequestHandler<RpcTx, RequestTx, RpcRx, RequestRx>>,
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Default for Registry<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	#[inline]
+	fn default() -> Self {
+		Self {
+			rpc: Vec::new(),
+			request: Vec::new(),
+		}
+	}
+}
+impl<RpcTx, RequestTx, RpcRx, RequestRx> Registry<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	/// Hands the event to the first handler that accepts it.
+	fn dispatch(&mut self, event: ViaductEvent<RpcTx, RequestTx, RpcRx, RequestRx>) {
+		match event {
+			ViaductEvent::Rpc(mut rpc) => {
+				for handler in &mut self.rpc {
+					match handler(rpc) {
+						Ok(()) => return,
+						Err(unhandled) => rpc = unhandled,
+					}
+				}
+
+				#[cfg(feature = "tracing")]
+				tracing::warn!("viaduct received an RPC with no registered handler");
+			}
+
+			ViaductEvent::Request { mut request, mut responder } => {
+				for handler in &mut self.request {
+					match handler(request, responder) {
+						Ok(()) => return,
+						Err(unhandled) => (request, responder) = unhandled,
+					}
+				}
+
+				#[cfg(feature = "tracing")]
+				tracing::warn!("viaduct received a request with no registered handler");
+
+				// Dropping the responder lets the peer know there's no response coming
+			}
+
+			ViaductEvent::Handle(_) => {
+				#[cfg(feature = "tracing")]
+				tracing::warn!("viaduct received a handle, which can't be handled by a registry");
+			}
+		}
+	}
+}
+
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductRx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize + 'static,
+	RequestTx: ViaductSerialize + 'static,
+	RpcRx: ViaductDeserialize + 'static,
+	RequestRx: ViaductDeserialize + 'static,
+{
+	/// Registers a handler for RPCs that can be converted into `T`, for use with [`ViaductRx::run_registry`].
+	///
+	/// This lets each module of a large protocol register handlers for its own messages, rather than handling every message in one big `match`. Typically, `RpcRx` is an enum and `T` is the contents of one of its variants, with a [`TryFrom`] implementation that gives the RPC back if it's a different variant.
+	///
+	/// Handlers are tried in the order they were registered, and each RPC is handled by the first one that accepts it.
+	///
+	/// # Example
+	///
+	/// ```no_run
+	/// # use viaduct::{ViaductChild, doctest::{ExampleRequest, ExampleRpc}};
+	/// # let mut rx = unsafe { ViaductChild::<ExampleRpc, ExampleRequest, ExampleRpc, ExampleRequest>::new().build() }.unwrap().split().1;
+	/// struct Moo;
+	/// impl TryFrom<ExampleRpc> for Moo {
+	///     type Error = ExampleRpc;
+	///
+	///     fn try_from(rpc: ExampleRpc) -> Result<Self, ExampleRpc> {
+	///         match rpc {
+	///             ExampleRpc::Cow => Ok(Moo),
+	///             rpc => Err(rpc),
+	///         }
+	///     }
+	/// }
+	///
+	/// rx.on::<Moo>(|Moo| println!("Moo"));
+	/// rx.run_registry().unwrap();
+	/// ```
+	pub fn on<T>(&mut self, mut handler: impl FnMut(T) + Send + 'static) -> &mut Self
+	where
+		T: TryFrom<RpcRx, Error = RpcRx> + 'static,
+	{
+		self.registry.rpc.push(Box::new(move |rpc| {
+			handler(T::try_from(rpc)?);
+			Ok(())
+		}));
+		self
+	}
+
+	/// Registers a handler for requests that can be converted into `T`, for use with [`ViaductRx::run_registry`].
+	///
+	/// See [`ViaductRx::on`].
+	pub fn on_request<T>(
+		&mut self,
+		mut handler: impl FnMut(T, ViaductRequestResponder<RpcTx, RequestTx, RpcRx, RequestRx>) + Send + 'static,
+	) -> &mut Self
+	where
+		T: TryFrom<RequestRx, Error = RequestRx> + 'static,
+	{
+		self.registry.request.push(Box::new(move |request, responder| {
+			match T::try_from(request) {
+				Ok(request) => handler(request, responder),
+				Err(request) => return Err((request, responder)),
+			}
+			Ok(())
+		}));
+		self
+	}
+
+	/// Runs the event loop, routing each RPC and request to the handler registered for it with [`ViaductRx::on`] or [`ViaductRx::on_request`]. This function will never return unless an error occurs.
+	///
+	/// RPCs without a handler are discarded. Requests without a handler are dropped, so the peer receives `None` as the response.
+	///
+	/// See [`ViaductRx::run`] for more information.
+	pub fn run_registry(mut self) -> Result<(), std::io::Error> {
+		let mut registry = std::mem::take(&mut self.registry);
+		self.run(move |event| registry.dispatch(event))
+	}
+}
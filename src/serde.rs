@@ -1,3 +1,5 @@
+use std::{borrow::Cow, ops::Deref};
+
 /// Types that can be serialized and deserialized for crossing the viaduct.
 pub trait ViaductSerialize {
 	/// The error returned if we fail to serialize the data.
@@ -14,12 +16,55 @@ pub trait ViaductDeserialize: Sized {
 	/// The error returned if we fail to deserialize the data.
 	type Error: std::fmt::Debug;
 
+	/// Whether this type has no values, like [`Never`], so that it can never be received.
+	///
+	/// The event loop refuses RPCs and requests of a type that is uninhabited with an error, without trying to deserialize them. Defaults to `false`.
+	const UNINHABITED: bool = false;
+
 	/// Deserialize this type from the given slice.
 	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error>;
 }
 
+/// Types whose archived form can be accessed where it was received, without deserializing it.
+///
+/// This is implemented for every type that can cross the viaduct using the `rkyv` backend; see [`LazyMessage::archived`](crate::LazyMessage::archived).
+///
+/// Requires the `rkyv` feature.
+#[cfg(feature = "rkyv")]
+pub trait ViaductArchived: ViaductDeserialize + ::rkyv::Archive {
+	/// Access the archived form of this type in the given slice, without deserializing it.
+	///
+	/// rkyv requires the slice to be aligned to the alignment of the archived type, and returns an error if it isn't. Viaduct receives each payload into the start of its own buffer, which the global allocator aligns to at least 8 bytes (16 bytes on most 64-bit platforms), so this only matters for archived types that need more than that, or with a global allocator that aligns byte buffers less strictly.
+	fn from_pipeable_archived(bytes: &[u8]) -> Result<&::rkyv::Archived<Self>, Self::Error>;
+}
+
+/// Returns the name of the serialization backend that Viaduct was compiled with.
+///
+/// This is `"bincode"`, `"speedy"`, `"postcard"`, `"json"`, `"rkyv"` or `"bytemuck"` depending on the enabled Cargo feature, or `"none"` if no serialization backend is enabled.
+///
+/// Both sides of a viaduct must be using the same backend, which is checked during the handshake.
+pub const fn backend_name() -> &'static str {
+	if cfg!(feature = "bincode") {
+		"bincode"
+	} else if cfg!(feature = "speedy") {
+		"speedy"
+	} else if cfg!(feature = "postcard") {
+		"postcard"
+	} else if cfg!(feature = "json") {
+		"json"
+	} else if cfg!(feature = "rkyv") {
+		"rkyv"
+	} else if cfg!(feature = "bytemuck") {
+		"bytemuck"
+	} else {
+		"none"
+	}
+}
+
 #[derive(Clone, Copy, Debug)]
 /// You can use this type (which implements [`ViaductSerialize`] and [`ViaductDeserialize`]) to specify that this type of packet (RCP/request) will never happen.
+///
+/// If the peer process sends a packet of this type anyway, the event loop returns an error of kind [`InvalidData`](std::io::ErrorKind::InvalidData). See also [`RpcOnly`](crate::RpcOnly) and [`RequestOnly`](crate::RequestOnly).
 pub enum Never {}
 impl ViaductSerialize for Never {
 	type Error = std::convert::Infallible;
@@ -31,11 +76,90 @@ impl ViaductSerialize for Never {
 impl ViaductDeserialize for Never {
 	type Error = std::convert::Infallible;
 
+	const UNINHABITED: bool = true;
+
 	fn from_pipeable(_bytes: &[u8]) -> Result<Self, Self::Error> {
 		unreachable!()
 	}
 }
 
+/// Bytes that cross the viaduct verbatim, whichever serialization backend is enabled.
+///
+/// This is an escape hatch for payloads that you encode yourself, or that are just passed through, so there's no need to derive anything for them. `Vec<u8>` and `Cow<[u8]>` can't be used for this directly, as the serialization backends already implement [`ViaductSerialize`] and [`ViaductDeserialize`] for them in their own formats, so wrap them in this instead. The bytes are written into the packet as they are, and received into an owned buffer.
+///
+/// # Example
+///
+/// ```no_run
+/// # use viaduct::{RawBytes, ViaductChild};
+/// let (tx, rx) = unsafe { ViaductChild::<RawBytes, (), RawBytes, ()>::new().build() }.unwrap().split();
+/// tx.rpc(RawBytes::from(b"already encoded".as_slice())).unwrap();
+/// tx.rpc(RawBytes::from(vec![1, 2, 3])).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RawBytes<'a>(pub Cow<'a, [u8]>);
+impl RawBytes<'_> {
+	/// Returns the bytes, copying them if they are borrowed.
+	#[inline]
+	pub fn into_vec(self) -> Vec<u8> {
+		self.0.into_owned()
+	}
+}
+impl Deref for RawBytes<'_> {
+	type Target = [u8];
+
+	#[inline]
+	fn deref(&self) -> &[u8] {
+		&self.0
+	}
+}
+impl AsRef<[u8]> for RawBytes<'_> {
+	#[inline]
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+impl From<Vec<u8>> for RawBytes<'_> {
+	#[inline]
+	fn from(bytes: Vec<u8>) -> Self {
+		Self(Cow::Owned(bytes))
+	}
+}
+impl<'a> From<&'a [u8]> for RawBytes<'a> {
+	#[inline]
+	fn from(bytes: &'a [u8]) -> Self {
+		Self(Cow::Borrowed(bytes))
+	}
+}
+impl<'a> From<Cow<'a, [u8]>> for RawBytes<'a> {
+	#[inline]
+	fn from(bytes: Cow<'a, [u8]>) -> Self {
+		Self(bytes)
+	}
+}
+impl From<RawBytes<'_>> for Vec<u8> {
+	#[inline]
+	fn from(bytes: RawBytes<'_>) -> Self {
+		bytes.into_vec()
+	}
+}
+impl ViaductSerialize for RawBytes<'_> {
+	type Error = std::convert::Infallible;
+
+	#[inline]
+	fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+		buf.extend_from_slice(&self.0);
+		Ok(())
+	}
+}
+impl ViaductDeserialize for RawBytes<'_> {
+	type Error = std::convert::Infallible;
+
+	#[inline]
+	fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(Self(Cow::Owned(bytes.to_vec())))
+	}
+}
+
 #[cfg(feature = "bincode")]
 mod bincode {
 	use super::{ViaductDeserialize, ViaductSerialize};
@@ -86,7 +210,108 @@ mod speedy {
 	}
 }
 
-#[cfg(all(feature = "bytemuck", not(any(feature = "bincode", feature = "speedy"))))]
+#[cfg(feature = "postcard")]
+mod postcard {
+	use super::{ViaductDeserialize, ViaductSerialize};
+
+	impl<T: serde::Serialize> ViaductSerialize for T {
+		type Error = postcard::Error;
+
+		#[inline]
+		fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+			postcard::to_io(self, buf).map(drop)
+		}
+	}
+	impl<T: serde::de::DeserializeOwned> ViaductDeserialize for T {
+		type Error = postcard::Error;
+
+		#[inline]
+		fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+			postcard::from_bytes(bytes)
+		}
+	}
+}
+
+#[cfg(feature = "json")]
+mod json {
+	use super::{ViaductDeserialize, ViaductSerialize};
+
+	impl<T: serde::Serialize> ViaductSerialize for T {
+		type Error = serde_json::Error;
+
+		#[inline]
+		fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+			serde_json::to_writer(buf, self)
+		}
+	}
+	impl<T: serde::de::DeserializeOwned> ViaductDeserialize for T {
+		type Error = serde_json::Error;
+
+		#[inline]
+		fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+			serde_json::from_slice(bytes)
+		}
+	}
+}
+
+#[cfg(feature = "rkyv")]
+mod rkyv {
+	use super::{ViaductArchived, ViaductDeserialize, ViaductSerialize};
+	use rkyv::{
+		api::high::{HighDeserializer, HighSerializer, HighValidator},
+		bytecheck::CheckBytes,
+		rancor,
+		ser::allocator::ArenaHandle,
+		util::AlignedVec,
+		Archive, Archived, Deserialize, Serialize,
+	};
+
+	impl<T> ViaductSerialize for T
+	where
+		T: for<'a, 'b> Serialize<HighSerializer<&'b mut Vec<u8>, ArenaHandle<'a>, rancor::Error>>,
+	{
+		type Error = rancor::Error;
+
+		#[inline]
+		fn to_pipeable(&self, buf: &mut Vec<u8>) -> Result<(), Self::Error> {
+			rkyv::api::high::to_bytes_in(self, buf).map(drop)
+		}
+	}
+	impl<T> ViaductDeserialize for T
+	where
+		T: Archive,
+		T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>> + Deserialize<T, HighDeserializer<rancor::Error>>,
+	{
+		type Error = rancor::Error;
+
+		#[inline]
+		fn from_pipeable(bytes: &[u8]) -> Result<Self, Self::Error> {
+			if (bytes.as_ptr() as usize).is_multiple_of(std::mem::align_of::<Archived<T>>()) {
+				rkyv::from_bytes(bytes)
+			} else {
+				// Unlike accessing the archive in place, deserializing can afford to copy the bytes somewhere that is aligned
+				let mut aligned = AlignedVec::<16>::with_capacity(bytes.len());
+				aligned.extend_from_slice(bytes);
+				rkyv::from_bytes(&aligned)
+			}
+		}
+	}
+	impl<T> ViaductArchived for T
+	where
+		T: Archive,
+		T::Archived: for<'a> CheckBytes<HighValidator<'a, rancor::Error>> + Deserialize<T, HighDeserializer<rancor::Error>>,
+	{
+		#[inline]
+		fn from_pipeable_archived(bytes: &[u8]) -> Result<&Archived<Self>, Self::Error> {
+			rkyv::access(bytes)
+		}
+	}
+}
+
+#[cfg(all(
+	feature = "bytemuck",
+	not(any(feature = "bincode", feature = "speedy", feature = "postcard", feature = "json", feature = "rkyv"))
+))]
 mod primitives {
 	use super::{ViaductDeserialize, ViaductSerialize};
 
@@ -0,0 +1,45 @@
+use crate::{ViaductDeserialize, ViaductSerialize, ViaductTx};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// A message queued for sending by the background task started by [`ViaductTx::into_async_sink`].
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum OutgoingMessage<RpcTx> {
+	/// An RPC to send to the peer process.
+	Rpc(RpcTx),
+}
+
+impl<RpcTx, RequestTx, RpcRx, RequestRx> ViaductTx<RpcTx, RequestTx, RpcRx, RequestRx>
+where
+	RpcTx: ViaductSerialize + Send + 'static,
+	RequestTx: ViaductSerialize + Send + 'static,
+	RpcRx: ViaductDeserialize + Send + 'static,
+	RequestRx: ViaductDeserialize + Send + 'static,
+{
+	/// Spawns a background task which drains a bounded channel of [`OutgoingMessage`]s into the viaduct, so that async tasks can send messages without blocking on the pipe.
+	///
+	/// Sending to the returned [`Sender`](tokio::sync::mpsc::Sender) waits for space in the channel once `capacity` messages are queued, which applies backpressure to producers if the peer falls behind.
+	///
+	/// The task stops once every sender has been dropped and the queue has been drained, or if writing to the pipe fails, in which case the error is returned from the [`JoinHandle`](tokio::task::JoinHandle).
+	///
+	/// Requires the `tokio` feature, and must be called from within a Tokio runtime.
+	///
+	/// # Panics
+	///
+	/// This function will panic if `capacity` is zero.
+	pub fn into_async_sink(self, capacity: usize) -> (mpsc::Sender<OutgoingMessage<RpcTx>>, JoinHandle<Result<(), std::io::Error>>) {
+		let (queue_tx, mut queue_rx) = mpsc::channel(capacity);
+
+		// Writing to the pipe blocks, so drain the queue on the blocking thread pool
+		let task = tokio::task::spawn_blocking(move || {
+			while let Some(message) = queue_rx.blocking_recv() {
+				match message {
+					OutgoingMessage::Rpc(rpc) => self.rpc(rpc)?,
+				}
+			}
+			Ok(())
+		});
+
+		(queue_tx, task)
+	}
+}
@@ -0,0 +1,88 @@
+use crate::{
+	capabilities::{Capabilities, Capability},
+	os::RawPipe,
+};
+use interprocess::unnamed_pipe::{UnnamedPipeReader, UnnamedPipeWriter};
+use std::{
+	net::{TcpListener, TcpStream, ToSocketAddrs},
+	os::unix::io::IntoRawFd,
+	time::{Duration, Instant},
+};
+
+/// The capabilities supported over TCP, which can't carry file descriptors.
+pub(super) const CAPABILITIES: Capabilities = Capabilities::LOCAL.without(Capability::HandlePassing);
+
+/// Binds to `addr` and waits for the peer process to connect.
+pub(super) fn accept<A: ToSocketAddrs>(addr: A, deadline: Option<Instant>) -> Result<(UnnamedPipeWriter, UnnamedPipeReader), std::io::Error> {
+	let listener = TcpListener::bind(addr)?;
+
+	let stream = match deadline {
+		None => listener.accept()?.0,
+
+		Some(deadline) => {
+			listener.set_nonblocking(true)?;
+			loop {
+				match listener.accept() {
+					Ok((stream, _)) => {
+						// Some platforms pass the listener's non-blocking mode on to the streams it accepts
+						stream.set_nonblocking(false)?;
+						break stream;
+					}
+
+					Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+						if Instant::now() >= deadline {
+							return Err(timed_out());
+						}
+						std::thread::sleep(Duration::from_millis(10));
+					}
+
+					Err(err) => return Err(err),
+				}
+			}
+		}
+	};
+
+	into_pipes(stream)
+}
+
+/// Connects to the peer process listening on `addr`.
+pub(super) fn connect<A: ToSocketAddrs>(addr: A, deadline: Option<Instant>) -> Result<(UnnamedPipeWriter, UnnamedPipeReader), std::io::Error> {
+	let Some(deadline) = deadline else {
+		return into_pipes(TcpStream::connect(addr)?);
+	};
+
+	// Try each address in turn, like `TcpStream::connect` does
+	let mut last_err = None;
+	for addr in addr.to_socket_addrs()? {
+		let timeout = deadline.saturating_duration_since(Instant::now());
+		if timeout.is_zero() {
+			return Err(timed_out());
+		}
+
+		match TcpStream::connect_timeout(&addr, timeout) {
+			Ok(stream) => return into_pipes(stream),
+			Err(err) => last_err = Some(err),
+		}
+	}
+	Err(last_err.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Could not resolve to any addresses")))
+}
+
+fn timed_out() -> std::io::Error {
+	std::io::Error::new(std::io::ErrorKind::TimedOut, "Timed out waiting for the peer process to connect")
+}
+
+/// Splits `stream` into a writer and a reader.
+///
+/// Sockets can be written to, read from and polled just like pipes on Unix, so the rest of the viaduct doesn't need to know the difference.
+fn into_pipes(stream: TcpStream) -> Result<(UnnamedPipeWriter, UnnamedPipeReader), std::io::Error> {
+	// Packets are already written in as few writes as possible, so don't hold them back waiting for more
+	stream.set_nodelay(true)?;
+
+	let reader = stream.try_clone()?;
+	Ok(unsafe {
+		(
+			UnnamedPipeWriter::from_raw(stream.into_raw_fd()),
+			UnnamedPipeReader::from_raw(reader.into_raw_fd()),
+		)
+	})
+}
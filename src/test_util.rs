@@ -0,0 +1,266 @@
+//! Utilities for testing code that uses Viaduct.
+//!
+//! Requires the `test-util` feature.
+
+use crate::{
+	channel, decompress, Capabilities, PipeReader, PipeSink, Viaduct, ViaductDeserialize, ViaductOptions, ViaductSerialize, BATCH, COMPRESSED,
+	GOODBYE, NONE_RESPONSE, REQUEST, REQUEST_WITH_CONTEXT, REQUEST_WITH_PRIORITY, RPC, SOME_RESPONSE, STREAM_CHUNK, STREAM_END, WINDOWED_RPC,
+};
+use parking_lot::Mutex;
+use std::{io::Write, mem::size_of, sync::Arc};
+use uuid::Uuid;
+
+/// Returns the number of handles (file descriptors on Unix) that are currently open in this process.
+#[cfg(unix)]
+pub fn open_handles() -> Result<usize, std::io::Error> {
+	// Listing the directory opens a file descriptor itself, but it does so consistently for every call
+	let dir = std::fs::read_dir("/proc/self/fd").or_else(|_| std::fs::read_dir("/dev/fd"))?;
+	Ok(dir.count())
+}
+
+/// Returns the number of handles (file descriptors on Unix) that are currently open in this process.
+#[cfg(windows)]
+pub fn open_handles() -> Result<usize, std::io::Error> {
+	use windows::Win32::System::Threading::{GetCurrentProcess, GetProcessHandleCount};
+
+	let mut count = 0;
+	if unsafe { GetProcessHandleCount(GetCurrentProcess(), &mut count) }.as_bool() {
+		Ok(count as usize)
+	} else {
+		Err(std::io::Error::last_os_error())
+	}
+}
+
+/// Runs `f` and asserts that the number of handles open in this process is the same before and after.
+///
+/// This is useful for checking that creating and dropping a viaduct doesn't leak any pipe handles.
+///
+/// Note that handles opened or closed by other threads while `f` is running will affect the result, so avoid doing so concurrently.
+///
+/// # Panics
+///
+/// This function will panic if the number of open handles changed, or if they couldn't be counted.
+///
+/// # Example
+///
+/// ```no_run
+/// # use viaduct::{ViaductParent, Never, test_util::assert_no_handle_leaks};
+/// # use std::process::Command;
+/// assert_no_handle_leaks(|| {
+///     let (viaduct, mut child) = ViaductParent::<Never, Never, Never, Never>::new(Command::new("child.exe"))
+///         .unwrap()
+///         .build()
+///         .unwrap();
+///
+///     drop(viaduct);
+///     child.wait().unwrap();
+/// });
+/// ```
+pub fn assert_no_handle_leaks<R>(f: impl FnOnce() -> R) -> R {
+	let before = open_handles().expect("Failed to count open handles");
+	let ret = f();
+	let after = open_handles().expect("Failed to count open handles");
+	assert_eq!(before, after, "{} handle(s) were leaked", after as isize - before as isize);
+	ret
+}
+
+/// An in-memory pipe that a viaduct created by [`in_memory`] writes to.
+///
+/// Clones share the same buffer.
+#[derive(Clone, Default)]
+pub struct MemoryPipe(Arc<Mutex<Vec<u8>>>);
+impl MemoryPipe {
+	/// Takes everything that has been written so far, leaving the pipe empty.
+	pub fn take(&self) -> Vec<u8> {
+		std::mem::take(&mut *self.0.lock())
+	}
+
+	/// Takes everything that has been written so far, and decodes it into the packets that were sent.
+	///
+	/// `Response` is the type that requests were responded to with.
+	///
+	/// # Panics
+	///
+	/// This function will panic if the packets can't be decoded, or if a packet was only partly written.
+	pub fn decode<Rpc, Request, Response>(&self) -> Vec<Sent<Rpc, Request, Response>>
+	where
+		Rpc: ViaductDeserialize,
+		Request: ViaductDeserialize,
+		Response: ViaductDeserialize,
+	{
+		let bytes = self.take();
+		let mut bytes = bytes.as_slice();
+
+		// Copied out so that it's aligned like the buffers the event loop deserializes from
+		fn read_len_prefixed(bytes: &mut &[u8]) -> Vec<u8> {
+			let (len, rest) = bytes.split_at(size_of::<u64>());
+			let len = u64::from_ne_bytes(len.try_into().unwrap()) as usize;
+			let (payload, rest) = rest.split_at(len);
+			*bytes = rest;
+			payload.to_vec()
+		}
+
+		let mut sent = Vec::new();
+		while let Some((&packet_type, rest)) = bytes.split_first() {
+			bytes = rest;
+
+			// Only a packet's main payload is compressed, not its other length-prefixed parts
+			let compressed = packet_type & COMPRESSED != 0;
+			let packet_type = packet_type & !COMPRESSED;
+			let read_payload = |bytes: &mut &[u8]| {
+				let mut payload = read_len_prefixed(bytes);
+				if compressed {
+					decompress(&mut payload, None).expect("Failed to decompress payload");
+				}
+				payload
+			};
+
+			let packet = match packet_type {
+				RPC | WINDOWED_RPC => {
+					if packet_type == WINDOWED_RPC {
+						bytes = &bytes[1..];
+					}
+					Sent::Rpc(Rpc::from_pipeable(&read_payload(&mut bytes)).expect("Failed to deserialize RPC"))
+				}
+
+				BATCH => {
+					// Each RPC in the batch is preceded by its length, just like a payload
+					let batch = read_payload(&mut bytes);
+					let mut batch = batch.as_slice();
+					while !batch.is_empty() {
+						sent.push(Sent::Rpc(
+							Rpc::from_pipeable(&read_len_prefixed(&mut batch)).expect("Failed to deserialize RPC"),
+						));
+					}
+					continue;
+				}
+
+				REQUEST | REQUEST_WITH_CONTEXT | REQUEST_WITH_PRIORITY => {
+					if packet_type == REQUEST_WITH_PRIORITY {
+						bytes = &bytes[1..];
+					}
+					bytes = &bytes[16..];
+					if packet_type == REQUEST_WITH_CONTEXT {
+						read_len_prefixed(&mut bytes);
+					}
+					Sent::Request(Request::from_pipeable(&read_payload(&mut bytes)).expect("Failed to deserialize request"))
+				}
+
+				SOME_RESPONSE => {
+					bytes = &bytes[16..];
+					Sent::Response(Some(
+						Response::from_pipeable(&read_payload(&mut bytes)).expect("Failed to deserialize response"),
+					))
+				}
+
+				NONE_RESPONSE => {
+					bytes = &bytes[16..];
+					Sent::Response(None)
+				}
+
+				STREAM_CHUNK => {
+					bytes = &bytes[16..];
+					Sent::StreamChunk(Response::from_pipeable(&read_payload(&mut bytes)).expect("Failed to deserialize response chunk"))
+				}
+
+				STREAM_END => {
+					bytes = &bytes[16..];
+					Sent::StreamEnd
+				}
+
+				GOODBYE => Sent::Goodbye,
+
+				_ => panic!("Unexpected packet type {packet_type} written to in-memory pipe"),
+			};
+			sent.push(packet);
+		}
+		sent
+	}
+}
+impl Write for MemoryPipe {
+	#[inline]
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	#[inline]
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/// A packet that was written to a [`MemoryPipe`], as decoded by [`MemoryPipe::decode`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Sent<Rpc, Request, Response> {
+	/// An RPC was sent, on its own or as part of a batch (see [`ViaductTx::rpc_batch`](crate::ViaductTx::rpc_batch)).
+	Rpc(Rpc),
+
+	/// A request was sent.
+	Request(Request),
+
+	/// A request was responded to, or its responder was dropped without responding.
+	Response(Option<Response>),
+
+	/// A chunk of a streamed response was sent (see [`ViaductRequestResponder::respond_stream`](crate::ViaductRequestResponder::respond_stream)).
+	StreamChunk(Response),
+
+	/// A streamed response was ended.
+	StreamEnd,
+
+	/// The viaduct was closed with [`ViaductTx::close`](crate::ViaductTx::close).
+	Goodbye,
+}
+
+/// Creates a viaduct that isn't connected to a peer process, for testing event handlers with [`ViaductRx::run_from_reader`](crate::ViaductRx::run_from_reader).
+///
+/// Everything sent over the viaduct - RPCs, requests and responses - is written to the returned [`MemoryPipe`]. Nothing is ever received in return, so requests sent over the viaduct will never get a response; use [`ViaductTx::request_timeout`](crate::ViaductTx::request_timeout) if your event handler sends any.
+///
+/// The viaduct behaves as if the peer supports every capability, but doesn't use any options that change how packets are framed.
+pub fn in_memory<RpcTx, RequestTx, RpcRx, RequestRx>() -> (Viaduct<RpcTx, RequestTx, RpcRx, RequestRx>, MemoryPipe)
+where
+	RpcTx: ViaductSerialize,
+	RequestTx: ViaductSerialize,
+	RpcRx: ViaductDeserialize,
+	RequestRx: ViaductDeserialize,
+{
+	let pipe = MemoryPipe::default();
+	let viaduct = channel(
+		PipeSink::Writer(Box::new(pipe.clone())),
+		PipeReader::Reader(Box::new(std::io::empty())),
+		None,
+		Capabilities::LOCAL,
+		ViaductOptions::default(),
+	);
+	(viaduct, pipe)
+}
+
+/// Encodes an RPC as the peer process would send it, for feeding into [`ViaductRx::run_from_reader`](crate::ViaductRx::run_from_reader).
+///
+/// # Panics
+///
+/// This function will panic if the RPC fails to serialize.
+pub fn rpc_frame(rpc: &impl ViaductSerialize) -> Vec<u8> {
+	let mut frame = vec![RPC];
+	write_payload(&mut frame, rpc);
+	frame
+}
+
+/// Encodes a request as the peer process would send it, for feeding into [`ViaductRx::run_from_reader`](crate::ViaductRx::run_from_reader).
+///
+/// # Panics
+///
+/// This function will panic if the request fails to serialize.
+pub fn request_frame(request: &impl ViaductSerialize) -> Vec<u8> {
+	let mut frame = vec![REQUEST];
+	frame.extend_from_slice(Uuid::new_v4().as_bytes());
+	write_payload(&mut frame, request);
+	frame
+}
+
+fn write_payload(frame: &mut Vec<u8>, data: &impl ViaductSerialize) {
+	let mut payload = Vec::new();
+	data.to_pipeable(&mut payload).expect("Failed to serialize test packet");
+	frame.extend_from_slice(&u64::to_ne_bytes(payload.len() as _));
+	frame.extend_from_slice(&payload);
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+#[cfg(feature = "timing")]
+use std::time::Instant;
+
+/// Cumulative time a viaduct has spent in each stage of sending and receiving packets.
+///
+/// Serialization and deserialization are measured separately from the time spent writing to and reading from the pipe, so that you can tell whether your latency comes from your codec or from the size of your messages.
+///
+/// See [`ViaductTx::timings`](crate::ViaductTx::timings).
+#[cfg(feature = "timing")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ViaductTimings {
+	/// Time spent serializing outgoing RPCs, requests and responses.
+	pub serialize: Duration,
+
+	/// Time spent writing outgoing packets to the pipe.
+	pub write: Duration,
+
+	/// Time spent reading incoming packets from the pipe, not counting time spent waiting for a packet to arrive.
+	pub read: Duration,
+
+	/// Time spent deserializing incoming RPCs, requests and responses.
+	pub deserialize: Duration,
+}
+
+/// Records [`ViaductTimings`] for a viaduct. Does nothing unless the `timing` feature is enabled.
+#[derive(Default)]
+pub(super) struct TimingRecorder {
+	#[cfg(feature = "timing")]
+	timings: parking_lot::Mutex<ViaductTimings>,
+}
+impl TimingRecorder {
+	#[cfg(feature = "timing")]
+	#[inline]
+	pub(super) fn get(&self) -> ViaductTimings {
+		*self.timings.lock()
+	}
+
+	#[inline]
+	pub(super) fn record_send(&self, serialize: Duration, write: Duration) {
+		#[cfg(feature = "timing")]
+		{
+			let mut timings = self.timings.lock();
+			timings.serialize += serialize;
+			timings.write += write;
+
+			#[cfg(feature = "tracing")]
+			tracing::trace!(?serialize, ?write, "viaduct packet sent");
+		}
+
+		#[cfg(not(feature = "timing"))]
+		let _ = (serialize, write);
+	}
+
+	#[inline]
+	pub(super) fn record_read(&self, read: Duration) {
+		#[cfg(feature = "timing")]
+		{
+			self.timings.lock().read += read;
+
+			#[cfg(feature = "tracing")]
+			tracing::trace!(?read, "viaduct packet read");
+		}
+
+		#[cfg(not(feature = "timing"))]
+		let _ = read;
+	}
+
+	#[inline]
+	pub(super) fn record_deserialize(&self, deserialize: Duration) {
+		#[cfg(feature = "timing")]
+		{
+			self.timings.lock().deserialize += deserialize;
+
+			#[cfg(feature = "tracing")]
+			tracing::trace!(?deserialize, "viaduct packet deserialized");
+		}
+
+		#[cfg(not(feature = "timing"))]
+		let _ = deserialize;
+	}
+}
+
+/// Measures how long a stage of sending or receiving a packet takes. Always measures zero unless the `timing` feature is enabled.
+pub(super) struct Stopwatch {
+	#[cfg(feature = "timing")]
+	start: Instant,
+}
+impl Stopwatch {
+	#[inline]
+	pub(super) fn start() -> Self {
+		Self {
+			#[cfg(feature = "timing")]
+			start: Instant::now(),
+		}
+	}
+
+	#[inline]
+	pub(super) fn elapsed(&self) -> Duration {
+		#[cfg(feature = "timing")]
+		return self.start.elapsed();
+
+		#[cfg(not(feature = "timing"))]
+		return Duration::ZERO;
+	}
+}
+
+/// When a packet was sent, according to the sender's monotonic clock.
+///
+/// The clock is shared by every process on the machine, so comparing a timestamp to [`Timestamp::now`] in the receiving process gives the time the packet spent in transit, including any time it spent queued in the pipe or waiting for the event loop.
+///
+/// See [`ViaductParent::timestamps`](crate::ViaductParent::timestamps) and [`ViaductRx::run_timestamped`](crate::ViaductRx::run_timestamped).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+impl Timestamp {
+	/// Reads the monotonic clock.
+	#[inline]
+	pub fn now() -> Self {
+		Self(crate::os::monotonic_nanos())
+	}
+
+	#[inline]
+	pub(super) const fn from_nanos(nanos: u64) -> Self {
+		Self(nanos)
+	}
+
+	/// Returns the reading of the monotonic clock, in nanoseconds.
+	///
+	/// The clock's starting point is unspecified, so this is only meaningful in comparison to other timestamps.
+	#[inline]
+	pub const fn as_nanos(self) -> u64 {
+		self.0
+	}
+
+	/// Returns the time elapsed since this timestamp, or zero if it's in the future.
+	#[inline]
+	pub fn elapsed(self) -> Duration {
+		Timestamp::now().duration_since(self)
+	}
+
+	/// Returns the time elapsed from `earlier` to this timestamp, or zero if `earlier` is later.
+	#[inline]
+	pub const fn duration_since(self, earlier: Timestamp) -> Duration {
+		Duration::from_nanos(self.0.saturating_sub(earlier.0))
+	}
+}